@@ -0,0 +1,99 @@
+use log::{error, info, warn};
+use serde::Deserialize;
+use std::io;
+use std::time::Duration;
+use sysinfo::{Pid, System};
+
+// Что делать с процессом, попавшим под правило. Всё, кроме notify, требует ENABLE_ACTIONS=true
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    #[default]
+    Notify,
+    Renice,
+    Kill,
+}
+
+pub const DEFAULT_NICE: i32 = 10;
+pub const DEFAULT_KILL_GRACE_SECONDS: u64 = 10;
+
+#[derive(Clone, Copy, Debug)]
+pub struct RuleAction {
+    pub action: Action,
+    pub nice: i32,
+    pub kill_grace: Duration,
+}
+
+fn last_error(what: &str, pid: Pid) -> io::Error {
+    let e = io::Error::last_os_error();
+    if e.kind() == io::ErrorKind::PermissionDenied {
+        error!(
+            event = "action_failed",
+            pid = pid.as_u32();
+            "{} of PID {} DENIED: {} (run as root or grant CAP_SYS_NICE/CAP_KILL)", what, pid, e
+        );
+    } else {
+        error!(event = "action_failed", pid = pid.as_u32(); "{} of PID {} failed: {}", what, pid, e);
+    }
+    e
+}
+
+fn renice(pid: Pid, nice: i32) -> io::Result<()> {
+    // SAFETY: setpriority не работает с памятью, только с PID и числом
+    let rc = unsafe { libc::setpriority(libc::PRIO_PROCESS, pid.as_u32() as libc::id_t, nice) };
+    if rc == 0 {
+        Ok(())
+    } else {
+        Err(last_error("Renice", pid))
+    }
+}
+
+fn signal(pid: Pid, signal: libc::c_int, name: &str) -> io::Result<()> {
+    // SAFETY: kill не работает с памятью, только с PID и номером сигнала
+    let rc = unsafe { libc::kill(pid.as_u32() as libc::pid_t, signal) };
+    if rc == 0 {
+        Ok(())
+    } else {
+        Err(last_error(name, pid))
+    }
+}
+
+// Выполняет действие и возвращает строку для уведомления. SIGKILL после SIGTERM шлётся
+// в фоне, если процесс с тем же временем старта пережил grace-период.
+pub fn perform(rule_action: &RuleAction, pid: Pid, start_time: u64) -> Option<String> {
+    let (description, result) = match rule_action.action {
+        Action::Notify => return None,
+        Action::Renice => (
+            format!("renice to {}", rule_action.nice),
+            renice(pid, rule_action.nice),
+        ),
+        Action::Kill => {
+            let result = signal(pid, libc::SIGTERM, "SIGTERM");
+            if result.is_ok() {
+                let grace = rule_action.kill_grace;
+                tokio::spawn(async move {
+                    tokio::time::sleep(grace).await;
+                    let mut sys = System::new();
+                    let still_running = sys.refresh_process(pid)
+                        && sys.process(pid).is_some_and(|process| process.start_time() == start_time);
+                    if still_running {
+                        warn!(event = "action", pid = pid.as_u32(); "PID {} survived SIGTERM, sending SIGKILL", pid);
+                        let _ = signal(pid, libc::SIGKILL, "SIGKILL");
+                    }
+                });
+            }
+            (
+                format!("kill (SIGTERM, SIGKILL after {}s)", rule_action.kill_grace.as_secs()),
+                result,
+            )
+        }
+    };
+
+    match result {
+        Ok(()) => {
+            info!(event = "action", pid = pid.as_u32(); "Applied {} to PID {}", description, pid);
+            Some(format!("{} — ok", description))
+        }
+        Err(e) => Some(format!("{} — failed: {}", description, e)),
+    }
+}
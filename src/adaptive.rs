@@ -0,0 +1,35 @@
+// ADAPTIVE_INTERVAL: чем выше общий CPU, тем чаще проверки. Цель линейно идёт от
+// MAX_INTERVAL при простое до MIN_INTERVAL при 100%. К более частым проверкам переходим
+// сразу, а обратно — на четверть за проверку, чтобы короткое затишье посреди инцидента
+// не возвращало редкие проверки
+const GROWTH: f64 = 1.25;
+
+pub struct AdaptiveInterval {
+    min: f64,
+    max: f64,
+    current: f64,
+}
+
+impl AdaptiveInterval {
+    pub fn new(min: f64, max: f64) -> Result<Self, String> {
+        if !(min > 0.0 && min <= max) {
+            return Err(format!(
+                "MIN_INTERVAL ({}) must be positive and not above MAX_INTERVAL ({})",
+                min, max
+            ));
+        }
+        Ok(AdaptiveInterval { min, max, current: max })
+    }
+
+    // Интервал до следующей проверки в секундах по общему CPU системы (0–100%)
+    pub fn next(&mut self, system_cpu: f32) -> f64 {
+        let load = (f64::from(system_cpu) / 100.0).clamp(0.0, 1.0);
+        let target = self.max - (self.max - self.min) * load;
+        self.current = if target < self.current {
+            target
+        } else {
+            (self.current * GROWTH).min(target)
+        };
+        self.current
+    }
+}
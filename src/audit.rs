@@ -0,0 +1,82 @@
+use cpu_watcher::{Alert, AlertKind, Severity};
+use log::warn;
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+// Почему оповещение не доставлено
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Undelivered {
+    SendFailed,
+    CircuitOpen,
+    QuietHours,
+    RateLimited,
+}
+
+#[derive(Serialize)]
+struct AuditRecord<'a> {
+    timestamp: String,
+    pid: u32,
+    name: &'a str,
+    cpu: f32,
+    memory_bytes: u64,
+    severity: Severity,
+    triggers: &'a [AlertKind],
+    cmdline: &'a str,
+    delivered: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<Undelivered>,
+}
+
+// AUDIT_LOG: по строке JSON на каждое оповещение о процессе, доставленное или нет. Файл только
+// дописывается и синхронизируется на диск после каждой пачки, чтобы сбой не съел хвост
+pub struct AuditLog {
+    path: PathBuf,
+    file: File,
+}
+
+impl AuditLog {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(AuditLog {
+            path: path.to_path_buf(),
+            file,
+        })
+    }
+
+    // Ошибка записи не останавливает проверки: пишется в лог, оповещения идут дальше
+    pub fn record(&mut self, alerts: &[Alert], reason: Option<Undelivered>) {
+        if alerts.is_empty() {
+            return;
+        }
+        if let Err(e) = self.write(alerts, reason) {
+            warn!(event = "audit_failed", error:% = e; "Cannot write audit log {}: {}", self.path.display(), e);
+        }
+    }
+
+    fn write(&mut self, alerts: &[Alert], reason: Option<Undelivered>) -> io::Result<()> {
+        let timestamp = chrono::Utc::now().to_rfc3339();
+        let mut lines = Vec::new();
+        for alert in alerts {
+            let record = AuditRecord {
+                timestamp: timestamp.clone(),
+                pid: alert.process.pid.as_u32(),
+                name: &alert.process.name,
+                cpu: alert.process.cpu_percent,
+                memory_bytes: alert.process.memory_bytes,
+                severity: alert.process.severity,
+                triggers: &alert.triggers,
+                cmdline: &alert.process.cmdline,
+                delivered: reason.is_none(),
+                reason,
+            };
+            serde_json::to_writer(&mut lines, &record)?;
+            lines.push(b'\n');
+        }
+        // Одна запись в файл на пачку: строки разных пачек не перемешаются
+        self.file.write_all(&lines)?;
+        self.file.sync_data()
+    }
+}
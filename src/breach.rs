@@ -0,0 +1,94 @@
+use std::collections::{HashMap, VecDeque};
+use sysinfo::Pid;
+
+// Результаты последних BREACH_WINDOW проверок CPU по каждому PID: был ли процесс выше порога
+pub struct BreachHistory {
+    count: usize,
+    window: usize,
+    samples: HashMap<Pid, VecDeque<bool>>,
+}
+
+impl BreachHistory {
+    pub fn new(count: usize, window: usize) -> Result<Self, String> {
+        if window == 0 || count == 0 || count > window {
+            return Err(format!(
+                "BREACH_COUNT ({}) must be between 1 and BREACH_WINDOW ({})",
+                count, window
+            ));
+        }
+        Ok(BreachHistory {
+            count,
+            window,
+            samples: HashMap::new(),
+        })
+    }
+
+    // Записывает результат проверки; true — процесс был выше порога хотя бы в count из
+    // последних window проверок. При 1 из 1 это просто текущий результат
+    pub fn record(&mut self, pid: Pid, breached: bool) -> bool {
+        let samples = self.samples.entry(pid).or_default();
+        if samples.len() == self.window {
+            samples.pop_front();
+        }
+        samples.push_back(breached);
+        samples.iter().filter(|breached| **breached).count() >= self.count
+    }
+
+    pub fn retain(&mut self, mut alive: impl FnMut(&Pid) -> bool) {
+        self.samples.retain(|pid, _| alive(pid));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record_all(history: &mut BreachHistory, pid: Pid, samples: &[bool]) -> Vec<bool> {
+        samples.iter().map(|breached| history.record(pid, *breached)).collect()
+    }
+
+    #[test]
+    fn one_of_one_follows_current_check() {
+        let mut history = BreachHistory::new(1, 1).unwrap();
+        let pid = Pid::from_u32(100);
+        assert_eq!(record_all(&mut history, pid, &[true, false, true]), [true, false, true]);
+    }
+
+    #[test]
+    fn non_consecutive_breaches_fire() {
+        let mut history = BreachHistory::new(3, 5).unwrap();
+        let pid = Pid::from_u32(100);
+        // Процесс скачет вокруг порога: третье превышение из пяти проверок срабатывает
+        assert_eq!(
+            record_all(&mut history, pid, &[true, false, true, false, true]),
+            [false, false, false, false, true]
+        );
+    }
+
+    #[test]
+    fn old_breaches_age_out() {
+        let mut history = BreachHistory::new(2, 3).unwrap();
+        let pid = Pid::from_u32(100);
+        assert_eq!(record_all(&mut history, pid, &[true, true]), [false, true]);
+        // Окно [true, true, false], затем [true, false, false] и [false, false, true]
+        assert_eq!(record_all(&mut history, pid, &[false, false, true]), [true, false, false]);
+        assert_eq!(record_all(&mut history, pid, &[true]), [true]);
+    }
+
+    #[test]
+    fn windows_are_per_pid() {
+        let mut history = BreachHistory::new(2, 2).unwrap();
+        assert!(!history.record(Pid::from_u32(100), true));
+        assert!(!history.record(Pid::from_u32(200), true));
+        assert!(history.record(Pid::from_u32(100), true));
+        history.retain(|pid| *pid != Pid::from_u32(200));
+        assert!(!history.record(Pid::from_u32(200), true));
+    }
+
+    #[test]
+    fn rejects_count_outside_window() {
+        assert!(BreachHistory::new(0, 3).is_err());
+        assert!(BreachHistory::new(4, 3).is_err());
+        assert!(BreachHistory::new(1, 0).is_err());
+    }
+}
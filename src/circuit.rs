@@ -0,0 +1,97 @@
+use async_trait::async_trait;
+use cpu_watcher::{Alert, Notifier, SendResult, WatcherError};
+use log::{debug, info, warn};
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[derive(Default)]
+struct State {
+    failures: u32,
+    // Когда предохранитель сработал; None — отправки идут как обычно
+    opened_at: Option<Instant>,
+}
+
+// Предохранитель (CIRCUIT_FAILURE_THRESHOLD): после стольких временных сбоев подряд отправки
+// пропускаются на CIRCUIT_COOLDOWN_SECONDS, чтобы не ждать таймаутов на каждой проверке и не
+// долбить лежащий сервис. Потом одна пробная отправка: удалась — канал снова открыт, нет —
+// снова пауза. Отказ сервиса (Ok(false)) значит, что он отвечает, и счётчик сбрасывает
+pub struct CircuitBreaker {
+    inner: Box<dyn Notifier>,
+    threshold: u32,
+    cooldown: Duration,
+    state: Mutex<State>,
+}
+
+impl CircuitBreaker {
+    pub fn new(inner: Box<dyn Notifier>, threshold: u32, cooldown: Duration) -> Self {
+        CircuitBreaker {
+            inner,
+            threshold,
+            cooldown,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    async fn guard<Fut: Future<Output = SendResult>>(&self, send: Fut) -> SendResult {
+        let probing = match self.state.lock() {
+            Ok(state) => match state.opened_at {
+                Some(opened_at) => {
+                    let elapsed = opened_at.elapsed();
+                    if elapsed < self.cooldown {
+                        let retry_in = self.cooldown - elapsed;
+                        debug!("Notifier circuit open, skipping send ({:?} left)", retry_in);
+                        return Err(WatcherError::CircuitOpen { retry_in });
+                    }
+                    true
+                }
+                None => false,
+            },
+            Err(_) => false,
+        };
+        if probing {
+            info!(event = "circuit_half_open"; "Notifier circuit half-open, sending a probe");
+        }
+
+        let result = send.await;
+        let Ok(mut state) = self.state.lock() else {
+            return result;
+        };
+        match &result {
+            Err(e) => {
+                state.failures += 1;
+                if probing {
+                    state.opened_at = Some(Instant::now());
+                    warn!(event = "circuit_open", error:% = e; "Notifier probe failed ({}), circuit open again for {}s", e, self.cooldown.as_secs());
+                } else if state.failures >= self.threshold && state.opened_at.is_none() {
+                    state.opened_at = Some(Instant::now());
+                    warn!(
+                        event = "circuit_open",
+                        failures = state.failures,
+                        error:% = e;
+                        "{} consecutive send failures (last: {}), circuit open: sends paused for {}s",
+                        state.failures, e, self.cooldown.as_secs()
+                    );
+                }
+            }
+            Ok(_) => {
+                if state.opened_at.take().is_some() {
+                    info!(event = "circuit_closed"; "Notifier recovered, circuit closed");
+                }
+                state.failures = 0;
+            }
+        }
+        result
+    }
+}
+
+#[async_trait]
+impl Notifier for CircuitBreaker {
+    async fn send(&self, text: &str) -> SendResult {
+        self.guard(self.inner.send(text)).await
+    }
+
+    async fn send_alerts(&self, alerts: &[Alert], text: &str) -> SendResult {
+        self.guard(self.inner.send_alerts(alerts, text)).await
+    }
+}
@@ -0,0 +1,70 @@
+use std::time::{Instant, SystemTime};
+
+/// Источник текущего времени для кулдаунов и затяжных превышений. В программе —
+/// [`SystemClock`]; в тестах его подменяют часами, которые двигают вручную.
+pub trait Clock: Send + Sync {
+    /// Настенное время: по нему считается возраст процессов.
+    fn now(&self) -> SystemTime;
+    /// Монотонное время для кулдаунов, затяжных превышений и скоростей: не прыгает,
+    /// когда NTP или администратор переводит системные часы.
+    fn monotonic(&self) -> Instant;
+}
+
+/// Системные часы.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    fn monotonic(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+#[cfg(test)]
+pub(crate) use manual::ManualClock;
+
+#[cfg(test)]
+mod manual {
+    use super::Clock;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant, SystemTime};
+
+    /// Часы для тестов: стоят на месте, пока их не сдвинут.
+    pub(crate) struct ManualClock {
+        now: Mutex<(SystemTime, Instant)>,
+    }
+
+    impl ManualClock {
+        pub(crate) fn new() -> Arc<Self> {
+            Arc::new(ManualClock {
+                now: Mutex::new((SystemTime::now(), Instant::now())),
+            })
+        }
+
+        /// Сдвигает вперёд и настенное, и монотонное время.
+        pub(crate) fn advance(&self, seconds: u64) {
+            let mut now = self.now.lock().unwrap();
+            now.0 += Duration::from_secs(seconds);
+            now.1 += Duration::from_secs(seconds);
+        }
+
+        /// Перевод системных часов: меняется только настенное время.
+        pub(crate) fn set_wall(&self, wall: SystemTime) {
+            self.now.lock().unwrap().0 = wall;
+        }
+    }
+
+    impl Clock for ManualClock {
+        fn now(&self) -> SystemTime {
+            self.now.lock().unwrap().0
+        }
+
+        fn monotonic(&self) -> Instant {
+            self.now.lock().unwrap().1
+        }
+    }
+}
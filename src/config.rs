@@ -0,0 +1,1114 @@
+use crate::actions::Action;
+use crate::error::WatcherError;
+use crate::markup::ParseMode;
+use crate::notify::{self, Delivery, HttpOptions, IpFamily};
+use crate::{AlertKind, Severity};
+use chrono::{DateTime, FixedOffset, Local, NaiveTime, Utc};
+use log::{info, warn};
+use serde::{Deserialize, Deserializer};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+const MIB: u64 = 1024 * 1024;
+
+/// Размер в байтах: число и необязательная единица, `500MiB`, `1.5GB`, `64kB`. SI-единицы
+/// (`kB`, `MB`, `GB`, `TB`) — степени 1000, IEC (`KiB`, `MiB`, `GiB`, `TiB`) — степени 1024,
+/// `B` — байты; регистр приставки не важен. Число без единицы — в `bare_unit` байт. С `rate`
+/// допускается хвост `/s` (`200kB/s`). Неоднозначные `M`, `Mb` или `MBit` — ошибка: непонятно,
+/// мегабайты это, мебибайты или мегабиты.
+pub fn parse_bytes(value: &str, bare_unit: u64, rate: bool) -> Result<u64, String> {
+    let value = value.trim();
+    let amount = match value.strip_suffix("/s") {
+        Some(amount) if rate => amount.trim_end(),
+        _ => value,
+    };
+    let split = amount.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(amount.len());
+    let (number, unit) = amount.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("{:?} is not a number with an optional unit", value))?;
+    let multiplier = match unit.trim() {
+        "" => bare_unit,
+        unit => byte_unit(unit).ok_or_else(|| {
+            format!(
+                "unknown or ambiguous unit {:?}: use B, kB, MB, GB, TB (powers of 1000) or KiB, MiB, GiB, TiB (powers of 1024)",
+                unit
+            )
+        })?,
+    };
+    let bytes = number * multiplier as f64;
+    if !(bytes.is_finite() && bytes >= 1.0) {
+        return Err(format!("{:?} must be at least one byte", value));
+    }
+    Ok(bytes as u64)
+}
+
+// Байт в единице; "B" обязательно заглавная, иначе это могут быть биты
+fn byte_unit(unit: &str) -> Option<u64> {
+    let (prefix, base) = match unit.strip_suffix("iB") {
+        Some(prefix) => (prefix, 1024u64),
+        None => (unit.strip_suffix('B')?, 1000),
+    };
+    let power = match prefix.to_ascii_lowercase().as_str() {
+        "" if base == 1000 => 0,
+        "k" => 1,
+        "m" => 2,
+        "g" => 3,
+        "t" => 4,
+        _ => return None,
+    };
+    Some(base.pow(power))
+}
+
+// Порог памяти: абсолютный (MEMORY_THRESHOLD=512 в MiB или 2GiB) или доля от всей RAM
+// (MEMORY_THRESHOLD=25%)
+#[derive(Clone, Copy, Debug)]
+pub enum MemoryThreshold {
+    Bytes(u64),
+    Percent(f32),
+}
+
+impl MemoryThreshold {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let value = value.trim();
+        if let Some(percent) = value.strip_suffix('%') {
+            match percent.trim().parse::<f32>() {
+                Ok(percent) if percent > 0.0 && percent <= 100.0 => Ok(MemoryThreshold::Percent(percent)),
+                _ => Err(format!("{:?} is not a percentage between 0 and 100", value)),
+            }
+        } else {
+            parse_bytes(value, MIB, false).map(MemoryThreshold::Bytes)
+        }
+    }
+
+    pub fn limit_bytes(&self, total_memory: u64) -> u64 {
+        match *self {
+            MemoryThreshold::Bytes(bytes) => bytes,
+            MemoryThreshold::Percent(percent) => (total_memory as f64 * percent as f64 / 100.0) as u64,
+        }
+    }
+}
+
+// Значение из файла, которое можно записать и числом, и строкой с единицей
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum NumberOrText {
+    Number(f64),
+    Text(String),
+}
+
+impl NumberOrText {
+    fn into_text(self) -> String {
+        match self {
+            NumberOrText::Number(n) => n.to_string(),
+            NumberOrText::Text(s) => s,
+        }
+    }
+}
+
+// В файле порог памяти можно задать и числом (MiB), и строкой ("25%", "2GiB")
+impl<'de> Deserialize<'de> for MemoryThreshold {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = NumberOrText::deserialize(deserializer)?.into_text();
+        MemoryThreshold::parse(&text)
+            .map_err(|e| serde::de::Error::custom(format!("invalid memory threshold: {}", e)))
+    }
+}
+
+// Пороги в байтах в секунду: числом (байты) или строкой с единицей ("200kB/s")
+fn byte_rate<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u64>, D::Error> {
+    let text = NumberOrText::deserialize(deserializer)?.into_text();
+    parse_bytes(&text, 1, true)
+        .map(Some)
+        .map_err(|e| serde::de::Error::custom(format!("invalid rate: {}", e)))
+}
+
+// Как трактовать CPU процесса: per-core — как отдаёт sysinfo (до 100% × число ядер),
+// normalized — делённым на число ядер (0–100%)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ThresholdMode {
+    #[default]
+    PerCore,
+    Normalized,
+}
+
+impl FromStr for ThresholdMode {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, ()> {
+        match value {
+            "per-core" => Ok(ThresholdMode::PerCore),
+            "normalized" => Ok(ThresholdMode::Normalized),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for ThresholdMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ThresholdMode::PerCore => write!(f, "per-core"),
+            ThresholdMode::Normalized => write!(f, "normalized"),
+        }
+    }
+}
+
+// По какому признаку считается кулдаун: pid — каждый процесс отдельно, cmdline — одинаковая
+// командная строка в любых PID, name — одинаковое имя процесса
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DedupBy {
+    #[default]
+    Pid,
+    Cmdline,
+    Name,
+}
+
+impl FromStr for DedupBy {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, ()> {
+        match value {
+            "pid" => Ok(DedupBy::Pid),
+            "cmdline" => Ok(DedupBy::Cmdline),
+            "name" => Ok(DedupBy::Name),
+            _ => Err(()),
+        }
+    }
+}
+
+// Куда идут сообщения: notify — в настроенные каналы, stdout-json — JSON Lines в stdout
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Output {
+    #[default]
+    Notify,
+    StdoutJson,
+}
+
+impl FromStr for Output {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, ()> {
+        match value {
+            "notify" => Ok(Output::Notify),
+            "stdout-json" => Ok(Output::StdoutJson),
+            _ => Err(()),
+        }
+    }
+}
+
+// Язык заголовков сообщений: ru — как было всегда, en — английский
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MessageLocale {
+    #[default]
+    Ru,
+    En,
+}
+
+impl FromStr for MessageLocale {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, ()> {
+        match value.to_lowercase().as_str() {
+            "ru" => Ok(MessageLocale::Ru),
+            "en" => Ok(MessageLocale::En),
+            _ => Err(()),
+        }
+    }
+}
+
+// Что обновлять у процессов на каждой проверке: minimal — только то, что нужно включённым
+// проверкам, full — всё, что умеет sysinfo (как раньше)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RefreshMode {
+    #[default]
+    Minimal,
+    Full,
+}
+
+impl FromStr for RefreshMode {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, ()> {
+        match value.to_lowercase().as_str() {
+            "minimal" => Ok(RefreshMode::Minimal),
+            "full" => Ok(RefreshMode::Full),
+            _ => Err(()),
+        }
+    }
+}
+
+// Канал доставки; используется в маршрутах по уровню оповещения (ROUTE_*)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    Telegram,
+    Discord,
+    Slack,
+    Webhook,
+    Socket,
+}
+
+impl FromStr for Backend {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, ()> {
+        match value.trim().to_lowercase().as_str() {
+            "telegram" => Ok(Backend::Telegram),
+            "discord" => Ok(Backend::Discord),
+            "slack" => Ok(Backend::Slack),
+            "webhook" => Ok(Backend::Webhook),
+            "socket" => Ok(Backend::Socket),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Backend::Telegram => write!(f, "telegram"),
+            Backend::Discord => write!(f, "discord"),
+            Backend::Slack => write!(f, "slack"),
+            Backend::Webhook => write!(f, "webhook"),
+            Backend::Socket => write!(f, "socket"),
+        }
+    }
+}
+
+// Тихие часы "22:00-07:00": окно может переходить через полночь; начало входит, конец — нет
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QuietHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl QuietHours {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+impl FromStr for QuietHours {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, String> {
+        let invalid = || format!("invalid quiet hours {:?}, expected HH:MM-HH:MM", value);
+        let (start, end) = value.split_once('-').ok_or_else(invalid)?;
+        let parse = |time: &str| NaiveTime::parse_from_str(time.trim(), "%H:%M").map_err(|_| invalid());
+        let hours = QuietHours {
+            start: parse(start)?,
+            end: parse(end)?,
+        };
+        if hours.start == hours.end {
+            return Err(invalid());
+        }
+        Ok(hours)
+    }
+}
+
+impl fmt::Display for QuietHours {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.start.format("%H:%M"), self.end.format("%H:%M"))
+    }
+}
+
+impl<'de> Deserialize<'de> for QuietHours {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+// Часовой пояс тихих часов: local — системный (учитывает TZ), UTC или смещение "+03:00"
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum QuietTimezone {
+    #[default]
+    Local,
+    Fixed(FixedOffset),
+}
+
+impl QuietTimezone {
+    pub fn time_of(&self, now: DateTime<Utc>) -> NaiveTime {
+        match self {
+            QuietTimezone::Local => now.with_timezone(&Local).time(),
+            QuietTimezone::Fixed(offset) => now.with_timezone(offset).time(),
+        }
+    }
+}
+
+impl FromStr for QuietTimezone {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, String> {
+        let value = value.trim();
+        if value.eq_ignore_ascii_case("local") {
+            return Ok(QuietTimezone::Local);
+        }
+        if value.eq_ignore_ascii_case("utc") || value == "Z" {
+            return Ok(QuietTimezone::Fixed(FixedOffset::east_opt(0).expect("zero offset is valid")));
+        }
+        value
+            .parse::<FixedOffset>()
+            .map(QuietTimezone::Fixed)
+            .map_err(|_| format!("invalid quiet hours timezone {:?}, expected local, UTC or +HH:MM", value))
+    }
+}
+
+impl<'de> Deserialize<'de> for QuietTimezone {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+// Правило с собственным порогом CPU: match — имя процесса или "re:<regex>" по имени/командной строке.
+// action срабатывает только при ENABLE_ACTIONS=true; nice и kill_grace_seconds — его параметры
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    pub name: Option<String>,
+    #[serde(rename = "match")]
+    pub pattern: String,
+    pub threshold: f32,
+    #[serde(default)]
+    pub action: Action,
+    pub nice: Option<i32>,
+    pub kill_grace_seconds: Option<u64>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub threshold: f32,
+    pub threshold_mode: ThresholdMode,
+    pub memory_threshold: Option<MemoryThreshold>,
+    /// Порог свопа процесса: MiB или доля всего свопа системы.
+    pub swap_threshold: Option<MemoryThreshold>,
+    pub sustained_seconds: u64,
+    pub breach_count: usize,
+    pub breach_window: usize,
+    pub max_runtime_seconds: Option<u64>,
+    /// Сколько секунд процесс может непрерывно провести в состоянии D (непрерываемый сон).
+    pub d_state_seconds: Option<u64>,
+    /// Сколько секунд CPU процесс может потратить с запуска (utime + stime).
+    pub cpu_time_budget: Option<u64>,
+    #[serde(deserialize_with = "byte_rate")]
+    pub disk_io_threshold: Option<u64>,
+    pub fd_threshold: Option<usize>,
+    pub thread_threshold: Option<usize>,
+    pub score_threshold: Option<f32>,
+    pub score_cpu_weight: f32,
+    pub score_memory_weight: f32,
+    pub zombie_threshold: Option<usize>,
+    pub restart_threshold: Option<f64>,
+    pub restart_window_seconds: u64,
+    #[serde(deserialize_with = "byte_rate")]
+    pub net_threshold: Option<u64>,
+    pub gpu_threshold: Option<f32>,
+    pub gpu_memory_threshold: Option<MemoryThreshold>,
+    pub warning_multiplier: f32,
+    pub critical_multiplier: f32,
+    pub min_process_age_seconds: u64,
+    /// Сколько миллисекунд CPU процесс должен потратить с прошлой проверки, чтобы
+    /// превышение порога CPU засчиталось; 0 — без нижней границы.
+    pub min_cpu_time_ms: u64,
+    pub aggregate_tree: bool,
+    /// Процессы (как `only_processes`), CPU которых суммируется с потомками, когда
+    /// `aggregate_tree` выключен.
+    pub aggregate_names: Vec<String>,
+    /// Проверять только процессы в состоянии R (выполняется) на момент проверки.
+    pub only_running: bool,
+    pub only_processes: Vec<String>,
+    pub ignore_processes: Vec<String>,
+    pub watch_users: Vec<String>,
+    pub ignore_users: Vec<String>,
+    pub check_interval: f64,
+    /// Сколько раз обновлять CPU процессов за интервал проверки; с порогом сравнивается среднее.
+    pub samples_per_interval: u32,
+    pub refresh_mode: RefreshMode,
+    /// Где смонтирован procfs, из которого читаются файлы процессов.
+    pub proc_path: PathBuf,
+    pub adaptive_interval: bool,
+    pub min_interval: f64,
+    pub max_interval: f64,
+    pub check_jitter_ms: u64,
+    pub warmup_ms: u64,
+    /// Сколько секунд после загрузки системы не оповещать.
+    pub boot_grace_seconds: u64,
+    pub cooldown_seconds: u64,
+    /// Кулдаун отдельных условий в секундах; для остальных — `cooldown_seconds`.
+    pub cooldowns: HashMap<AlertKind, u64>,
+    pub max_cooldown_seconds: u64,
+    pub heartbeat_seconds: u64,
+    /// Сообщать в канал о запуске (после прогрева) и о штатной остановке.
+    pub notify_on_start: bool,
+    pub digest_seconds: u64,
+    pub quiet_hours: Option<QuietHours>,
+    pub quiet_hours_tz: QuietTimezone,
+    pub critical_processes: Vec<String>,
+    pub dedup_by: DedupBy,
+    pub secrets_file: Option<PathBuf>,
+    pub bot_token: Option<String>,
+    pub chat_id: Option<String>,
+    pub telegram_parse_mode: Option<ParseMode>,
+    pub telegram_delivery: Delivery,
+    pub telegram_proxy: Option<String>,
+    pub telegram_api_base: String,
+    pub skip_token_check: bool,
+    pub http_pool_max_idle: Option<usize>,
+    pub http_pool_idle_timeout: Option<f64>,
+    pub http_connect_timeout: Option<f64>,
+    pub http_ip_family: IpFamily,
+    pub send_timeout_seconds: f64,
+    pub discord_webhook_url: Option<String>,
+    pub slack_webhook_url: Option<String>,
+    pub slack_channel: Option<String>,
+    pub webhook_url: Option<String>,
+    pub webhook_headers: Option<String>,
+    pub socket_path: Option<PathBuf>,
+    pub route_info: Vec<Backend>,
+    pub route_warning: Vec<Backend>,
+    pub route_critical: Vec<Backend>,
+    /// Запасной канал на случай, когда основной не доставил сообщение после всех повторов.
+    pub fallback_backend: Option<Backend>,
+    pub fallback_max_per_hour: usize,
+    /// Сколько временных сбоев отправки подряд размыкают предохранитель; 0 — без предохранителя.
+    pub circuit_failure_threshold: u32,
+    pub circuit_cooldown_seconds: u64,
+    pub send_max_retries: u32,
+    pub metrics_addr: Option<SocketAddr>,
+    pub health_addr: Option<SocketAddr>,
+    pub state_file: Option<PathBuf>,
+    pub audit_log: Option<PathBuf>,
+    pub max_batch_lines: usize,
+    pub max_alerts_per_minute: usize,
+    pub watch_pid: Option<u32>,
+    pub watch_pidfile: Option<PathBuf>,
+    pub alert_on_exit: bool,
+    pub notify_recovery: bool,
+    pub enable_actions: bool,
+    pub telegram_buttons: bool,
+    pub telegram_commands: bool,
+    pub mute_seconds: u64,
+    pub dry_run: bool,
+    pub output: Output,
+    pub message_template: Option<String>,
+    /// Язык встроенных текстов сообщений.
+    pub message_locale: MessageLocale,
+    /// TOML-файл, заменяющий отдельные тексты сообщений (см. [`crate::locale::Strings`]).
+    pub message_strings: Option<PathBuf>,
+    /// Знаков после запятой у CPU и других процентов в сообщениях.
+    pub cpu_decimals: usize,
+    /// Локаль вроде `ru_RU.UTF-8` или `de`, по которой выбирается десятичный разделитель;
+    /// `system` — из LC_ALL/LC_NUMERIC/LANG. None — точка.
+    pub number_locale: Option<String>,
+    pub resolve_exe: bool,
+    pub include_hostname: bool,
+    pub include_version: bool,
+    /// Показывать в строке System самые загруженные ядра процессора.
+    pub include_per_core: bool,
+    pub trend_window: usize,
+    pub rules: Vec<Rule>,
+    /// Переменные окружения, которые не разобрались: вместо них взято значение из файла
+    /// или по умолчанию. При запуске это предупреждение, в `--validate-config` — ошибка.
+    #[serde(skip)]
+    pub invalid_env: Vec<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            threshold: 50.0,
+            threshold_mode: ThresholdMode::PerCore,
+            memory_threshold: None,
+            swap_threshold: None,
+            sustained_seconds: 0,
+            breach_count: 1,
+            breach_window: 1,
+            max_runtime_seconds: None,
+            d_state_seconds: None,
+            cpu_time_budget: None,
+            disk_io_threshold: None,
+            fd_threshold: None,
+            thread_threshold: None,
+            score_threshold: None,
+            score_cpu_weight: 1.0,
+            score_memory_weight: 1.0,
+            zombie_threshold: None,
+            restart_threshold: None,
+            restart_window_seconds: 300,
+            net_threshold: None,
+            gpu_threshold: None,
+            gpu_memory_threshold: None,
+            warning_multiplier: 1.0,
+            critical_multiplier: 2.0,
+            min_process_age_seconds: 0,
+            min_cpu_time_ms: 0,
+            aggregate_tree: false,
+            aggregate_names: Vec::new(),
+            only_running: false,
+            only_processes: Vec::new(),
+            ignore_processes: Vec::new(),
+            watch_users: Vec::new(),
+            ignore_users: Vec::new(),
+            check_interval: 1.0,
+            samples_per_interval: 1,
+            refresh_mode: RefreshMode::Minimal,
+            proc_path: PathBuf::from("/proc"),
+            adaptive_interval: false,
+            min_interval: 1.0,
+            max_interval: 5.0,
+            check_jitter_ms: 0,
+            warmup_ms: sysinfo::MINIMUM_CPU_UPDATE_INTERVAL.as_millis() as u64,
+            boot_grace_seconds: 0,
+            cooldown_seconds: 600,
+            cooldowns: HashMap::new(),
+            max_cooldown_seconds: 0,
+            heartbeat_seconds: 0,
+            notify_on_start: false,
+            digest_seconds: 0,
+            quiet_hours: None,
+            quiet_hours_tz: QuietTimezone::Local,
+            critical_processes: Vec::new(),
+            dedup_by: DedupBy::Pid,
+            secrets_file: None,
+            bot_token: None,
+            chat_id: None,
+            telegram_parse_mode: None,
+            telegram_delivery: Delivery::All,
+            telegram_proxy: None,
+            telegram_api_base: notify::DEFAULT_TELEGRAM_API_BASE.to_string(),
+            skip_token_check: false,
+            http_pool_max_idle: None,
+            http_pool_idle_timeout: None,
+            http_connect_timeout: None,
+            http_ip_family: IpFamily::Any,
+            send_timeout_seconds: notify::DEFAULT_SEND_TIMEOUT.as_secs_f64(),
+            discord_webhook_url: None,
+            slack_webhook_url: None,
+            slack_channel: None,
+            webhook_url: None,
+            webhook_headers: None,
+            socket_path: None,
+            route_info: Vec::new(),
+            route_warning: Vec::new(),
+            route_critical: Vec::new(),
+            fallback_backend: None,
+            fallback_max_per_hour: 10,
+            circuit_failure_threshold: 0,
+            circuit_cooldown_seconds: 300,
+            send_max_retries: 3,
+            metrics_addr: None,
+            health_addr: None,
+            state_file: None,
+            audit_log: None,
+            max_batch_lines: 20,
+            max_alerts_per_minute: 0,
+            watch_pid: None,
+            watch_pidfile: None,
+            alert_on_exit: false,
+            notify_recovery: false,
+            enable_actions: false,
+            telegram_buttons: false,
+            telegram_commands: false,
+            mute_seconds: 3600,
+            dry_run: false,
+            output: Output::Notify,
+            message_template: None,
+            message_locale: MessageLocale::Ru,
+            message_strings: None,
+            cpu_decimals: 1,
+            number_locale: None,
+            resolve_exe: false,
+            include_hostname: true,
+            include_version: true,
+            include_per_core: false,
+            trend_window: 10,
+            rules: Vec::new(),
+            invalid_env: Vec::new(),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Source<'a> {
+    Default,
+    File,
+    Env(&'a str),
+}
+
+impl fmt::Display for Source<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::File => write!(f, "config file"),
+            Source::Env(key) => write!(f, "env {}", key),
+        }
+    }
+}
+
+// Накладывает переменные окружения поверх значений из файла и запоминает источник каждого значения
+struct Loader {
+    file_keys: toml::Table,
+    invalid: RefCell<Vec<String>>,
+    // Ошибки в значениях, которые останавливают загрузку (см. require)
+    rejected: RefCell<Vec<String>>,
+}
+
+impl Loader {
+    fn resolve<T>(&self, key: &str, env_key: &str, value: &mut T, parse: impl Fn(&str) -> Option<T>) {
+        let from_file = if self.file_keys.contains_key(key) {
+            Source::File
+        } else {
+            Source::Default
+        };
+
+        let source = match env::var(env_key) {
+            Ok(raw) => match parse(&raw) {
+                Some(parsed) => {
+                    *value = parsed;
+                    Source::Env(env_key)
+                }
+                None => {
+                    warn!("Invalid {}={:?}, using {} value", env_key, raw, from_file);
+                    self.invalid.borrow_mut().push(format!("invalid {}={:?}", env_key, raw));
+                    from_file
+                }
+            },
+            Err(_) => from_file,
+        };
+
+        info!(event = "config", key = key, source:% = source; "config: {} from {}", key, source);
+    }
+
+    // Как resolve, но ошибка в значении не заменяется значением из файла, а останавливает
+    // загрузку: порог в байтах с опечаткой в единице молча сработал бы не там
+    fn require<T>(&self, key: &str, env_key: &str, value: &mut T, parse: impl Fn(&str) -> Result<T, String>) {
+        if let Ok(raw) = env::var(env_key) {
+            if let Err(e) = parse(&raw) {
+                self.rejected.borrow_mut().push(format!("invalid {}={:?}: {}", env_key, raw, e));
+                return;
+            }
+        }
+        self.resolve(key, env_key, value, |raw| parse(raw).ok());
+    }
+}
+
+fn parsed<T: FromStr>(raw: &str) -> Option<T> {
+    raw.trim().parse().ok()
+}
+
+// Список через запятую: "postgres, java"
+fn list(raw: &str) -> Option<Vec<String>> {
+    Some(
+        raw.split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(str::to_string)
+            .collect(),
+    )
+}
+
+// Список каналов через запятую: "slack, telegram"; одна опечатка отбрасывает весь список
+fn backends(raw: &str) -> Option<Vec<Backend>> {
+    list(raw)?.iter().map(|item| item.parse().ok()).collect()
+}
+
+// COOLDOWNS: "cpu=600,memory=3600", имена условий — как в triggers вебхука
+fn parse_cooldowns(raw: &str) -> Result<HashMap<AlertKind, u64>, String> {
+    let mut cooldowns = HashMap::new();
+    for item in list(raw).unwrap_or_default() {
+        let (kind, seconds) = item.split_once('=').ok_or_else(|| format!("expected metric=seconds, got {:?}", item))?;
+        let kind: AlertKind = kind.trim().parse().map_err(|_| format!("unknown metric {:?}", kind.trim()))?;
+        let seconds = seconds.trim().parse().map_err(|_| format!("invalid seconds in {:?}", item))?;
+        cooldowns.insert(kind, seconds);
+    }
+    Ok(cooldowns)
+}
+
+fn flag(raw: &str) -> Option<bool> {
+    match raw.trim().to_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+fn optional<T: FromStr>(raw: &str) -> Option<Option<T>> {
+    parsed(raw).map(Some)
+}
+
+// Необязательная длительность в секундах, дробная: "2.5"
+fn seconds(raw: &str) -> Option<Option<f64>> {
+    parsed::<f64>(raw).filter(|seconds| seconds.is_finite() && *seconds > 0.0).map(Some)
+}
+
+fn text(raw: &str) -> Option<Option<String>> {
+    Some(Some(raw.to_string()))
+}
+
+// Секреты, которые можно держать в SECRETS_FILE вместо окружения: переменные окружения
+// видны в /proc/PID/environ
+const SECRETS: [&str; 6] = [
+    "TELEGRAM_BOT_TOKEN",
+    "TELEGRAM_PROXY",
+    "DISCORD_WEBHOOK_URL",
+    "SLACK_WEBHOOK_URL",
+    "WEBHOOK_URL",
+    "WEBHOOK_HEADERS",
+];
+
+// Файл секретов: строки KEY=value с именами переменных окружения, # — комментарий, кавычки
+// вокруг значения снимаются. Значения из файла важнее окружения и файла конфигурации
+fn load_secrets(path: &Path, config: &mut Config) -> Result<(), WatcherError> {
+    let content =
+        fs::read_to_string(path).map_err(|e| WatcherError::io(format!("cannot read secrets file {}", path.display()), e))?;
+    #[cfg(unix)]
+    if let Ok(metadata) = fs::metadata(path) {
+        use std::os::unix::fs::PermissionsExt;
+        if metadata.permissions().mode() & 0o004 != 0 {
+            warn!("Secrets file {} is world-readable, run: chmod 600 {}", path.display(), path.display());
+        }
+    }
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| WatcherError::Config(format!("secrets file {}, line {}: expected KEY=value", path.display(), number + 1)))?;
+        let key = key.trim();
+        let value = value.trim();
+        let value = value
+            .strip_prefix('"')
+            .and_then(|value| value.strip_suffix('"'))
+            .or_else(|| value.strip_prefix('\'').and_then(|value| value.strip_suffix('\'')))
+            .unwrap_or(value)
+            .to_string();
+        let (file_key, target) = match key {
+            "TELEGRAM_BOT_TOKEN" => ("bot_token", &mut config.bot_token),
+            "TELEGRAM_PROXY" => ("telegram_proxy", &mut config.telegram_proxy),
+            "DISCORD_WEBHOOK_URL" => ("discord_webhook_url", &mut config.discord_webhook_url),
+            "SLACK_WEBHOOK_URL" => ("slack_webhook_url", &mut config.slack_webhook_url),
+            "WEBHOOK_URL" => ("webhook_url", &mut config.webhook_url),
+            "WEBHOOK_HEADERS" => ("webhook_headers", &mut config.webhook_headers),
+            _ => {
+                return Err(WatcherError::Config(format!(
+                    "secrets file {}, line {}: unknown key {} (expected one of {})",
+                    path.display(),
+                    number + 1,
+                    key,
+                    SECRETS.join(", ")
+                )))
+            }
+        };
+        *target = Some(value);
+        info!("config: {} from secrets file", file_key);
+    }
+    Ok(())
+}
+
+impl Config {
+    // Основной канал: Discord, если задан его webhook, затем Slack, затем JSON-вебхук
+    // (WEBHOOK_URL), затем Unix-сокет (SOCKET_PATH), иначе — Telegram. Запасной канал
+    // (FALLBACK_BACKEND) основным не выбирается
+    pub fn primary_backend(&self) -> Backend {
+        let configured = [
+            (Backend::Discord, self.discord_webhook_url.is_some()),
+            (Backend::Slack, self.slack_webhook_url.is_some()),
+            (Backend::Webhook, self.webhook_url.is_some()),
+            (Backend::Socket, self.socket_path.is_some()),
+        ];
+        configured
+            .into_iter()
+            .find(|(backend, set)| *set && Some(*backend) != self.fallback_backend)
+            .map_or(Backend::Telegram, |(backend, _)| backend)
+    }
+
+    // Каналы для оповещений уровня; пустой список — основной канал
+    pub fn route(&self, severity: Severity) -> &[Backend] {
+        match severity {
+            Severity::Info => &self.route_info,
+            Severity::Warning => &self.route_warning,
+            Severity::Critical => &self.route_critical,
+        }
+    }
+
+    // Все каналы, куда что-то может уйти: основной, упомянутые в маршрутах и запасной, без повторов
+    pub fn backends(&self) -> Vec<Backend> {
+        let mut backends = vec![self.primary_backend()];
+        let routes = [&self.route_info, &self.route_warning, &self.route_critical].into_iter().flatten();
+        for backend in routes.chain(&self.fallback_backend) {
+            if !backends.contains(backend) {
+                backends.push(*backend);
+            }
+        }
+        backends
+    }
+
+    // Уходят ли сообщения в каналы; с DRY_RUN и OUTPUT=stdout-json каналы не нужны
+    pub fn sends(&self) -> bool {
+        !self.dry_run && self.output == Output::Notify
+    }
+
+    // Обязательные настройки, которых не хватает используемым каналам: (переменная, ключ в файле)
+    pub fn missing_required(&self) -> Vec<(&'static str, &'static str)> {
+        let mut missing = Vec::new();
+        if !self.sends() {
+            return missing;
+        }
+        for backend in self.backends() {
+            match backend {
+                Backend::Telegram => {
+                    if self.bot_token.as_deref().map(str::trim).filter(|token| !token.is_empty()).is_none() {
+                        missing.push(("TELEGRAM_BOT_TOKEN", "bot_token"));
+                    }
+                    if self.chat_ids().is_empty() {
+                        missing.push(("TELEGRAM_CHAT_ID", "chat_id"));
+                    }
+                }
+                Backend::Discord if self.discord_webhook_url.is_none() => {
+                    missing.push(("DISCORD_WEBHOOK_URL", "discord_webhook_url"))
+                }
+                Backend::Slack if self.slack_webhook_url.is_none() => missing.push(("SLACK_WEBHOOK_URL", "slack_webhook_url")),
+                Backend::Webhook if self.webhook_url.is_none() => missing.push(("WEBHOOK_URL", "webhook_url")),
+                Backend::Socket if self.socket_path.is_none() => missing.push(("SOCKET_PATH", "socket_path")),
+                _ => {}
+            }
+        }
+        missing
+    }
+
+    // Настройки HTTP-клиента, общего для всех каналов
+    pub fn http_options(&self) -> HttpOptions {
+        HttpOptions {
+            proxy: self.telegram_proxy.clone(),
+            pool_max_idle: self.http_pool_max_idle,
+            pool_idle_timeout: self.http_pool_idle_timeout.map(Duration::from_secs_f64),
+            connect_timeout: self.http_connect_timeout.map(Duration::from_secs_f64),
+            timeout: Some(self.send_timeout()),
+            ip_family: self.http_ip_family,
+        }
+    }
+
+    // Таймаут одной отправки; SEND_TIMEOUT_SECONDS проверяется при запуске
+    pub fn send_timeout(&self) -> Duration {
+        Duration::try_from_secs_f64(self.send_timeout_seconds).unwrap_or(notify::DEFAULT_SEND_TIMEOUT)
+    }
+
+    // TELEGRAM_CHAT_ID может содержать несколько чатов через запятую
+    pub fn chat_ids(&self) -> Vec<String> {
+        self.chat_id.as_deref().and_then(list).unwrap_or_default()
+    }
+
+    // Путь к файлу необязателен; если он задан, файл обязан существовать и быть корректным
+    pub fn load(path: Option<&Path>) -> Result<Config, WatcherError> {
+        let (mut config, file_keys) = match path {
+            Some(path) => {
+                let content = fs::read_to_string(path)
+                    .map_err(|e| WatcherError::io(format!("cannot read config file {}", path.display()), e))?;
+                let file_keys: toml::Table = toml::from_str(&content)
+                    .map_err(|e| WatcherError::Config(format!("invalid config file {}: {}", path.display(), e)))?;
+                let config: Config = toml::from_str(&content)
+                    .map_err(|e| WatcherError::Config(format!("invalid config file {}: {}", path.display(), e)))?;
+                info!("Loaded config file {}", path.display());
+                (config, file_keys)
+            }
+            None => (Config::default(), toml::Table::new()),
+        };
+
+        let loader = Loader {
+            file_keys,
+            invalid: RefCell::new(Vec::new()),
+            rejected: RefCell::new(Vec::new()),
+        };
+        loader.resolve("threshold", "CPU_THRESHOLD", &mut config.threshold, parsed);
+        loader.resolve("threshold_mode", "THRESHOLD_MODE", &mut config.threshold_mode, parsed);
+        loader.require("memory_threshold", "MEMORY_THRESHOLD", &mut config.memory_threshold, |raw| {
+            MemoryThreshold::parse(raw).map(Some)
+        });
+        loader.require("swap_threshold", "SWAP_THRESHOLD", &mut config.swap_threshold, |raw| {
+            MemoryThreshold::parse(raw).map(Some)
+        });
+        loader.resolve("sustained_seconds", "SUSTAINED_SECONDS", &mut config.sustained_seconds, parsed);
+        loader.resolve("breach_count", "BREACH_COUNT", &mut config.breach_count, parsed);
+        loader.resolve("breach_window", "BREACH_WINDOW", &mut config.breach_window, parsed);
+        loader.resolve("max_runtime_seconds", "MAX_RUNTIME_SECONDS", &mut config.max_runtime_seconds, optional);
+        loader.resolve("d_state_seconds", "D_STATE_SECONDS", &mut config.d_state_seconds, optional);
+        loader.resolve("cpu_time_budget", "CPU_TIME_BUDGET", &mut config.cpu_time_budget, optional);
+        loader.require("disk_io_threshold", "DISK_IO_THRESHOLD", &mut config.disk_io_threshold, |raw| {
+            parse_bytes(raw, 1, true).map(Some)
+        });
+        loader.resolve("fd_threshold", "FD_THRESHOLD", &mut config.fd_threshold, optional);
+        loader.resolve("thread_threshold", "THREAD_THRESHOLD", &mut config.thread_threshold, optional);
+        loader.resolve("score_threshold", "SCORE_THRESHOLD", &mut config.score_threshold, optional);
+        loader.resolve("score_cpu_weight", "SCORE_CPU_WEIGHT", &mut config.score_cpu_weight, parsed);
+        loader.resolve("score_memory_weight", "SCORE_MEMORY_WEIGHT", &mut config.score_memory_weight, parsed);
+        loader.resolve("zombie_threshold", "ZOMBIE_THRESHOLD", &mut config.zombie_threshold, optional);
+        loader.resolve("restart_threshold", "RESTART_THRESHOLD", &mut config.restart_threshold, optional);
+        loader.resolve("restart_window_seconds", "RESTART_WINDOW_SECONDS", &mut config.restart_window_seconds, parsed);
+        loader.require("net_threshold", "NET_THRESHOLD", &mut config.net_threshold, |raw| {
+            parse_bytes(raw, 1, true).map(Some)
+        });
+        loader.resolve("gpu_threshold", "GPU_THRESHOLD", &mut config.gpu_threshold, optional);
+        loader.require("gpu_memory_threshold", "GPU_MEMORY_THRESHOLD", &mut config.gpu_memory_threshold, |raw| {
+            MemoryThreshold::parse(raw).map(Some)
+        });
+        loader.resolve("warning_multiplier", "WARNING_MULTIPLIER", &mut config.warning_multiplier, parsed);
+        loader.resolve("critical_multiplier", "CRITICAL_MULTIPLIER", &mut config.critical_multiplier, parsed);
+        loader.resolve("min_process_age_seconds", "MIN_PROCESS_AGE_SECONDS", &mut config.min_process_age_seconds, parsed);
+        loader.resolve("min_cpu_time_ms", "MIN_CPU_TIME_MS", &mut config.min_cpu_time_ms, parsed);
+        loader.resolve("aggregate_tree", "AGGREGATE_TREE", &mut config.aggregate_tree, flag);
+        loader.resolve("aggregate_names", "AGGREGATE_NAMES", &mut config.aggregate_names, list);
+        loader.resolve("only_running", "ONLY_RUNNING", &mut config.only_running, flag);
+        loader.resolve("only_processes", "ONLY_PROCESSES", &mut config.only_processes, list);
+        loader.resolve("ignore_processes", "IGNORE_PROCESSES", &mut config.ignore_processes, list);
+        loader.resolve("watch_users", "WATCH_USERS", &mut config.watch_users, list);
+        loader.resolve("ignore_users", "IGNORE_USERS", &mut config.ignore_users, list);
+        loader.resolve("refresh_mode", "REFRESH_MODE", &mut config.refresh_mode, parsed);
+        loader.resolve("proc_path", "PROC_PATH", &mut config.proc_path, parsed);
+        loader.resolve("check_interval", "CHECK_INTERVAL", &mut config.check_interval, parsed);
+        loader.resolve("samples_per_interval", "SAMPLES_PER_INTERVAL", &mut config.samples_per_interval, parsed);
+        loader.resolve("adaptive_interval", "ADAPTIVE_INTERVAL", &mut config.adaptive_interval, flag);
+        loader.resolve("min_interval", "MIN_INTERVAL", &mut config.min_interval, parsed);
+        loader.resolve("max_interval", "MAX_INTERVAL", &mut config.max_interval, parsed);
+        loader.resolve("check_jitter_ms", "CHECK_JITTER_MS", &mut config.check_jitter_ms, parsed);
+        loader.resolve("warmup_ms", "WARMUP_MS", &mut config.warmup_ms, parsed);
+        loader.resolve("boot_grace_seconds", "BOOT_GRACE_SECONDS", &mut config.boot_grace_seconds, parsed);
+        loader.resolve("cooldown_seconds", "COOLDOWN_SECONDS", &mut config.cooldown_seconds, parsed);
+        loader.require("cooldowns", "COOLDOWNS", &mut config.cooldowns, parse_cooldowns);
+        loader.resolve("max_cooldown_seconds", "MAX_COOLDOWN_SECONDS", &mut config.max_cooldown_seconds, parsed);
+        loader.resolve("heartbeat_seconds", "HEARTBEAT_SECONDS", &mut config.heartbeat_seconds, parsed);
+        loader.resolve("notify_on_start", "NOTIFY_ON_START", &mut config.notify_on_start, flag);
+        loader.resolve("digest_seconds", "DIGEST_SECONDS", &mut config.digest_seconds, parsed);
+        loader.resolve("quiet_hours", "QUIET_HOURS", &mut config.quiet_hours, optional);
+        loader.resolve("quiet_hours_tz", "QUIET_HOURS_TZ", &mut config.quiet_hours_tz, parsed);
+        loader.resolve("critical_processes", "CRITICAL_PROCESSES", &mut config.critical_processes, list);
+        loader.resolve("dedup_by", "DEDUP_BY", &mut config.dedup_by, parsed);
+        loader.resolve("bot_token", "TELEGRAM_BOT_TOKEN", &mut config.bot_token, text);
+        loader.resolve("chat_id", "TELEGRAM_CHAT_ID", &mut config.chat_id, text);
+        loader.resolve("telegram_parse_mode", "TELEGRAM_PARSE_MODE", &mut config.telegram_parse_mode, optional);
+        loader.resolve("telegram_delivery", "TELEGRAM_DELIVERY", &mut config.telegram_delivery, parsed);
+        loader.resolve("telegram_proxy", "TELEGRAM_PROXY", &mut config.telegram_proxy, text);
+        loader.resolve("telegram_api_base", "TELEGRAM_API_BASE", &mut config.telegram_api_base, parsed);
+        loader.resolve("skip_token_check", "SKIP_TOKEN_CHECK", &mut config.skip_token_check, flag);
+        loader.resolve("http_pool_max_idle", "HTTP_POOL_MAX_IDLE", &mut config.http_pool_max_idle, optional);
+        loader.resolve("http_pool_idle_timeout", "HTTP_POOL_IDLE_TIMEOUT", &mut config.http_pool_idle_timeout, seconds);
+        loader.resolve("http_connect_timeout", "HTTP_CONNECT_TIMEOUT", &mut config.http_connect_timeout, seconds);
+        loader.resolve("http_ip_family", "HTTP_IP_FAMILY", &mut config.http_ip_family, parsed);
+        loader.resolve("send_timeout_seconds", "SEND_TIMEOUT_SECONDS", &mut config.send_timeout_seconds, |raw| {
+            seconds(raw).flatten()
+        });
+        loader.resolve("discord_webhook_url", "DISCORD_WEBHOOK_URL", &mut config.discord_webhook_url, text);
+        loader.resolve("slack_webhook_url", "SLACK_WEBHOOK_URL", &mut config.slack_webhook_url, text);
+        loader.resolve("slack_channel", "SLACK_CHANNEL", &mut config.slack_channel, text);
+        loader.resolve("webhook_url", "WEBHOOK_URL", &mut config.webhook_url, text);
+        loader.resolve("webhook_headers", "WEBHOOK_HEADERS", &mut config.webhook_headers, text);
+        loader.resolve("socket_path", "SOCKET_PATH", &mut config.socket_path, optional);
+        loader.resolve("route_info", "ROUTE_INFO", &mut config.route_info, backends);
+        loader.resolve("route_warning", "ROUTE_WARNING", &mut config.route_warning, backends);
+        loader.resolve("route_critical", "ROUTE_CRITICAL", &mut config.route_critical, backends);
+        loader.resolve("fallback_backend", "FALLBACK_BACKEND", &mut config.fallback_backend, optional);
+        loader.resolve("fallback_max_per_hour", "FALLBACK_MAX_PER_HOUR", &mut config.fallback_max_per_hour, parsed);
+        loader.resolve(
+            "circuit_failure_threshold",
+            "CIRCUIT_FAILURE_THRESHOLD",
+            &mut config.circuit_failure_threshold,
+            parsed,
+        );
+        loader.resolve("circuit_cooldown_seconds", "CIRCUIT_COOLDOWN_SECONDS", &mut config.circuit_cooldown_seconds, parsed);
+        loader.resolve("send_max_retries", "SEND_MAX_RETRIES", &mut config.send_max_retries, parsed);
+        loader.resolve("metrics_addr", "METRICS_ADDR", &mut config.metrics_addr, optional);
+        loader.resolve("health_addr", "HEALTH_ADDR", &mut config.health_addr, optional);
+        loader.resolve("state_file", "STATE_FILE", &mut config.state_file, optional);
+        loader.resolve("audit_log", "AUDIT_LOG", &mut config.audit_log, optional);
+        loader.resolve("max_batch_lines", "MAX_BATCH_LINES", &mut config.max_batch_lines, parsed);
+        loader.resolve("max_alerts_per_minute", "MAX_ALERTS_PER_MINUTE", &mut config.max_alerts_per_minute, parsed);
+        loader.resolve("watch_pid", "WATCH_PID", &mut config.watch_pid, optional);
+        loader.resolve("watch_pidfile", "WATCH_PIDFILE", &mut config.watch_pidfile, optional);
+        loader.resolve("alert_on_exit", "ALERT_ON_EXIT", &mut config.alert_on_exit, flag);
+        loader.resolve("notify_recovery", "NOTIFY_RECOVERY", &mut config.notify_recovery, flag);
+        loader.resolve("enable_actions", "ENABLE_ACTIONS", &mut config.enable_actions, flag);
+        loader.resolve("telegram_buttons", "TELEGRAM_BUTTONS", &mut config.telegram_buttons, flag);
+        loader.resolve("telegram_commands", "TELEGRAM_COMMANDS", &mut config.telegram_commands, flag);
+        loader.resolve("mute_seconds", "MUTE_SECONDS", &mut config.mute_seconds, parsed);
+        loader.resolve("dry_run", "DRY_RUN", &mut config.dry_run, flag);
+        loader.resolve("output", "OUTPUT", &mut config.output, parsed);
+        loader.resolve("message_template", "MESSAGE_TEMPLATE", &mut config.message_template, text);
+        loader.resolve("message_locale", "MESSAGE_LOCALE", &mut config.message_locale, parsed);
+        loader.resolve("message_strings", "MESSAGE_STRINGS", &mut config.message_strings, optional);
+        loader.resolve("cpu_decimals", "CPU_DECIMALS", &mut config.cpu_decimals, parsed);
+        loader.resolve("number_locale", "NUMBER_LOCALE", &mut config.number_locale, text);
+        loader.resolve("resolve_exe", "RESOLVE_EXE", &mut config.resolve_exe, flag);
+        loader.resolve("include_hostname", "INCLUDE_HOSTNAME", &mut config.include_hostname, flag);
+        loader.resolve("include_version", "INCLUDE_VERSION", &mut config.include_version, flag);
+        loader.resolve("include_per_core", "INCLUDE_PER_CORE", &mut config.include_per_core, flag);
+        loader.resolve("trend_window", "TREND_WINDOW", &mut config.trend_window, parsed);
+        loader.resolve("secrets_file", "SECRETS_FILE", &mut config.secrets_file, optional);
+
+        let rejected = loader.rejected.take();
+        if !rejected.is_empty() {
+            return Err(WatcherError::Config(rejected.join("; ")));
+        }
+        if let Some(path) = config.secrets_file.clone() {
+            load_secrets(&path, &mut config)?;
+        }
+        config.invalid_env = loader.invalid.into_inner();
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIB: u64 = 1024 * 1024;
+
+    #[test]
+    fn binary_and_decimal_units_differ() {
+        assert_eq!(parse_bytes("500MiB", MIB, false), Ok(500 * MIB));
+        assert_eq!(parse_bytes("500MB", MIB, false), Ok(500_000_000));
+        assert_eq!(parse_bytes("64kB", MIB, false), Ok(64_000));
+        assert_eq!(parse_bytes("64KiB", MIB, false), Ok(64 * 1024));
+        // Регистр приставки не важен, а "b" строчная — это биты
+        assert_eq!(parse_bytes("2giB", MIB, false), Ok(2 << 30));
+        assert!(parse_bytes("2gib", MIB, false).is_err());
+    }
+
+    #[test]
+    fn fractions_and_bare_numbers() {
+        assert_eq!(parse_bytes("1.5GB", MIB, false), Ok(1_500_000_000));
+        assert_eq!(parse_bytes("0.5GiB", MIB, false), Ok(512 * MIB));
+        assert_eq!(parse_bytes(" 512 ", MIB, false), Ok(512 * MIB));
+        assert_eq!(parse_bytes("100 B", MIB, false), Ok(100));
+    }
+
+    #[test]
+    fn rate_suffix_only_for_rates() {
+        assert_eq!(parse_bytes("200kB/s", 1, true), Ok(200_000));
+        assert_eq!(parse_bytes("10MiB/s", 1, true), Ok(10 * MIB));
+        assert!(parse_bytes("200kB/s", 1, false).is_err());
+    }
+
+    #[test]
+    fn rejects_ambiguous_and_unparseable_values() {
+        for value in ["500M", "500Mb", "1MBit", "10kb", "5 parsecs", "", "MB", "1.2.3GB", "-5MB", "0MB", "0.1B"] {
+            assert!(parse_bytes(value, MIB, false).is_err(), "{:?} was accepted", value);
+        }
+    }
+}
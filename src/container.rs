@@ -0,0 +1,37 @@
+use sysinfo::Pid;
+
+// Длина полного ID контейнера Docker/containerd и короткой формы, как в `docker ps`
+const ID_LEN: usize = 64;
+pub const SHORT_ID_LEN: usize = 12;
+
+// Префиксы systemd-юнитов, в которые рантаймы кладут контейнеры при cgroup v2
+const SCOPE_PREFIXES: [&str; 4] = ["docker-", "cri-containerd-", "crio-", "libpod-"];
+
+// ID контейнера из /proc/PID/cgroup. Путь бывает вида /docker/<id> (cgroup v1),
+// /system.slice/docker-<id>.scope (cgroup v2), /kubepods/.../<id> или cri-containerd-<id>.scope
+#[cfg(target_os = "linux")]
+pub fn container_id(pid: Pid) -> Option<String> {
+    let content = std::fs::read_to_string(crate::procfs::process_file(pid, "cgroup")).ok()?;
+    parse_cgroup(&content)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn container_id(_pid: Pid) -> Option<String> {
+    None
+}
+
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_cgroup(content: &str) -> Option<String> {
+    content
+        .lines()
+        .filter_map(|line| line.splitn(3, ':').nth(2))
+        .flat_map(|path| path.rsplit('/'))
+        .find_map(|segment| {
+            let segment = segment.strip_suffix(".scope").unwrap_or(segment);
+            let id = SCOPE_PREFIXES
+                .iter()
+                .find_map(|prefix| segment.strip_prefix(prefix))
+                .unwrap_or(segment);
+            (id.len() == ID_LEN && id.bytes().all(|b| b.is_ascii_hexdigit())).then(|| id.to_string())
+        })
+}
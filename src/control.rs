@@ -0,0 +1,219 @@
+use cpu_watcher::actions::Action;
+use cpu_watcher::notify::{self, Button, CallbackQuery, IncomingMessage};
+use log::{info, warn};
+use std::time::Duration;
+use sysinfo::Pid;
+use tokio::sync::{mpsc, oneshot};
+
+// Сколько Telegram держит один запрос getUpdates, если нажатий нет
+const POLL_WAIT: Duration = Duration::from_secs(25);
+// Пауза после ошибки опроса, чтобы не долбить API (например, при 409 из-за webhook)
+const RETRY_DELAY: Duration = Duration::from_secs(10);
+// Сколько ждать, пока основной цикл примет запрос (он смотрит в канал раз в проверку)
+const REPLY_TIMEOUT: Duration = Duration::from_secs(120);
+
+const USAGE: &str = "Usage: /mute <pid> <duration> (e.g. /mute 1234 2h), /unmute <pid>";
+
+// Что сделать с процессом
+pub enum Command {
+    Mute(Duration),
+    Unmute,
+    // Kill или renice по кнопке: основной цикл проверяет правило процесса, как перед действием по нему
+    Act(Action),
+}
+
+// Watcher живёт в основном цикле, поэтому mute и действия передаются туда через канал
+pub struct ControlRequest {
+    pub pid: Pid,
+    // Время старта из кнопки; у команды его нет
+    pub start_time: Option<u64>,
+    pub command: Command,
+    // Имя процесса или причина отказа
+    pub reply: oneshot::Sender<Result<String, String>>,
+}
+
+// Кнопки под оповещениями и команды /mute, /unmute: длинный опрос getUpdates, проверка чата
+// и действие. Принимается только то, что пришло из чатов TELEGRAM_CHAT_ID
+pub struct Control {
+    pub client: reqwest::Client,
+    pub api_base: String,
+    pub bot_token: String,
+    pub chat_ids: Vec<String>,
+    pub buttons: bool,
+    pub commands: bool,
+    pub mute_duration: Duration,
+    pub requests: mpsc::Sender<ControlRequest>,
+}
+
+impl Control {
+    pub async fn run(self) {
+        let mut allowed = Vec::new();
+        if self.buttons {
+            allowed.push("callback_query");
+        }
+        if self.commands {
+            allowed.push("message");
+        }
+        info!("Listening for Telegram {}", allowed.join(" and "));
+        let mut offset = 0;
+        loop {
+            let updates =
+                notify::telegram_get_updates(&self.client, &self.api_base, &self.bot_token, offset, POLL_WAIT, &allowed);
+            match updates.await {
+                Ok(updates) => {
+                    for update in updates {
+                        offset = update.update_id + 1;
+                        if let Some(query) = update.callback_query.filter(|_| self.buttons) {
+                            self.handle(query).await;
+                        }
+                        if let Some(message) = update.message.filter(|_| self.commands) {
+                            self.command(message).await;
+                        }
+                    }
+                }
+                Err(e) => {
+                    warn!("Telegram getUpdates failed: {}", e);
+                    tokio::time::sleep(RETRY_DELAY).await;
+                }
+            }
+        }
+    }
+
+    fn authorized(&self, message: &IncomingMessage) -> bool {
+        self.chat_ids.iter().any(|chat_id| message.chat.matches(chat_id))
+    }
+
+    async fn handle(&self, query: CallbackQuery) {
+        let message = query.message.as_ref().filter(|message| self.authorized(message));
+        let Some(message) = message else {
+            warn!(event = "control_denied", user:% = query.from; "Ignoring button press by {} from an unknown chat", query.from);
+            self.answer(&query.id, "Not allowed").await;
+            return;
+        };
+        let Some((button, pid, start_time)) = query.data.as_deref().and_then(Button::parse) else {
+            self.answer(&query.id, "Unknown button").await;
+            return;
+        };
+
+        let result = match button {
+            Button::Kill => self.request(pid, Some(start_time), Command::Act(Action::Kill)).await,
+            Button::Renice => self.request(pid, Some(start_time), Command::Act(Action::Renice)).await,
+            Button::Mute => self.mute(pid, Some(start_time), Some(self.mute_duration)).await,
+        };
+        let text = match &result {
+            Ok(text) | Err(text) => text.as_str(),
+        };
+        info!(event = "control", user:% = query.from, pid = pid.as_u32(), button:? = button; "{} by {}", text, query.from);
+        self.answer(&query.id, text).await;
+        self.reply(message, &format!("{} ({})", text, query.from)).await;
+    }
+
+    // /mute <pid> <duration> и /unmute <pid>; прочие сообщения в чате не трогаем
+    async fn command(&self, message: IncomingMessage) {
+        let Some(text) = message.text.as_deref() else {
+            return;
+        };
+        let mut words = text.split_whitespace();
+        // В группах команда приходит как /mute@имя_бота
+        let command = words.next().unwrap_or_default();
+        let command = command.split('@').next().unwrap_or(command);
+        if command != "/mute" && command != "/unmute" {
+            return;
+        }
+        let user = message.from.as_ref().map_or_else(|| "unknown user".to_string(), ToString::to_string);
+        if !self.authorized(&message) {
+            warn!(event = "control_denied", user = user.as_str(); "Ignoring {} by {} from an unknown chat", command, user);
+            return;
+        }
+        let args: Vec<&str> = words.collect();
+        let request = match (command, args.as_slice()) {
+            ("/mute", [pid, duration]) => pid.parse::<u32>().ok().zip(parse_duration(duration).map(Some)),
+            ("/unmute", [pid]) => pid.parse::<u32>().ok().map(|pid| (pid, None)),
+            _ => None,
+        };
+        let result = match request {
+            Some((pid, duration)) => self.mute(Pid::from_u32(pid), None, duration).await,
+            None => Err(USAGE.to_string()),
+        };
+        let text = match &result {
+            Ok(text) | Err(text) => text.as_str(),
+        };
+        info!(event = "control", user = user.as_str(); "{}: {}", text.lines().next().unwrap_or_default(), user);
+        self.reply(&message, text).await;
+    }
+
+    async fn reply(&self, message: &IncomingMessage, text: &str) {
+        let chat_id = message.chat.id.to_string();
+        match notify::telegram_reply(&self.client, &self.api_base, &self.bot_token, &chat_id, message.message_id, text).await {
+            Ok(true) => {}
+            Ok(false) => warn!("Telegram rejected the reply to a control request"),
+            Err(e) => warn!("Cannot reply to a control request: {}", e),
+        }
+    }
+
+    async fn mute(&self, pid: Pid, start_time: Option<u64>, duration: Option<Duration>) -> Result<String, String> {
+        let command = match duration {
+            Some(duration) => Command::Mute(duration),
+            None => Command::Unmute,
+        };
+        let name = self.request(pid, start_time, command).await?;
+        Ok(match duration {
+            Some(duration) => format!(
+                "{} (PID {}) muted for {}",
+                name,
+                pid,
+                cpu_watcher::message::format_duration(duration)
+            ),
+            None => format!("{} (PID {}) unmuted", name, pid),
+        })
+    }
+
+    // Отправляет запрос в основной цикл и ждёт ответа
+    async fn request(&self, pid: Pid, start_time: Option<u64>, command: Command) -> Result<String, String> {
+        let (reply, response) = oneshot::channel();
+        let request = ControlRequest {
+            pid,
+            start_time,
+            command,
+            reply,
+        };
+        if self.requests.send(request).await.is_err() {
+            return Err("watcher is shutting down".to_string());
+        }
+        match tokio::time::timeout(REPLY_TIMEOUT, response).await {
+            Ok(Ok(result)) => result,
+            _ => Err("watcher did not respond".to_string()),
+        }
+    }
+
+    async fn answer(&self, callback_id: &str, text: &str) {
+        if let Err(e) = notify::telegram_answer_callback(&self.client, &self.api_base, &self.bot_token, callback_id, text).await {
+            warn!("Cannot answer a button press: {}", e);
+        }
+    }
+}
+
+// Длительность вида 90s, 30m, 2h, 1d или 1h30m; число без единицы — секунды
+fn parse_duration(raw: &str) -> Option<Duration> {
+    if let Ok(seconds) = raw.parse::<u64>() {
+        return (seconds > 0).then(|| Duration::from_secs(seconds));
+    }
+    let mut total = 0u64;
+    let mut number = String::new();
+    for c in raw.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86400,
+            _ => return None,
+        };
+        let value: u64 = std::mem::take(&mut number).parse().ok()?;
+        total = total.checked_add(value.checked_mul(unit)?)?;
+    }
+    (number.is_empty() && total > 0).then(|| Duration::from_secs(total))
+}
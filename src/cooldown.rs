@@ -0,0 +1,140 @@
+use crate::clock::Clock;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::Instant;
+
+/// Последнее оповещение по ключу и условию. `streak` — сколько оповещений подряд пришло
+/// без перерыва в превышении: от него растёт кулдаун при MAX_COOLDOWN_SECONDS.
+/// Время монотонное ([`Clock::monotonic`]), поэтому перевод системных часов кулдаун не сбивает.
+#[derive(Clone, Copy, Debug)]
+pub struct Cooldown {
+    pub alerted_at: Instant,
+    pub streak: u32,
+}
+
+/// Длительность кулдауна: COOLDOWN_SECONDS, удваивающийся с каждым оповещением подряд,
+/// но не больше MAX_COOLDOWN_SECONDS (если он не больше базового — кулдаун не растёт).
+#[derive(Clone, Copy, Debug)]
+pub struct CooldownPolicy {
+    pub base_seconds: u64,
+    pub max_seconds: u64,
+}
+
+impl CooldownPolicy {
+    pub fn seconds_for(&self, streak: u32) -> u64 {
+        if self.max_seconds <= self.base_seconds {
+            return self.base_seconds;
+        }
+        let doublings = streak.saturating_sub(1).min(31);
+        self.base_seconds.saturating_mul(1 << doublings).min(self.max_seconds)
+    }
+
+    // Сколько хранить запись о прошлом оповещении
+    pub fn retention_seconds(&self) -> u64 {
+        self.base_seconds.max(self.max_seconds) * 5
+    }
+}
+
+/// Можно ли снова оповещать по `key`: раньше не оповещали или кулдаун истёк.
+pub fn should_alert<K: Eq + Hash>(
+    clock: &dyn Clock,
+    alerted: &HashMap<K, Cooldown>,
+    key: &K,
+    policy: &CooldownPolicy,
+) -> bool {
+    match alerted.get(key) {
+        Some(cooldown) => {
+            let elapsed = clock.monotonic().saturating_duration_since(cooldown.alerted_at);
+            elapsed.as_secs() >= policy.seconds_for(cooldown.streak)
+        }
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::config::Config;
+    use crate::{AlertKind, CannedProcess, CannedProcesses, Watcher};
+
+    const POLICY: CooldownPolicy = CooldownPolicy {
+        base_seconds: 600,
+        max_seconds: 3600,
+    };
+
+    #[test]
+    fn cooldown_doubles_up_to_max() {
+        let seconds: Vec<u64> = (1..=6).map(|streak| POLICY.seconds_for(streak)).collect();
+        assert_eq!(seconds, [600, 1200, 2400, 3600, 3600, 3600]);
+        assert_eq!(POLICY.seconds_for(u32::MAX), 3600);
+    }
+
+    #[test]
+    fn cooldown_is_fixed_without_larger_max() {
+        let policy = CooldownPolicy {
+            base_seconds: 600,
+            max_seconds: 0,
+        };
+        assert_eq!(policy.seconds_for(5), 600);
+    }
+
+    #[test]
+    fn should_alert_after_streak_cooldown() {
+        let clock = ManualClock::new();
+        let alerted = HashMap::from([(
+            "worker",
+            Cooldown {
+                alerted_at: clock.monotonic(),
+                streak: 2,
+            },
+        )]);
+        assert!(should_alert(clock.as_ref(), &alerted, &"other", &POLICY));
+        clock.advance(1199);
+        assert!(!should_alert(clock.as_ref(), &alerted, &"worker", &POLICY));
+        clock.advance(1);
+        assert!(should_alert(clock.as_ref(), &alerted, &"worker", &POLICY));
+    }
+
+    #[test]
+    fn streak_resets_after_a_check_below_threshold() {
+        let config = Config {
+            threshold: 50.0,
+            cooldown_seconds: 600,
+            max_cooldown_seconds: 3600,
+            ..Config::default()
+        };
+        let mut processes = CannedProcesses::new(1, 8 << 30);
+        processes.insert(CannedProcess::new(100, "worker", 95.0));
+        let mut watcher = Watcher::new(&config, &processes).unwrap();
+        let clock = ManualClock::new();
+        watcher.set_clock(clock.clone());
+        let check = |watcher: &mut Watcher, processes: &CannedProcesses| {
+            let alerts = watcher.check(processes, None);
+            watcher.mark_alerted(&alerts);
+            !alerts.is_empty()
+        };
+        let streak = |watcher: &Watcher| watcher.alerted().values().map(|cooldown| cooldown.streak).max();
+
+        // Процесс всё время горячий: 600, затем 1200 секунд до следующего оповещения
+        assert!(check(&mut watcher, &processes));
+        clock.advance(600);
+        assert!(check(&mut watcher, &processes));
+        clock.advance(600);
+        assert!(!check(&mut watcher, &processes));
+        clock.advance(600);
+        assert!(check(&mut watcher, &processes));
+        assert_eq!(streak(&watcher), Some(3));
+
+        // Одна проверка ниже порога обнуляет серию, и кулдаун снова базовый
+        processes.get_mut(100).unwrap().cpu_usage = 10.0;
+        clock.advance(60);
+        assert!(!check(&mut watcher, &processes));
+        assert_eq!(streak(&watcher), Some(0));
+        processes.get_mut(100).unwrap().cpu_usage = 95.0;
+        clock.advance(540);
+        assert!(check(&mut watcher, &processes));
+        assert_eq!(watcher.alerted().keys().map(|(_, kind)| *kind).collect::<Vec<_>>(), [AlertKind::Cpu]);
+        assert_eq!(streak(&watcher), Some(1));
+    }
+}
@@ -0,0 +1,59 @@
+use std::io;
+use std::time::Duration;
+use thiserror::Error;
+
+/// Ошибки загрузки настроек и доставки уведомлений.
+///
+/// Для [`crate::Notifier`] любая из них — временный сбой, который имеет смысл повторить;
+/// окончательный отказ сервиса — `Ok(false)`.
+#[derive(Debug, Error)]
+pub enum WatcherError {
+    /// Сбой соединения или таймаут HTTP-запроса. В тексте нет URL: в нём токен бота.
+    #[error("network error: {0}")]
+    Network(String),
+    /// Сервис ответил 5xx или 429.
+    #[error("{service} server error: {status}")]
+    Server {
+        service: &'static str,
+        status: reqwest::StatusCode,
+    },
+    /// Telegram вернул ответ, который не удалось разобрать, или отказал в запросе.
+    #[error("Telegram API error: {0}")]
+    TelegramApi(String),
+    /// Файл настроек или секретов с ошибкой.
+    #[error("{0}")]
+    Config(String),
+    /// Ошибка ввода-вывода; `context` — что делалось и с каким файлом или сокетом.
+    #[error("{context}: {source}")]
+    Io {
+        context: String,
+        #[source]
+        source: io::Error,
+    },
+    /// Отправка пропущена: канал подряд не отвечал, и предохранитель ещё не дал новую попытку.
+    #[error("notifier circuit is open, next attempt in {}s", retry_in.as_secs())]
+    CircuitOpen { retry_in: Duration },
+    /// Не удалось закодировать JSON-строку для сокета.
+    #[error("cannot encode JSON: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+impl WatcherError {
+    pub fn io(context: impl Into<String>, source: io::Error) -> Self {
+        WatcherError::Io {
+            context: context.into(),
+            source,
+        }
+    }
+}
+
+// Текст ошибки reqwest с причиной, но без URL
+impl From<reqwest::Error> for WatcherError {
+    fn from(e: reqwest::Error) -> Self {
+        let e = e.without_url();
+        WatcherError::Network(match std::error::Error::source(&e) {
+            Some(source) => format!("{}: {}", e, source),
+            None => e.to_string(),
+        })
+    }
+}
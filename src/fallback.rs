@@ -0,0 +1,64 @@
+use crate::ratelimit::RateLimiter;
+use async_trait::async_trait;
+use cpu_watcher::{Alert, Notifier, SendResult};
+use log::warn;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// FALLBACK_BACKEND: запасной канал, когда основной не доставил сообщение и после всех
+// повторов. Сам запасной канал больше никуда не откатывается, а его отправки ограничены
+// FALLBACK_MAX_PER_HOUR, чтобы долгий сбой основного не превратился в поток сообщений
+pub struct FallbackNotifier {
+    pub primary: Box<dyn Notifier>,
+    pub fallback: Box<dyn Notifier>,
+    pub name: String,
+    limiter: Mutex<RateLimiter>,
+}
+
+impl FallbackNotifier {
+    pub fn new(primary: Box<dyn Notifier>, fallback: Box<dyn Notifier>, name: String, max_per_hour: usize) -> Self {
+        FallbackNotifier {
+            primary,
+            fallback,
+            name,
+            limiter: Mutex::new(RateLimiter::new(max_per_hour, Duration::from_secs(3600))),
+        }
+    }
+
+    // Можно ли сейчас отправить через запасной канал; результат основного пишется в лог
+    fn take_fallback(&self, primary: &SendResult) -> bool {
+        let reason = match primary {
+            Ok(true) => return false,
+            Ok(false) => "rejected".to_string(),
+            Err(e) => e.to_string(),
+        };
+        let allowed = self.limiter.lock().is_ok_and(|mut limiter| limiter.try_acquire(Instant::now()));
+        if allowed {
+            warn!(event = "fallback_used", backend = self.name.as_str(); "Primary notifier failed ({}), sending via fallback {}", reason, self.name);
+        } else {
+            warn!(event = "fallback_limited", backend = self.name.as_str(); "Primary notifier failed ({}), fallback {} is over its hourly limit, message dropped", reason, self.name);
+        }
+        allowed
+    }
+}
+
+#[async_trait]
+impl Notifier for FallbackNotifier {
+    async fn send(&self, text: &str) -> SendResult {
+        let result = self.primary.send(text).await;
+        if self.take_fallback(&result) {
+            self.fallback.send(text).await
+        } else {
+            result
+        }
+    }
+
+    async fn send_alerts(&self, alerts: &[Alert], text: &str) -> SendResult {
+        let result = self.primary.send_alerts(alerts, text).await;
+        if self.take_fallback(&result) {
+            self.fallback.send_alerts(alerts, text).await
+        } else {
+            result
+        }
+    }
+}
@@ -0,0 +1,268 @@
+use log::{debug, warn};
+use regex::Regex;
+use std::collections::HashSet;
+use crate::source::ProcessView;
+use sysinfo::{Pid, Uid, Users};
+
+// Элемент списка: литерал сравнивается с именем процесса без учёта регистра,
+// "re:<pattern>" — регулярное выражение по имени или полной командной строке,
+// "env:KEY=VALUE" — переменная окружения процесса (без "=VALUE" — любое значение)
+pub enum Pattern {
+    Literal(String),
+    Regex(Regex),
+    Env { key: String, value: Option<String> },
+}
+
+impl Pattern {
+    pub fn parse(entry: &str) -> Result<Self, String> {
+        if let Some(variable) = entry.strip_prefix("env:") {
+            let (key, value) = match variable.split_once('=') {
+                Some((key, value)) => (key, Some(value.to_string())),
+                None => (variable, None),
+            };
+            if key.is_empty() {
+                return Err(format!("empty variable name in {:?}", entry));
+            }
+            return Ok(Pattern::Env {
+                key: key.to_string(),
+                value,
+            });
+        }
+        match entry.strip_prefix("re:") {
+            Some(pattern) => Regex::new(pattern)
+                .map(Pattern::Regex)
+                .map_err(|e| format!("invalid regex {:?}: {}", pattern, e)),
+            None => Ok(Pattern::Literal(entry.to_lowercase())),
+        }
+    }
+
+    pub fn matches<F: FnOnce() -> Option<String>>(&self, name: &str, cmdline: &mut LazyCmdline<'_, F>) -> bool {
+        match self {
+            Pattern::Literal(literal) => *literal == name.to_lowercase(),
+            Pattern::Regex(regex) => {
+                regex.is_match(name) || cmdline.get().is_some_and(|cmd| regex.is_match(cmd))
+            }
+            Pattern::Env { key, value } => {
+                let Some(found) = cmdline.env(key) else {
+                    return false;
+                };
+                let found = found.to_string();
+                if value.as_ref().is_some_and(|value| *value != found) {
+                    return false;
+                }
+                cmdline.record_env(format!("{}={}", key, found));
+                true
+            }
+        }
+    }
+}
+
+// Командная строка и окружение читаются из /proc только если до них дошло дело, и не больше
+// одного раза. Заодно запоминаются переменные, совпавшие с шаблонами env:, — для уведомления
+pub struct LazyCmdline<'a, F> {
+    pid: Pid,
+    process: &'a dyn ProcessView,
+    init: Option<F>,
+    value: Option<String>,
+    environ: Option<Option<Vec<(String, String)>>>,
+    matched_env: Vec<String>,
+}
+
+impl<'a, F: FnOnce() -> Option<String>> LazyCmdline<'a, F> {
+    pub fn new(pid: Pid, process: &'a dyn ProcessView, init: F) -> Self {
+        LazyCmdline {
+            pid,
+            process,
+            init: Some(init),
+            value: None,
+            environ: None,
+            matched_env: Vec::new(),
+        }
+    }
+
+    pub fn get(&mut self) -> Option<&str> {
+        if let Some(init) = self.init.take() {
+            self.value = init();
+        }
+        self.value.as_deref()
+    }
+
+    fn env(&mut self, key: &str) -> Option<&str> {
+        let (pid, process) = (self.pid, self.process);
+        let environ = self.environ.get_or_insert_with(|| {
+            let environ = process.environ();
+            if environ.is_none() {
+                debug!("Cannot read environment of PID {}, env: patterns do not match it", pid);
+            }
+            environ
+        });
+        environ
+            .as_ref()?
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn record_env(&mut self, entry: String) {
+        if !self.matched_env.contains(&entry) {
+            self.matched_env.push(entry);
+        }
+    }
+
+    // Переменные вида KEY=VALUE, по которым процесс подошёл под шаблоны env:
+    pub fn matched_env(&self) -> &[String] {
+        &self.matched_env
+    }
+}
+
+// Фильтр процессов по имени или командной строке.
+// IGNORE_PROCESSES исключает всегда, даже если процесс подходит под ONLY_PROCESSES.
+pub struct ProcessFilter {
+    only: Vec<Pattern>,
+    ignore: Vec<Pattern>,
+}
+
+impl ProcessFilter {
+    pub fn new(only: &[String], ignore: &[String]) -> Result<Self, String> {
+        let parse = |entries: &[String]| {
+            entries
+                .iter()
+                .map(|entry| Pattern::parse(entry))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        Ok(ProcessFilter {
+            only: parse(only)?,
+            ignore: parse(ignore)?,
+        })
+    }
+
+    pub fn allows<F: FnOnce() -> Option<String>>(&self, name: &str, cmdline: &mut LazyCmdline<'_, F>) -> bool {
+        if self.ignore.iter().any(|pattern| pattern.matches(name, cmdline)) {
+            return false;
+        }
+        self.only.is_empty() || self.only.iter().any(|pattern| pattern.matches(name, cmdline))
+    }
+}
+
+// Фильтр по владельцу процесса: WATCH_USERS / IGNORE_USERS, имена через запятую.
+// Если UID не удаётся сопоставить с именем (пользователь удалён, UID из контейнера),
+// процесс сравнивается по числовому UID, а без UID вообще — не проходит WATCH_USERS.
+pub struct UserFilter {
+    watch: Vec<String>,
+    ignore: Vec<String>,
+    users: Users,
+    // UID, для которых список пользователей уже перечитывали и имени так и не нашли
+    unresolved: HashSet<Uid>,
+}
+
+impl UserFilter {
+    pub fn new(watch: &[String], ignore: &[String]) -> Self {
+        let users = if watch.is_empty() && ignore.is_empty() {
+            Users::new()
+        } else {
+            Users::new_with_refreshed_list()
+        };
+        UserFilter {
+            watch: watch.to_vec(),
+            ignore: ignore.to_vec(),
+            users,
+            unresolved: HashSet::new(),
+        }
+    }
+
+    pub fn allows(&mut self, uid: Option<&Uid>) -> bool {
+        if self.watch.is_empty() && self.ignore.is_empty() {
+            return true;
+        }
+        let user = uid.map(|uid| self.resolve(uid));
+        let listed = |list: &[String]| user.as_ref().is_some_and(|user| list.iter().any(|entry| entry == user));
+        !listed(&self.ignore) && (self.watch.is_empty() || listed(&self.watch))
+    }
+
+    fn resolve(&mut self, uid: &Uid) -> String {
+        if self.users.get_user_by_id(uid).is_none() && !self.unresolved.contains(uid) {
+            // Возможно, пользователя добавили после запуска
+            self.users.refresh_list();
+            if self.users.get_user_by_id(uid).is_none() {
+                warn!("Cannot resolve UID {} to a user name, matching by UID", **uid);
+                self.unresolved.insert(uid.clone());
+            }
+        }
+        match self.users.get_user_by_id(uid) {
+            Some(user) => user.name().to_string(),
+            None => (**uid).to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::CannedProcess;
+
+    fn allows(filter: &ProcessFilter, process: &CannedProcess) -> bool {
+        let mut cmdline = LazyCmdline::new(process.pid, process, || process.cmdline.clone());
+        filter.allows(&process.name, &mut cmdline)
+    }
+
+    fn filter(only: &[&str], ignore: &[&str]) -> ProcessFilter {
+        let list = |entries: &[&str]| entries.iter().map(|entry| entry.to_string()).collect::<Vec<_>>();
+        ProcessFilter::new(&list(only), &list(ignore)).unwrap()
+    }
+
+    fn python_script() -> CannedProcess {
+        CannedProcess {
+            cmdline: Some("/usr/bin/python3 /opt/jobs/report.py --daily".to_string()),
+            ..CannedProcess::new(100, "python3", 0.0)
+        }
+    }
+
+    #[test]
+    fn literal_matches_whole_name_ignoring_case() {
+        let process = python_script();
+        assert!(allows(&filter(&["Python3"], &[]), &process));
+        assert!(!allows(&filter(&["python"], &[]), &process));
+        // Литерал не смотрит в командную строку
+        assert!(!allows(&filter(&["report.py"], &[]), &process));
+    }
+
+    #[test]
+    fn regex_matches_name_or_cmdline() {
+        let process = python_script();
+        assert!(allows(&filter(&["re:^py"], &[]), &process));
+        assert!(allows(&filter(&[r"re:report\.py"], &[]), &process));
+        assert!(!allows(&filter(&["re:^report"], &[]), &process));
+    }
+
+    #[test]
+    fn regex_on_name_does_not_read_cmdline() {
+        let process = python_script();
+        let filter = filter(&["re:^python"], &[]);
+        let mut cmdline = LazyCmdline::new(process.pid, &process, || -> Option<String> {
+            panic!("cmdline read although the name matched")
+        });
+        assert!(filter.allows(&process.name, &mut cmdline));
+    }
+
+    #[test]
+    fn ignore_wins_over_only() {
+        let process = python_script();
+        assert!(!allows(&filter(&["python3"], &["re:--daily"]), &process));
+        assert!(allows(&filter(&["python3"], &["re:--hourly"]), &process));
+    }
+
+    #[test]
+    fn unreadable_cmdline_matches_regex_by_name_only() {
+        let process = CannedProcess {
+            cmdline: None,
+            ..python_script()
+        };
+        assert!(!allows(&filter(&[r"re:report\.py"], &[]), &process));
+        assert!(allows(&filter(&["re:python"], &[]), &process));
+    }
+
+    #[test]
+    fn invalid_regex_is_rejected() {
+        assert!(ProcessFilter::new(&["re:(".to_string()], &[]).is_err());
+    }
+}
@@ -0,0 +1,273 @@
+use crate::config::{Config, MemoryThreshold};
+use log::{info, warn};
+use std::collections::HashMap;
+use std::ffi::{c_char, c_int, c_uint, c_void, CStr};
+use std::time::Instant;
+use sysinfo::{Pid, System};
+
+// Библиотека драйвера грузится при запуске через dlopen: сборка не зависит от CUDA,
+// а на машине без драйвера проверка GPU просто выключается
+const NVML_LIBRARY: &[u8] = b"libnvidia-ml.so.1\0";
+const NVML_SUCCESS: c_int = 0;
+const NAME_BUFFER_SIZE: usize = 96;
+// Больше процессов на одной карте почти не бывает; если их больше, NVML вернёт ошибку
+// и процесс в уведомлении не покажется
+const MAX_GPU_PROCESSES: usize = 64;
+
+type Device = *mut c_void;
+
+#[repr(C)]
+#[derive(Default)]
+struct Utilization {
+    gpu: c_uint,
+    memory: c_uint,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct Memory {
+    total: u64,
+    free: u64,
+    used: u64,
+}
+
+// nvmlProcessInfo_v2_t
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct RunningProcess {
+    pid: c_uint,
+    used_gpu_memory: u64,
+    gpu_instance_id: c_uint,
+    compute_instance_id: c_uint,
+}
+
+// Функции NVML, которые нужны проверке
+struct Nvml {
+    handle: *mut c_void,
+    shutdown: unsafe extern "C" fn() -> c_int,
+    device_count: unsafe extern "C" fn(*mut c_uint) -> c_int,
+    device_by_index: unsafe extern "C" fn(c_uint, *mut Device) -> c_int,
+    device_name: unsafe extern "C" fn(Device, *mut c_char, c_uint) -> c_int,
+    utilization: unsafe extern "C" fn(Device, *mut Utilization) -> c_int,
+    memory: unsafe extern "C" fn(Device, *mut Memory) -> c_int,
+    processes: unsafe extern "C" fn(Device, *mut c_uint, *mut RunningProcess) -> c_int,
+}
+
+// SAFETY: NVML потокобезопасна, а указатель на библиотеку используется только для dlclose
+unsafe impl Send for Nvml {}
+
+impl Nvml {
+    fn load() -> Result<Self, String> {
+        // SAFETY: dlopen получает корректную C-строку; результат проверяется на null
+        let handle = unsafe { libc::dlopen(NVML_LIBRARY.as_ptr().cast(), libc::RTLD_NOW) };
+        if handle.is_null() {
+            return Err("libnvidia-ml.so.1 not found (no NVIDIA driver?)".to_string());
+        }
+
+        // Сначала ищутся все функции: если какой-то нет (старый драйвер), библиотека выгружается,
+        // так и не инициализированной
+        // SAFETY: сигнатуры повторяют объявления из nvml.h
+        let (init, nvml) = match unsafe { Nvml::resolve(handle) } {
+            Ok(resolved) => resolved,
+            Err(e) => {
+                // SAFETY: handle получен из dlopen и больше нигде не используется
+                unsafe { libc::dlclose(handle) };
+                return Err(e);
+            }
+        };
+        // SAFETY: nvmlInit_v2 без аргументов
+        let rc = unsafe { init() };
+        if rc != NVML_SUCCESS {
+            // Drop вызвал бы nvmlShutdown для неинициализированной библиотеки
+            std::mem::forget(nvml);
+            // SAFETY: как выше
+            unsafe { libc::dlclose(handle) };
+            return Err(format!("nvmlInit failed with code {}", rc));
+        }
+        Ok(nvml)
+    }
+
+    // SAFETY: вызывающий передаёт handle из dlopen
+    unsafe fn resolve(handle: *mut c_void) -> Result<(unsafe extern "C" fn() -> c_int, Nvml), String> {
+        let init = symbol(handle, b"nvmlInit_v2\0")?;
+        let nvml = Nvml {
+            handle,
+            shutdown: symbol(handle, b"nvmlShutdown\0")?,
+            device_count: symbol(handle, b"nvmlDeviceGetCount_v2\0")?,
+            device_by_index: symbol(handle, b"nvmlDeviceGetHandleByIndex_v2\0")?,
+            device_name: symbol(handle, b"nvmlDeviceGetName\0")?,
+            utilization: symbol(handle, b"nvmlDeviceGetUtilizationRates\0")?,
+            memory: symbol(handle, b"nvmlDeviceGetMemoryInfo\0")?,
+            processes: symbol(handle, b"nvmlDeviceGetComputeRunningProcesses_v2\0")?,
+        };
+        Ok((init, nvml))
+    }
+}
+
+impl Drop for Nvml {
+    fn drop(&mut self) {
+        // SAFETY: библиотека инициализирована в load и больше не используется
+        unsafe {
+            (self.shutdown)();
+            libc::dlclose(self.handle);
+        }
+    }
+}
+
+// SAFETY: вызывающий отвечает за то, что T — указатель на функцию с верной сигнатурой,
+// а name завершается нулём
+unsafe fn symbol<T: Copy>(handle: *mut c_void, name: &[u8]) -> Result<T, String> {
+    let ptr = libc::dlsym(handle, name.as_ptr().cast());
+    if ptr.is_null() {
+        let name = String::from_utf8_lossy(&name[..name.len() - 1]);
+        return Err(format!("NVML symbol {} not found (driver too old?)", name));
+    }
+    Ok(std::mem::transmute_copy(&ptr))
+}
+
+/// Что превысила видеокарта.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GpuAlertKind {
+    Utilization,
+    Memory,
+}
+
+/// Видеокарта выше порога и процесс, занявший на ней больше всего памяти.
+pub struct GpuAlert {
+    pub index: u32,
+    pub name: String,
+    pub triggers: Vec<GpuAlertKind>,
+    pub utilization: u32,
+    pub memory_used: u64,
+    pub memory_total: u64,
+    /// PID, имя и занятая им видеопамять, если NVML отдала список процессов.
+    pub top_process: Option<(Pid, String, u64)>,
+}
+
+/// Проверка загрузки и памяти видеокарт со своим кулдауном; уведомления уходят тем же каналом.
+pub struct GpuMonitor {
+    nvml: Nvml,
+    utilization_threshold: Option<f32>,
+    memory_threshold: Option<MemoryThreshold>,
+    cooldown_seconds: u64,
+    alerted: HashMap<(u32, GpuAlertKind), Instant>,
+}
+
+impl GpuMonitor {
+    /// None, если GPU-пороги не заданы или NVML недоступна (тогда пишется предупреждение).
+    pub fn new(config: &Config) -> Option<GpuMonitor> {
+        if config.gpu_threshold.is_none() && config.gpu_memory_threshold.is_none() {
+            return None;
+        }
+        let nvml = match Nvml::load() {
+            Ok(nvml) => nvml,
+            Err(e) => {
+                warn!("GPU monitoring disabled: {}", e);
+                return None;
+            }
+        };
+        let mut count = 0;
+        // SAFETY: NVML инициализирована, count — корректный указатель
+        unsafe { (nvml.device_count)(&mut count) };
+        info!("GPU monitoring enabled ({} device(s))", count);
+        Some(GpuMonitor {
+            nvml,
+            utilization_threshold: config.gpu_threshold,
+            memory_threshold: config.gpu_memory_threshold,
+            cooldown_seconds: config.cooldown_seconds,
+            alerted: HashMap::new(),
+        })
+    }
+
+    /// Видеокарты выше порогов, о которых пора оповестить. Ошибки NVML по отдельной карте
+    /// пишутся в лог, карта пропускается.
+    pub fn check(&mut self, sys: &System, now: Instant) -> Vec<GpuAlert> {
+        let mut count = 0;
+        // SAFETY: NVML инициализирована, count — корректный указатель
+        if unsafe { (self.nvml.device_count)(&mut count) } != NVML_SUCCESS {
+            warn!("nvmlDeviceGetCount failed");
+            return Vec::new();
+        }
+        (0..count).filter_map(|index| self.check_device(index, sys, now)).collect()
+    }
+
+    fn check_device(&self, index: u32, sys: &System, now: Instant) -> Option<GpuAlert> {
+        let nvml = &self.nvml;
+        let mut device: Device = std::ptr::null_mut();
+        let mut utilization = Utilization::default();
+        let mut memory = Memory::default();
+        let mut name = [0 as c_char; NAME_BUFFER_SIZE];
+        // SAFETY: все указатели на локальные переменные нужного размера
+        let rc = unsafe {
+            let rc = (nvml.device_by_index)(index, &mut device);
+            if rc != NVML_SUCCESS {
+                rc
+            } else {
+                (nvml.device_name)(device, name.as_mut_ptr(), NAME_BUFFER_SIZE as c_uint);
+                let rc = (nvml.utilization)(device, &mut utilization);
+                if rc != NVML_SUCCESS {
+                    rc
+                } else {
+                    (nvml.memory)(device, &mut memory)
+                }
+            }
+        };
+        if rc != NVML_SUCCESS {
+            warn!("Cannot read GPU {}: NVML error {}", index, rc);
+            return None;
+        }
+
+        let mut triggers = Vec::new();
+        if self.utilization_threshold.is_some_and(|threshold| utilization.gpu as f32 >= threshold) {
+            triggers.push(GpuAlertKind::Utilization);
+        }
+        if self.memory_threshold.is_some_and(|threshold| memory.used >= threshold.limit_bytes(memory.total)) {
+            triggers.push(GpuAlertKind::Memory);
+        }
+        triggers.retain(|kind| match self.alerted.get(&(index, *kind)) {
+            Some(last) => now.saturating_duration_since(*last).as_secs() >= self.cooldown_seconds,
+            None => true,
+        });
+        if triggers.is_empty() {
+            return None;
+        }
+
+        // SAFETY: name заполнен NVML и завершён нулём (буфер обнулён заранее)
+        let name = unsafe { CStr::from_ptr(name.as_ptr()) }.to_string_lossy().into_owned();
+        let top_process = self.top_process(device).map(|(pid, used)| {
+            let process_name = sys.process(pid).map_or_else(|| "?".to_string(), |p| p.name().to_string());
+            (pid, process_name, used)
+        });
+        Some(GpuAlert {
+            index,
+            name,
+            triggers,
+            utilization: utilization.gpu,
+            memory_used: memory.used,
+            memory_total: memory.total,
+            top_process,
+        })
+    }
+
+    // Процесс с наибольшим объёмом видеопамяти: NVML не даёт загрузку по процессам дёшево
+    fn top_process(&self, device: Device) -> Option<(Pid, u64)> {
+        let mut processes = [RunningProcess::default(); MAX_GPU_PROCESSES];
+        let mut count = MAX_GPU_PROCESSES as c_uint;
+        // SAFETY: count — размер буфера processes
+        let rc = unsafe { (self.nvml.processes)(device, &mut count, processes.as_mut_ptr()) };
+        if rc != NVML_SUCCESS {
+            return None;
+        }
+        processes[..count as usize]
+            .iter()
+            .max_by_key(|process| process.used_gpu_memory)
+            .map(|process| (Pid::from_u32(process.pid), process.used_gpu_memory))
+    }
+
+    /// Запоминает отправленное оповещение: до конца кулдауна оно не повторится.
+    pub fn mark_alerted(&mut self, alert: &GpuAlert, now: Instant) {
+        for kind in &alert.triggers {
+            self.alerted.insert((alert.index, *kind), now);
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use std::time::{Duration, SystemTime};
+
+// Случайный сдвиг паузы между проверками: watcher'ы на многих машинах, запущенные
+// одновременно, не обновляют процессы в один и тот же момент. Криптостойкость не нужна,
+// поэтому xorshift64*, засеянный PID и временем запуска
+pub struct Jitter {
+    max_ms: u64,
+    state: u64,
+}
+
+impl Jitter {
+    pub fn new(max_ms: u64) -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos() as u64);
+        let seed = nanos ^ (u64::from(std::process::id()) << 32);
+        Jitter {
+            max_ms,
+            // Нулевое состояние xorshift не покидает
+            state: seed | 1,
+        }
+    }
+
+    fn next(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    // interval ± max_ms, но не меньше нуля
+    pub fn apply(&mut self, interval: Duration) -> Duration {
+        if self.max_ms == 0 {
+            return interval;
+        }
+        let offset = (self.next() % (2 * self.max_ms + 1)) as i64 - self.max_ms as i64;
+        let ms = interval.as_millis() as i64 + offset;
+        Duration::from_millis(ms.max(0) as u64)
+    }
+}
@@ -0,0 +1,1497 @@
+//! Поиск процессов, превысивших пороги CPU и памяти, и доставка уведомлений о них.
+//!
+//! Бинарник `cpu_watcher` — тонкая обёртка: он в цикле обновляет [`sysinfo::System`],
+//! вызывает [`Watcher::check`] и отправляет найденное через [`Notifier`]. Для разовой
+//! проверки без собственного цикла есть [`check_processes`].
+
+pub mod actions;
+mod breach;
+mod clock;
+pub mod config;
+mod container;
+mod cooldown;
+pub mod error;
+mod filter;
+#[cfg(feature = "gpu")]
+pub mod gpu;
+pub mod locale;
+pub mod markup;
+pub mod message;
+pub mod network;
+pub mod notify;
+pub mod procfs;
+pub mod restart;
+mod rules;
+mod source;
+pub mod template;
+mod tree;
+mod trend;
+pub mod zombie;
+
+pub use actions::Action;
+pub use clock::{Clock, SystemClock};
+pub use config::Config;
+pub use cooldown::{should_alert, Cooldown, CooldownPolicy};
+pub use error::WatcherError;
+pub use notify::{Notifier, SendResult};
+pub use source::{CannedProcess, CannedProcesses, ProcessSource, ProcessView};
+pub use trend::Trend;
+
+use actions::RuleAction;
+use breach::BreachHistory;
+use chrono::{DateTime, Utc};
+use config::{DedupBy, RefreshMode, ThresholdMode};
+use filter::{LazyCmdline, Pattern, ProcessFilter, UserFilter};
+use log::{debug, warn};
+use network::NamespaceTraffic;
+use rules::RuleSet;
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use sysinfo::{Pid, ProcessRefreshKind, ProcessStatus, System, UpdateKind};
+use tree::ProcessTree;
+use trend::CpuHistory;
+
+/// Условие, по которому сработало оповещение.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertKind {
+    Cpu,
+    Memory,
+    Runtime,
+    #[serde(rename = "disk_io")]
+    DiskIo,
+    #[serde(rename = "open_files")]
+    OpenFiles,
+    Threads,
+    Network,
+    Score,
+    Swap,
+    #[serde(rename = "d_state")]
+    DState,
+    #[serde(rename = "cpu_time")]
+    CpuTime,
+}
+
+// Имена как в JSON: "cpu", "disk_io", "d_state", "cpu_time"
+impl std::str::FromStr for AlertKind {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, ()> {
+        match value {
+            "cpu" => Ok(AlertKind::Cpu),
+            "memory" => Ok(AlertKind::Memory),
+            "runtime" => Ok(AlertKind::Runtime),
+            "disk_io" => Ok(AlertKind::DiskIo),
+            "open_files" => Ok(AlertKind::OpenFiles),
+            "threads" => Ok(AlertKind::Threads),
+            "network" => Ok(AlertKind::Network),
+            "score" => Ok(AlertKind::Score),
+            "swap" => Ok(AlertKind::Swap),
+            "d_state" => Ok(AlertKind::DState),
+            "cpu_time" => Ok(AlertKind::CpuTime),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Насколько процесс превысил порог: отношение значения к порогу сравнивается
+/// с `warning_multiplier` и `critical_multiplier`.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Critical,
+}
+
+impl Severity {
+    /// Значок в начале уведомления.
+    pub fn emoji(self) -> &'static str {
+        match self {
+            Severity::Info => "ℹ",
+            Severity::Warning => "⚠",
+            Severity::Critical => "🔥",
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Critical => "critical",
+        })
+    }
+}
+
+/// Скорость чтения и записи диска процессом, байт в секунду.
+#[derive(Clone, Copy, Debug)]
+pub struct DiskRate {
+    pub read_per_sec: u64,
+    pub write_per_sec: u64,
+}
+
+/// Скорость сетевого трафика, байт в секунду.
+#[derive(Clone, Copy, Debug)]
+pub struct NetRate {
+    pub rx_per_sec: u64,
+    pub tx_per_sec: u64,
+}
+
+/// Комбинированная нагрузка: `cpu_weight × cpu_percent + memory_weight × memory_percent`.
+#[derive(Clone, Copy, Debug)]
+pub struct Score {
+    pub value: f32,
+    /// CPU в режиме `threshold_mode`.
+    pub cpu_percent: f32,
+    /// Доля всей RAM, %.
+    pub memory_percent: f32,
+}
+
+/// Процесс, превысивший порог, со всем, что попадает в уведомление.
+pub struct ProcessInfo {
+    pub name: String,
+    pub pid: Pid,
+    /// CPU в режиме `threshold_mode` (per-core или normalized).
+    pub cpu_percent: f32,
+    /// Из скольких замеров усреднён `cpu_percent` (только при `samples_per_interval > 1`).
+    pub cpu_samples: Option<u32>,
+    /// Сколько потомков учтено в `cpu_percent` (только при `aggregate_tree`).
+    pub children: Option<usize>,
+    /// Время CPU с запуска процесса (user + system, с потомками при суммировании дерева).
+    /// Только Linux: вне его и при нечитаемом /proc/PID/stat — None.
+    pub cpu_time: Option<Duration>,
+    /// Порог CPU, с которым сравнивали процесс, и правило, откуда он взят.
+    pub cpu_threshold: f32,
+    pub rule: Option<String>,
+    /// Правило процесса с действием (`action` не notify): только для таких процессов под
+    /// оповещением в Telegram есть кнопки Kill и Renice.
+    pub actionable: bool,
+    /// Направление и пик CPU за последние `trend_window` проверок.
+    pub trend: Option<Trend>,
+    pub memory_bytes: u64,
+    pub cmdline: String,
+    /// Полный путь к исполняемому файлу (только при `resolve_exe`; None, если не прочитался).
+    pub exe_path: Option<String>,
+    /// Переменные `KEY=VALUE`, по которым процесс подошёл под шаблоны `env:` (только Linux).
+    pub matched_env: Vec<String>,
+    pub create_time: Option<DateTime<Utc>>,
+    /// Сколько процесс непрерывно держится выше порога CPU (только при `sustained_seconds > 0`).
+    pub over_limit_for: Option<Duration>,
+    /// Скорость дискового ввода-вывода с прошлой проверки (только при `disk_io_threshold`).
+    pub disk_io: Option<DiskRate>,
+    /// Число открытых файловых дескрипторов (только при `fd_threshold`, только Linux).
+    pub open_files: Option<usize>,
+    /// Число потоков (только при `thread_threshold`, только Linux).
+    pub threads: Option<usize>,
+    /// Объём процесса в свопе, байт (только при `swap_threshold`, только Linux).
+    pub swap_bytes: Option<u64>,
+    /// Комбинированная нагрузка CPU и памяти (только при `score_threshold`).
+    pub score: Option<Score>,
+    /// Трафик сетевого пространства имён, которое создал этот процесс (только при
+    /// `net_threshold`, только Linux; трафик хоста — см. [`network::SystemNetwork`]).
+    pub network: Option<NetRate>,
+    /// Сколько процесс уже работает (только при `max_runtime_seconds`).
+    pub age: Option<Duration>,
+    /// Сколько процесс непрерывно в состоянии D (только при `d_state_seconds`).
+    pub d_state_for: Option<Duration>,
+    /// Функция ядра, в которой процесс ждёт, из /proc/PID/wchan (только при оповещении о
+    /// состоянии D, только Linux).
+    pub wchan: Option<String>,
+    /// Что сделали с процессом по правилу (renice/kill) и чем это кончилось.
+    pub action: Option<String>,
+    /// Полный ID контейнера Docker/containerd, если процесс в контейнере (только Linux).
+    pub container: Option<String>,
+    /// Процесс из `critical_processes`: о нём оповещают и в тихие часы.
+    pub critical: bool,
+    /// Уровень по самому сильному из сработавших условий.
+    pub severity: Severity,
+}
+
+/// Строка `--top`: процесс и порог, с которым его сравнила бы проверка.
+pub struct TopProcess {
+    pub name: String,
+    pub pid: Pid,
+    /// CPU в режиме `threshold_mode`, без суммирования потомков.
+    pub cpu_percent: f32,
+    pub cpu_threshold: f32,
+    pub cmdline: String,
+}
+
+/// Процесс, о котором оповещали и который всю проверку провёл ниже всех порогов.
+pub struct Recovery {
+    pub pid: Pid,
+    pub name: String,
+}
+
+/// Ключ кулдауна: конкретный процесс либо одна и та же команда (имя) в любых PID.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum DedupKey {
+    Pid(Pid),
+    Cmdline(String),
+    Name(String),
+}
+
+/// Процесс и условия, по которым он сработал в этой проверке.
+pub struct Alert {
+    pub process: ProcessInfo,
+    pub triggers: Vec<AlertKind>,
+    /// Ключ, под которым оповещение попадёт в кулдаун (см. `dedup_by`).
+    pub key: DedupKey,
+}
+
+/// Перемена в чтении /proc, о которой стоит сообщить (см. [`Watcher::take_proc_health`]).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProcHealth {
+    /// Больше `PROC_FAILURE_RATIO` чтений командных строк завершились ошибкой.
+    Degraded { failures: usize, attempts: usize },
+    /// Чтения снова проходят.
+    Recovered,
+}
+
+// Доля неудачных чтений /proc, после которой watcher считает себя деградировавшим
+const PROC_FAILURE_RATIO: f32 = 0.8;
+// Чтения копятся, пока их не наберётся столько: по одному-двум судить рано
+const MIN_PROC_READS: usize = 10;
+
+// Попытки и неудачи чтения /proc/PID/cmdline. Завершившийся процесс неудачей не считается
+#[derive(Default)]
+struct ProcReads {
+    attempts: Cell<usize>,
+    failures: Cell<usize>,
+}
+
+// Командная строка из источника процессов. Завершившийся процесс (NotFound) неудачей не считается
+fn read_cmdline(process: &dyn ProcessView, reads: &ProcReads) -> Option<String> {
+    reads.attempts.set(reads.attempts.get() + 1);
+    match process.cmdline() {
+        Ok(cmdline) => cmdline,
+        Err(e) => {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                reads.failures.set(reads.failures.get() + 1);
+            }
+            None
+        }
+    }
+}
+
+// Время CPU процесса, а при суммировании дерева — вместе с живыми потомками
+fn total_cpu_time(sys: &dyn ProcessSource, pid: Pid, process: &dyn ProcessView, tree: Option<&ProcessTree>) -> Option<Duration> {
+    let own = process.cpu_time()?;
+    let children = tree.map_or(Duration::ZERO, |tree| {
+        tree.descendants(pid)
+            .into_iter()
+            .filter_map(|child| sys.process(child)?.cpu_time())
+            .sum()
+    });
+    Some(own + children)
+}
+
+// Скорость по разнице накопительных счётчиков с прошлой проверки; для первой — None
+fn disk_rate(
+    samples: &mut HashMap<Pid, (u64, u64, Instant)>,
+    pid: Pid,
+    process: &dyn ProcessView,
+    now: Instant,
+) -> Option<DiskRate> {
+    let usage = process.disk_usage();
+    let current = (usage.total_read_bytes, usage.total_written_bytes, now);
+    let (read, written, at) = samples.insert(pid, current)?;
+    let elapsed = now.saturating_duration_since(at).as_secs_f64();
+    if elapsed <= 0.0 {
+        return None;
+    }
+    let per_sec = |delta: u64| (delta as f64 / elapsed) as u64;
+    Some(DiskRate {
+        read_per_sec: per_sec(usage.total_read_bytes.saturating_sub(read)),
+        write_per_sec: per_sec(usage.total_written_bytes.saturating_sub(written)),
+    })
+}
+
+/// Состояние мониторинга между проверками: фильтры, правила, начало затяжных превышений,
+/// история CPU и кулдауны уже отправленных оповещений.
+pub struct Watcher {
+    threshold: f32,
+    threshold_mode: ThresholdMode,
+    sustained_seconds: u64,
+    cooldown: CooldownPolicy,
+    // COOLDOWNS: свой кулдаун у отдельных условий, остальные — по COOLDOWN_SECONDS
+    cooldowns: HashMap<AlertKind, CooldownPolicy>,
+    clock: Arc<dyn Clock>,
+    dedup_by: DedupBy,
+    enable_actions: bool,
+    resolve_exe: bool,
+    refresh_kind: ProcessRefreshKind,
+    memory_limit: Option<u64>,
+    swap_limit: Option<u64>,
+    max_runtime: Option<Duration>,
+    d_state_limit: Option<Duration>,
+    cpu_time_budget: Option<Duration>,
+    disk_io_threshold: Option<u64>,
+    fd_threshold: Option<usize>,
+    thread_threshold: Option<usize>,
+    score_threshold: Option<f32>,
+    score_weights: (f32, f32),
+    total_memory: u64,
+    net_threshold: Option<u64>,
+    warning_multiplier: f32,
+    critical_multiplier: f32,
+    min_process_age: Duration,
+    min_cpu_time: Duration,
+    // Момент прошлой проверки: от него считается потраченное с тех пор время CPU
+    last_check: Option<Instant>,
+    aggregate_tree: bool,
+    aggregate_names: Vec<Pattern>,
+    only_running: bool,
+    cpu_count: f32,
+    filter: ProcessFilter,
+    user_filter: UserFilter,
+    critical: Vec<Pattern>,
+    rules: RuleSet,
+    // Момент, с которого процесс непрерывно держится выше порога CPU
+    breach_started: HashMap<Pid, Instant>,
+    // Момент, с которого процесс непрерывно в состоянии D
+    d_state_since: HashMap<Pid, Instant>,
+    breach_history: BreachHistory,
+    // SAMPLES_PER_INTERVAL: сумма и число промежуточных замеров CPU с прошлой проверки
+    cpu_samples: HashMap<Pid, (f32, u32)>,
+    cpu_history: CpuHistory,
+    // Счётчики прочитанных/записанных байт на прошлой проверке и её время
+    disk_samples: HashMap<Pid, (u64, u64, Instant)>,
+    net_traffic: NamespaceTraffic,
+    proc_reads: ProcReads,
+    proc_degraded: bool,
+    proc_health: Option<ProcHealth>,
+    notify_recovery: bool,
+    // PID, о которых оповещали и которые с тех пор не опускались ниже порогов
+    firing: HashMap<Pid, String>,
+    recoveries: Vec<Recovery>,
+    // Заглушённые вручную (кнопка Mute в Telegram): до какого момента не оповещать
+    muted: HashMap<DedupKey, Instant>,
+    alerted: HashMap<(DedupKey, AlertKind), Cooldown>,
+}
+
+impl Watcher {
+    /// Собирает фильтры и правила из конфигурации. `sys` нужен для числа ядер и объёма RAM.
+    pub fn new(config: &Config, sys: &dyn ProcessSource) -> Result<Watcher, String> {
+        let filter = ProcessFilter::new(&config.only_processes, &config.ignore_processes)
+            .map_err(|e| format!("process filter: {}", e))?;
+        let rules = RuleSet::new(&config.rules)?;
+        let breach_history = BreachHistory::new(config.breach_count, config.breach_window)?;
+        if !(config.warning_multiplier > 0.0 && config.warning_multiplier <= config.critical_multiplier) {
+            return Err(format!(
+                "WARNING_MULTIPLIER ({}) must be positive and not above CRITICAL_MULTIPLIER ({})",
+                config.warning_multiplier, config.critical_multiplier
+            ));
+        }
+        let critical = config
+            .critical_processes
+            .iter()
+            .map(|entry| Pattern::parse(entry))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("critical processes: {}", e))?;
+        let aggregate_names = config
+            .aggregate_names
+            .iter()
+            .map(|entry| Pattern::parse(entry))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("aggregate names: {}", e))?;
+        if config.aggregate_tree && config.only_processes.is_empty() && config.watch_pid.is_none() && config.watch_pidfile.is_none() {
+            warn!("AGGREGATE_TREE without ONLY_PROCESSES: init and session leaders will sum up CPU of the whole system");
+        }
+        if !config.enable_actions {
+            for rule in rules.with_actions() {
+                warn!("Rule {:?} has action {:?}, but ENABLE_ACTIONS is off: only notifying", rule.label, rule.action.action);
+            }
+        }
+
+        Ok(Watcher {
+            threshold: config.threshold,
+            threshold_mode: config.threshold_mode,
+            sustained_seconds: config.sustained_seconds,
+            cooldown: CooldownPolicy {
+                base_seconds: config.cooldown_seconds,
+                max_seconds: config.max_cooldown_seconds,
+            },
+            cooldowns: config
+                .cooldowns
+                .iter()
+                .map(|(kind, seconds)| {
+                    let policy = CooldownPolicy {
+                        base_seconds: *seconds,
+                        max_seconds: config.max_cooldown_seconds,
+                    };
+                    (*kind, policy)
+                })
+                .collect(),
+            clock: Arc::new(SystemClock),
+            dedup_by: config.dedup_by,
+            enable_actions: config.enable_actions,
+            resolve_exe: config.resolve_exe,
+            refresh_kind: process_refresh_kind(config),
+            memory_limit: config.memory_threshold.map(|t| t.limit_bytes(sys.total_memory())),
+            // Доля от нуля — порог 0, а свопа без раздела подкачки всё равно нет
+            swap_limit: config
+                .swap_threshold
+                .filter(|_| sys.total_swap() > 0)
+                .map(|t| t.limit_bytes(sys.total_swap())),
+            max_runtime: config.max_runtime_seconds.map(Duration::from_secs),
+            d_state_limit: config.d_state_seconds.map(Duration::from_secs),
+            cpu_time_budget: config.cpu_time_budget.map(Duration::from_secs),
+            disk_io_threshold: config.disk_io_threshold,
+            fd_threshold: config.fd_threshold,
+            thread_threshold: config.thread_threshold,
+            score_threshold: config.score_threshold,
+            score_weights: (config.score_cpu_weight, config.score_memory_weight),
+            total_memory: sys.total_memory(),
+            net_threshold: config.net_threshold,
+            warning_multiplier: config.warning_multiplier,
+            critical_multiplier: config.critical_multiplier,
+            min_process_age: Duration::from_secs(config.min_process_age_seconds),
+            min_cpu_time: Duration::from_millis(config.min_cpu_time_ms),
+            last_check: None,
+            aggregate_tree: config.aggregate_tree,
+            aggregate_names,
+            only_running: config.only_running,
+            cpu_count: sys.cpu_count().max(1) as f32,
+            filter,
+            user_filter: UserFilter::new(&config.watch_users, &config.ignore_users),
+            critical,
+            rules,
+            breach_started: HashMap::new(),
+            d_state_since: HashMap::new(),
+            breach_history,
+            cpu_samples: HashMap::new(),
+            cpu_history: CpuHistory::new(config.trend_window),
+            disk_samples: HashMap::new(),
+            net_traffic: NamespaceTraffic::new(),
+            proc_reads: ProcReads::default(),
+            proc_degraded: false,
+            proc_health: None,
+            notify_recovery: config.notify_recovery,
+            firing: HashMap::new(),
+            recoveries: Vec::new(),
+            muted: HashMap::new(),
+            alerted: HashMap::new(),
+        })
+    }
+
+    /// Что обновлять у процессов перед проверкой (см. [`process_refresh_kind`]).
+    pub fn refresh_kind(&self) -> ProcessRefreshKind {
+        self.refresh_kind
+    }
+
+    /// Порог памяти в байтах, если он задан.
+    pub fn memory_limit(&self) -> Option<u64> {
+        self.memory_limit
+    }
+
+    /// Порог свопа в байтах, если он задан и в системе есть своп.
+    pub fn swap_limit(&self) -> Option<u64> {
+        self.swap_limit
+    }
+
+    /// Время последних оповещений по (ключ, условие) — для сохранения между запусками.
+    pub fn alerted(&self) -> &HashMap<(DedupKey, AlertKind), Cooldown> {
+        &self.alerted
+    }
+
+    // Суммируется ли CPU процесса с потомками: все при AGGREGATE_TREE, иначе по AGGREGATE_NAMES
+    fn aggregates<F: FnOnce() -> Option<String>>(&self, name: &str, cmdline: &mut LazyCmdline<'_, F>) -> bool {
+        self.aggregate_tree || self.aggregate_names.iter().any(|pattern| pattern.matches(name, cmdline))
+    }
+
+    // Кулдаун условия из COOLDOWNS или общий
+    fn cooldown_for(&self, kind: AlertKind) -> &CooldownPolicy {
+        self.cooldowns.get(&kind).unwrap_or(&self.cooldown)
+    }
+
+    /// Запоминает CPU процессов после промежуточного обновления `sys`: следующая проверка
+    /// сравнит с порогом среднее этих замеров и своего (SAMPLES_PER_INTERVAL).
+    pub fn record_sample(&mut self, sys: &dyn ProcessSource) {
+        for (pid, process) in sys.processes() {
+            let (sum, count) = self.cpu_samples.entry(*pid).or_default();
+            *sum += process.cpu_usage();
+            *count += 1;
+        }
+    }
+
+    // CPU процесса, усреднённый с промежуточными замерами, и число замеров
+    fn sampled_cpu(&self, pid: Pid, process: &dyn ProcessView) -> (f32, u32) {
+        let (sum, count) = self.cpu_samples.get(&pid).copied().unwrap_or_default();
+        ((sum + process.cpu_usage()) / (count + 1) as f32, count + 1)
+    }
+
+    /// Восстанавливает кулдауны, сохранённые прошлым запуском.
+    pub fn restore_alerted(&mut self, alerted: HashMap<(DedupKey, AlertKind), Cooldown>) {
+        self.alerted = alerted;
+    }
+
+    /// Подменяет часы (по умолчанию [`SystemClock`]): так тесты проверяют кулдауны без ожидания.
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    /// Проверяет процессы из уже обновлённого `sys` и возвращает те, о которых пора оповестить
+    /// (с учётом SUSTAINED_SECONDS и кулдауна). `watched` ограничивает проверку набором PID.
+    /// Действия правил (renice/kill) выполняются здесь же.
+    pub fn check(&mut self, sys: &dyn ProcessSource, watched: Option<&HashSet<Pid>>) -> Vec<Alert> {
+        let now = self.clock.now();
+        let instant = self.clock.monotonic();
+        self.net_traffic.begin();
+        let mut alerts = Vec::new();
+        let mut seen = HashSet::new();
+        // Условия, которые сейчас превышены (даже если оповещение ещё рано слать)
+        let mut hot = HashSet::new();
+        let mut hot_pids = HashSet::new();
+        // Ключи процессов, пропущенных из-за ONLY_RUNNING: их серию кулдауна не трогаем
+        let mut asleep = HashSet::new();
+        let tree = (self.aggregate_tree || !self.aggregate_names.is_empty()).then(|| ProcessTree::new(sys));
+        let since_last_check = self.last_check.replace(instant).map(|at| instant.saturating_duration_since(at));
+
+        for (pid, process) in sys.processes() {
+            if watched.is_some_and(|tree| !tree.contains(pid)) {
+                continue;
+            }
+            let mut cmdline = LazyCmdline::new(*pid, process, || read_cmdline(process, &self.proc_reads));
+            // Дерево процесса, если его CPU суммируется с потомками
+            let own_tree = tree.as_ref().filter(|_| self.aggregates(process.name(), &mut cmdline));
+            // Спящий в момент замера процесс мог только что работать: состояние неизвестно,
+            // поэтому ни затяжное превышение, ни серию кулдауна, ни оповещение о восстановлении он не сбрасывает
+            // Процесс в состоянии D не в R, но при D_STATE_SECONDS он как раз и нужен
+            let stuck = process.status() == ProcessStatus::UninterruptibleDiskSleep;
+            if self.only_running && !(stuck && self.d_state_limit.is_some()) && !running(sys, *pid, process, own_tree) {
+                hot_pids.insert(*pid);
+                asleep.insert(self.dedup_key(*pid, process, &mut cmdline));
+                continue;
+            }
+            if !self.filter.allows(process.name(), &mut cmdline) || !self.user_filter.allows(process.user_id()) {
+                continue;
+            }
+            // Короткоживущие процессы (компиляторы, конвейеры shell) пропускаем, пока не подрастут
+            let age = match process.start_time() {
+                0 => None,
+                start_time => now.duration_since(SystemTime::UNIX_EPOCH + Duration::from_secs(start_time)).ok(),
+            };
+            if age.is_some_and(|age| age < self.min_process_age) {
+                continue;
+            }
+            let rule = self.rules.find(process.name(), &mut cmdline);
+            let cpu_threshold = rule.map_or(self.threshold, |rule| rule.threshold);
+
+            // В режиме дерева CPU потомков прибавляется к родителю. Потомок, чей родитель сам
+            // проходит фильтры и суммирует потомков (AGGREGATE_TREE или AGGREGATE_NAMES), уже
+            // учтён в родителе и отдельно не проверяется.
+            if tree.is_some() {
+                let parent = process.parent().and_then(|ppid| Some((ppid, sys.process(ppid)?)));
+                if let Some((ppid, parent)) = parent {
+                    let mut parent_cmdline = LazyCmdline::new(ppid, parent, || read_cmdline(parent, &self.proc_reads));
+                    if self.filter.allows(parent.name(), &mut parent_cmdline)
+                        && self.user_filter.allows(parent.user_id())
+                        && self.aggregates(parent.name(), &mut parent_cmdline)
+                    {
+                        continue;
+                    }
+                }
+            }
+            let (own_cpu, samples) = self.sampled_cpu(*pid, process);
+            let (raw_cpu, children) = match own_tree {
+                Some(tree) => {
+                    let descendants = tree.descendants(*pid);
+                    let children_cpu: f32 = descendants
+                        .iter()
+                        .filter_map(|child| Some((*child, sys.process(*child)?)))
+                        .map(|(child, process)| self.sampled_cpu(child, process).0)
+                        .sum();
+                    (own_cpu + children_cpu, Some(descendants.len()))
+                }
+                None => (own_cpu, None),
+            };
+            let cpu = match self.threshold_mode {
+                ThresholdMode::PerCore => raw_cpu,
+                ThresholdMode::Normalized => raw_cpu / self.cpu_count,
+            };
+            let memory = process.memory();
+            self.cpu_history.record(*pid, cpu);
+
+            let mut triggers = Vec::new();
+            let mut over_limit_for = None;
+            // MIN_CPU_TIME_MS: процент за короткий промежуток — это всего несколько миллисекунд.
+            // Время CPU — процент (per-core, с потомками) за время с прошлой проверки, но не
+            // дольше, чем процесс живёт; если ни то, ни другое неизвестно, граница не применяется
+            let window = match (since_last_check, age) {
+                (Some(since), Some(age)) => Some(since.min(age)),
+                (since, age) => since.or(age),
+            };
+            let cpu_time = window.map(|window| window.mul_f32(raw_cpu.max(0.0) / 100.0));
+            let negligible = cpu_time.is_some_and(|time| time < self.min_cpu_time);
+            let over_cpu = cpu >= cpu_threshold && !negligible;
+            // BREACH_COUNT из BREACH_WINDOW: прерывистые превышения тоже засчитываются
+            let frequent = self.breach_history.record(*pid, over_cpu);
+            if over_cpu {
+                let since = *self.breach_started.entry(*pid).or_insert(instant);
+                let elapsed = instant.saturating_duration_since(since);
+                if frequent && elapsed.as_secs() >= self.sustained_seconds {
+                    triggers.push(AlertKind::Cpu);
+                    if self.sustained_seconds > 0 {
+                        over_limit_for = Some(elapsed);
+                    }
+                }
+            } else {
+                self.breach_started.remove(pid);
+            }
+            if self.memory_limit.is_some_and(|limit| memory >= limit) {
+                triggers.push(AlertKind::Memory);
+            }
+            // Возраст — независимое условие: зависший процесс может почти не тратить CPU
+            if self.max_runtime.is_some_and(|max| age.is_some_and(|age| age >= max)) {
+                triggers.push(AlertKind::Runtime);
+            }
+            let disk_io = self.disk_io_threshold.and_then(|_| disk_rate(&mut self.disk_samples, *pid, process, instant));
+            if let (Some(limit), Some(rate)) = (self.disk_io_threshold, disk_io) {
+                if rate.read_per_sec + rate.write_per_sec >= limit {
+                    triggers.push(AlertKind::DiskIo);
+                }
+            }
+            let open_files = self.fd_threshold.and_then(|_| process.open_files());
+            if self.fd_threshold.is_some_and(|limit| open_files.is_some_and(|count| count >= limit)) {
+                triggers.push(AlertKind::OpenFiles);
+            }
+            let threads = self.thread_threshold.and_then(|_| process.threads());
+            if self.thread_threshold.is_some_and(|limit| threads.is_some_and(|count| count >= limit)) {
+                triggers.push(AlertKind::Threads);
+            }
+            // Процесс, который не вылезает из свопа, тормозит, хотя CPU и RSS у него скромные
+            let swap = self.swap_limit.and_then(|_| process.swap());
+            if self.swap_limit.is_some_and(|limit| swap.is_some_and(|bytes| bytes > 0 && bytes >= limit)) {
+                triggers.push(AlertKind::Swap);
+            }
+            // Непрерываемый сон обычно значит зависший диск или NFS: kill такой процесс не берёт
+            let d_state_for = match self.d_state_limit {
+                Some(_) if stuck => {
+                    let since = *self.d_state_since.entry(*pid).or_insert(instant);
+                    Some(instant.saturating_duration_since(since))
+                }
+                _ => {
+                    self.d_state_since.remove(pid);
+                    None
+                }
+            };
+            if self.d_state_limit.is_some_and(|limit| d_state_for.is_some_and(|elapsed| elapsed >= limit)) {
+                triggers.push(AlertKind::DState);
+            }
+            // Медленная утечка CPU: процент невысокий, но за дни набегают часы процессорного времени
+            let total_cpu = self.cpu_time_budget.and_then(|_| total_cpu_time(sys, *pid, process, own_tree));
+            if self.cpu_time_budget.is_some_and(|budget| total_cpu.is_some_and(|total| total >= budget)) {
+                triggers.push(AlertKind::CpuTime);
+            }
+            // Ни CPU, ни память по отдельности могут не дотягивать до порогов, а вместе — да
+            let score = self.score_threshold.map(|_| {
+                let memory_percent = if self.total_memory > 0 {
+                    memory as f32 / self.total_memory as f32 * 100.0
+                } else {
+                    0.0
+                };
+                Score {
+                    value: self.score_weights.0 * cpu + self.score_weights.1 * memory_percent,
+                    cpu_percent: cpu,
+                    memory_percent,
+                }
+            });
+            if let (Some(limit), Some(score)) = (self.score_threshold, score) {
+                if score.value >= limit {
+                    triggers.push(AlertKind::Score);
+                }
+            }
+            let network = self.net_threshold.and_then(|_| {
+                let parent = process.parent().and_then(|ppid| Some((ppid, sys.process(ppid)?)));
+                self.net_traffic.rate(*pid, process, parent, instant)
+            });
+            if let (Some(limit), Some(rate)) = (self.net_threshold, network) {
+                if rate.rx_per_sec + rate.tx_per_sec >= limit {
+                    triggers.push(AlertKind::Network);
+                }
+            }
+
+            if triggers.is_empty() && !over_cpu {
+                continue;
+            }
+            hot_pids.insert(*pid);
+
+            let key = self.dedup_key(*pid, process, &mut cmdline);
+            if over_cpu {
+                hot.insert((key.clone(), AlertKind::Cpu));
+            }
+            for kind in &triggers {
+                hot.insert((key.clone(), *kind));
+            }
+            // Заглушённый процесс остаётся «горячим»: серия кулдауна после mute не сбрасывается
+            if self.muted.get(&key).is_some_and(|until| instant < *until) {
+                continue;
+            }
+
+            // Кулдаун отдельный для каждого условия: алерт по CPU не глушит алерт по памяти.
+            // Одинаковые команды в разных PID за одну проверку тоже дают одно оповещение.
+            triggers.retain(|kind| {
+                let entry = (key.clone(), *kind);
+                should_alert(self.clock.as_ref(), &self.alerted, &entry, self.cooldown_for(*kind)) && seen.insert(entry)
+            });
+
+            if triggers.is_empty() {
+                continue;
+            }
+
+            // Во сколько раз превышен порог: берём самое сильное из сработавших условий
+            let ratio = triggers
+                .iter()
+                .map(|kind| match kind {
+                    AlertKind::Cpu => over(cpu as f64, cpu_threshold as f64),
+                    AlertKind::Memory => over(memory as f64, self.memory_limit.unwrap_or_default() as f64),
+                    AlertKind::Runtime => over(
+                        age.unwrap_or_default().as_secs_f64(),
+                        self.max_runtime.unwrap_or_default().as_secs_f64(),
+                    ),
+                    AlertKind::DiskIo => over(
+                        disk_io.map_or(0, |rate| rate.read_per_sec + rate.write_per_sec) as f64,
+                        self.disk_io_threshold.unwrap_or_default() as f64,
+                    ),
+                    AlertKind::OpenFiles => {
+                        over(open_files.unwrap_or_default() as f64, self.fd_threshold.unwrap_or_default() as f64)
+                    }
+                    AlertKind::Threads => {
+                        over(threads.unwrap_or_default() as f64, self.thread_threshold.unwrap_or_default() as f64)
+                    }
+                    AlertKind::Swap => over(swap.unwrap_or_default() as f64, self.swap_limit.unwrap_or_default() as f64),
+                    AlertKind::DState => over(
+                        d_state_for.unwrap_or_default().as_secs_f64(),
+                        self.d_state_limit.unwrap_or_default().as_secs_f64(),
+                    ),
+                    AlertKind::CpuTime => over(
+                        total_cpu.unwrap_or_default().as_secs_f64(),
+                        self.cpu_time_budget.unwrap_or_default().as_secs_f64(),
+                    ),
+                    AlertKind::Score => over(
+                        score.map_or(0.0, |score| score.value) as f64,
+                        self.score_threshold.unwrap_or_default() as f64,
+                    ),
+                    AlertKind::Network => over(
+                        network.map_or(0, |rate| rate.rx_per_sec + rate.tx_per_sec) as f64,
+                        self.net_threshold.unwrap_or_default() as f64,
+                    ),
+                })
+                .fold(0.0, f32::max);
+            let severity = self.severity(ratio);
+
+            let critical = self.critical.iter().any(|pattern| pattern.matches(process.name(), &mut cmdline));
+            let wchan = triggers.contains(&AlertKind::DState).then(|| process.wchan()).flatten();
+            let matched_env = cmdline.matched_env().to_vec();
+
+            // Получаем полную командную строку как в psutil. Если её нет, потому что процесс успел
+            // завершиться после обновления списка, оповещение было бы о призраке — пропускаем его
+            let cmdline = match cmdline.get() {
+                Some(cmdline) => cmdline.to_string(),
+                None if process.exited() => {
+                    debug!(event = "process_exited"; "Process {} (PID {}) exited before the alert, skipping", process.name(), pid);
+                    continue;
+                }
+                None => process.name().to_string(),
+            };
+
+            let create_time = match process.start_time() {
+                0 => None,
+                start_time => {
+                    Some(DateTime::<Utc>::from(SystemTime::UNIX_EPOCH + Duration::from_secs(start_time)))
+                }
+            };
+
+            // Действия применяются только к процессам, попавшим под правило, и только по CPU
+            let action = match rule {
+                Some(rule) if self.enable_actions && triggers.contains(&AlertKind::Cpu) => {
+                    actions::perform(&rule.action, *pid, process.start_time())
+                }
+                _ => None,
+            };
+
+            let process = ProcessInfo {
+                name: process.name().to_string(),
+                pid: *pid,
+                cpu_percent: cpu,
+                cpu_samples: Some(samples).filter(|samples| *samples > 1),
+                children,
+                // Без CPU_TIME_BUDGET время читается только для оповещения: файл на процесс
+                cpu_time: total_cpu.or_else(|| total_cpu_time(sys, *pid, process, own_tree)),
+                cpu_threshold,
+                rule: rule.map(|rule| rule.label.clone()),
+                actionable: rule.is_some_and(|rule| rule.action.action != Action::Notify),
+                trend: self.cpu_history.trend(*pid),
+                memory_bytes: memory,
+                cmdline,
+                exe_path: self.resolve_exe.then(|| process.exe()).flatten(),
+                matched_env,
+                create_time,
+                over_limit_for,
+                disk_io,
+                open_files,
+                threads,
+                swap_bytes: swap,
+                score,
+                network,
+                age: age.filter(|_| self.max_runtime.is_some()),
+                d_state_for,
+                wchan,
+                action,
+                container: process.container_id(),
+                critical,
+                severity,
+            };
+            alerts.push(Alert { process, triggers, key });
+        }
+
+        // Одна проверка без превышения обнуляет серию: следующее оповещение снова с обычным кулдауном.
+        // Спящий в момент замера процесс ниже порога не был — о нём просто ничего не известно
+        for (key, cooldown) in self.alerted.iter_mut() {
+            if !hot.contains(key) && !asleep.contains(&key.0) {
+                cooldown.streak = 0;
+            }
+        }
+        self.cpu_samples.clear();
+        self.update_proc_health();
+
+        // Процесс, о котором оповещали, всю проверку был ниже порогов; завершившиеся не в счёт
+        let cooled: Vec<Pid> = self.firing.keys().filter(|pid| !hot_pids.contains(*pid)).copied().collect();
+        for pid in cooled {
+            if let Some(name) = self.firing.remove(&pid) {
+                let alive = sys.process(pid).is_some_and(|process| process.status() != ProcessStatus::Zombie);
+                if alive {
+                    self.recoveries.push(Recovery { pid, name });
+                }
+            }
+        }
+
+        alerts
+    }
+
+    fn dedup_key<F: FnOnce() -> Option<String>>(&self, pid: Pid, process: &dyn ProcessView, cmdline: &mut LazyCmdline<'_, F>) -> DedupKey {
+        match self.dedup_by {
+            DedupBy::Pid => DedupKey::Pid(pid),
+            DedupBy::Cmdline => DedupKey::Cmdline(cmdline.get().unwrap_or_else(|| process.name()).to_string()),
+            DedupBy::Name => DedupKey::Name(process.name().to_string()),
+        }
+    }
+
+    /// Не оповещать о процессе `pid` в течение `duration` (по ключу DEDUP_BY, то есть и о его
+    /// «тёзках» при dedup по имени или командной строке). `start_time` защищает от PID,
+    /// выданного уже другому процессу. Возвращает имя процесса.
+    pub fn mute(&mut self, sys: &dyn ProcessSource, pid: Pid, start_time: Option<u64>, duration: Duration) -> Result<String, String> {
+        let process = sys
+            .process(pid)
+            .filter(|process| match start_time {
+                Some(start_time) => process.start_time() == start_time,
+                None => true,
+            })
+            .ok_or_else(|| format!("PID {} is no longer running", pid))?;
+        let mut cmdline = LazyCmdline::new(pid, process, || read_cmdline(process, &self.proc_reads));
+        let key = self.dedup_key(pid, process, &mut cmdline);
+        self.muted.insert(key, self.clock.monotonic() + duration);
+        Ok(process.name().to_string())
+    }
+
+    /// Снимает [`mute`](Watcher::mute) с процесса `pid`. Возвращает имя процесса.
+    pub fn unmute(&mut self, sys: &dyn ProcessSource, pid: Pid) -> Result<String, String> {
+        let process = sys.process(pid).ok_or_else(|| format!("PID {} is no longer running", pid))?;
+        let mut cmdline = LazyCmdline::new(pid, process, || read_cmdline(process, &self.proc_reads));
+        let key = self.dedup_key(pid, process, &mut cmdline);
+        match self.muted.remove(&key) {
+            Some(_) => Ok(process.name().to_string()),
+            None => Err(format!("{} (PID {}) is not muted", process.name(), pid)),
+        }
+    }
+
+    /// Kill или renice по кнопке в Telegram. Процесс проходит те же проверки, что и перед
+    /// действием по правилу: действия включены (`enable_actions`), процесс подходит под правило
+    /// с действием не notify, а PID всё ещё у процесса со временем старта `start_time` (если задано).
+    /// Уровень nice и пауза перед SIGKILL берутся из правила. Возвращает строку для ответа.
+    pub fn act(&mut self, sys: &dyn ProcessSource, pid: Pid, start_time: Option<u64>, action: Action) -> Result<String, String> {
+        let process = sys
+            .process(pid)
+            .filter(|process| match start_time {
+                Some(start_time) => process.start_time() == start_time,
+                None => true,
+            })
+            .ok_or_else(|| format!("PID {} is no longer running", pid))?;
+        let name = process.name();
+        if !self.enable_actions {
+            return Err(format!("{} (PID {}): actions are disabled (ENABLE_ACTIONS)", name, pid));
+        }
+        let mut cmdline = LazyCmdline::new(pid, process, || read_cmdline(process, &self.proc_reads));
+        let rule = self
+            .rules
+            .find(name, &mut cmdline)
+            .filter(|rule| rule.action.action != Action::Notify)
+            .ok_or_else(|| format!("{} (PID {}) matches no rule with an action", name, pid))?;
+        let rule_action = RuleAction { action, ..rule.action };
+        let outcome = actions::perform(&rule_action, pid, process.start_time()).unwrap_or_default();
+        Ok(format!("{} (PID {}): {}", name, pid, outcome))
+    }
+
+    /// Процессы, вернувшиеся ниже порогов с прошлого вызова (только при `notify_recovery`).
+    pub fn take_recoveries(&mut self) -> Vec<Recovery> {
+        std::mem::take(&mut self.recoveries)
+    }
+
+    // Набралось достаточно чтений /proc — сравниваем долю неудач с порогом и начинаем заново
+    fn update_proc_health(&mut self) {
+        let attempts = self.proc_reads.attempts.get();
+        if attempts < MIN_PROC_READS {
+            return;
+        }
+        let failures = self.proc_reads.failures.take();
+        self.proc_reads.attempts.set(0);
+        let degraded = failures as f32 / attempts as f32 > PROC_FAILURE_RATIO;
+        if degraded != self.proc_degraded {
+            self.proc_degraded = degraded;
+            self.proc_health = Some(if degraded {
+                ProcHealth::Degraded { failures, attempts }
+            } else {
+                ProcHealth::Recovered
+            });
+        }
+    }
+
+    /// Перемена в чтении /proc с прошлого вызова: о деградации сообщается один раз,
+    /// повторно — только после восстановления.
+    pub fn take_proc_health(&mut self) -> Option<ProcHealth> {
+        self.proc_health.take()
+    }
+
+    fn severity(&self, ratio: f32) -> Severity {
+        if ratio >= self.critical_multiplier {
+            Severity::Critical
+        } else if ratio >= self.warning_multiplier {
+            Severity::Warning
+        } else {
+            Severity::Info
+        }
+    }
+
+    /// `count` процессов с наибольшим CPU так, как их видит проверка: с фильтрами, режимом
+    /// порога и порогами из правил. Кулдауны, действия и остальные условия не учитываются.
+    pub fn top(&mut self, sys: &dyn ProcessSource, count: usize) -> Vec<TopProcess> {
+        let mut candidates: Vec<(&Pid, &dyn ProcessView, f32)> = Vec::new();
+        for (pid, process) in sys.processes() {
+            let mut cmdline = LazyCmdline::new(*pid, process, || read_cmdline(process, &self.proc_reads));
+            if !self.filter.allows(process.name(), &mut cmdline) || !self.user_filter.allows(process.user_id()) {
+                continue;
+            }
+            let cpu = match self.threshold_mode {
+                ThresholdMode::PerCore => process.cpu_usage(),
+                ThresholdMode::Normalized => process.cpu_usage() / self.cpu_count,
+            };
+            candidates.push((pid, process, cpu));
+        }
+        candidates.sort_by(|a, b| b.2.total_cmp(&a.2));
+        candidates
+            .into_iter()
+            .take(count)
+            .map(|(pid, process, cpu)| {
+                let mut cmdline = LazyCmdline::new(*pid, process, || read_cmdline(process, &self.proc_reads));
+                let cpu_threshold = self
+                    .rules
+                    .find(process.name(), &mut cmdline)
+                    .map_or(self.threshold, |rule| rule.threshold);
+                TopProcess {
+                    name: process.name().to_string(),
+                    pid: *pid,
+                    cpu_percent: cpu,
+                    cpu_threshold,
+                    cmdline: cmdline.get().unwrap_or_else(|| process.name()).to_string(),
+                }
+            })
+            .collect()
+    }
+
+    /// Запоминает успешно отправленные оповещения: до конца кулдауна они не повторятся.
+    pub fn mark_alerted(&mut self, alerts: &[Alert]) {
+        let now = self.clock.monotonic();
+        for alert in alerts {
+            if self.notify_recovery {
+                self.firing.insert(alert.process.pid, alert.process.name.clone());
+            }
+            for kind in &alert.triggers {
+                let streak = self.alerted.get(&(alert.key.clone(), *kind)).map_or(0, |cooldown| cooldown.streak) + 1;
+                self.alerted.insert((alert.key.clone(), *kind), Cooldown { alerted_at: now, streak });
+            }
+        }
+    }
+
+    /// Применяет новую конфигурацию без перезапуска: пороги, фильтры и правила заменяются,
+    /// а кулдауны, начало затяжных превышений и счётчики трафика сохраняются. Истории CPU
+    /// и BREACH_WINDOW копятся заново — их окно могло измениться. При ошибке в `config`
+    /// текущие настройки остаются как были.
+    pub fn reload(&mut self, config: &Config, sys: &dyn ProcessSource) -> Result<(), String> {
+        let mut next = Watcher::new(config, sys)?;
+        next.clock = self.clock.clone();
+        std::mem::swap(&mut next.alerted, &mut self.alerted);
+        std::mem::swap(&mut next.muted, &mut self.muted);
+        std::mem::swap(&mut next.breach_started, &mut self.breach_started);
+        std::mem::swap(&mut next.d_state_since, &mut self.d_state_since);
+        std::mem::swap(&mut next.disk_samples, &mut self.disk_samples);
+        std::mem::swap(&mut next.net_traffic, &mut self.net_traffic);
+        std::mem::swap(&mut next.proc_reads, &mut self.proc_reads);
+        next.last_check = self.last_check;
+        next.proc_degraded = self.proc_degraded;
+        next.proc_health = self.proc_health.take();
+        // Без NOTIFY_RECOVERY список оповещённых PID не ведётся
+        if next.notify_recovery {
+            std::mem::swap(&mut next.firing, &mut self.firing);
+            std::mem::swap(&mut next.recoveries, &mut self.recoveries);
+        }
+        *self = next;
+        Ok(())
+    }
+
+    /// Выбрасывает устаревшие кулдауны и данные о процессах, которых больше нет.
+    pub fn prune(&mut self, sys: &dyn ProcessSource) {
+        let now = self.clock.monotonic();
+        let retention = self.cooldowns.values().map(CooldownPolicy::retention_seconds).fold(self.cooldown.retention_seconds(), u64::max);
+        let retention = Duration::from_secs(retention);
+        self.alerted.retain(|_, cooldown| now.saturating_duration_since(cooldown.alerted_at) < retention);
+        self.breach_started.retain(|pid, _| sys.process(*pid).is_some());
+        self.d_state_since.retain(|pid, _| sys.process(*pid).is_some());
+        self.breach_history.retain(|pid| sys.process(*pid).is_some());
+        self.cpu_history.retain(|pid| sys.process(*pid).is_some());
+        self.disk_samples.retain(|pid, _| sys.process(*pid).is_some());
+        self.firing.retain(|pid, _| sys.process(*pid).is_some());
+        self.muted.retain(|_, until| now < *until);
+    }
+}
+
+// ONLY_RUNNING: процесс в состоянии R; в режиме дерева — он сам или хотя бы один потомок
+fn running(sys: &dyn ProcessSource, pid: Pid, process: &dyn ProcessView, tree: Option<&ProcessTree>) -> bool {
+    let is_running = |process: &dyn ProcessView| process.status() == ProcessStatus::Run;
+    is_running(process)
+        || tree.is_some_and(|tree| {
+            tree.descendants(pid)
+                .iter()
+                .any(|child| sys.process(*child).is_some_and(is_running))
+        })
+}
+
+// Отношение значения к порогу; нулевой порог превышен всегда и сильно
+fn over(value: f64, limit: f64) -> f32 {
+    if limit > 0.0 {
+        (value / limit) as f32
+    } else {
+        f32::INFINITY
+    }
+}
+
+/// Что обновлять у процессов: при `RefreshMode::Minimal` — CPU и память, а диск, владельца,
+/// командную строку и путь к файлу — только если они нужны включённым проверкам.
+pub fn process_refresh_kind(config: &Config) -> ProcessRefreshKind {
+    if config.refresh_mode == RefreshMode::Full {
+        return ProcessRefreshKind::everything();
+    }
+    let mut kind = ProcessRefreshKind::new().with_cpu().with_memory();
+    if config.disk_io_threshold.is_some() {
+        kind = kind.with_disk_usage();
+    }
+    if !config.watch_users.is_empty() || !config.ignore_users.is_empty() {
+        kind = kind.with_user(UpdateKind::OnlyIfNotSet);
+    }
+    // На Linux командная строка и путь читаются из /proc напрямую, в остальных системах — из sysinfo
+    if cfg!(not(target_os = "linux")) {
+        kind = kind.with_cmd(UpdateKind::OnlyIfNotSet);
+        if config.resolve_exe {
+            kind = kind.with_exe(UpdateKind::OnlyIfNotSet);
+        }
+    }
+    kind
+}
+
+/// Обновляет память и процессоры (для порогов в процентах и нормализации CPU), затем процессы.
+pub fn refresh_system(sys: &mut System, kind: ProcessRefreshKind) {
+    sys.refresh_memory();
+    sys.refresh_cpu_usage();
+    sys.refresh_processes_specifics(kind);
+}
+
+/// Разовая проверка: обновляет список процессов в `sys` и возвращает те, что сейчас выше
+/// порогов. Действия правил не выполняются, кулдауны не ведутся, а `sustained_seconds > 0`
+/// при разовой проверке не срабатывает никогда. Для корректного CPU `sys` должен быть
+/// обновлён хотя бы раз заранее (sysinfo считает CPU между двумя обновлениями).
+/// Ошибка — некорректные фильтры или правила в `config`.
+pub fn check_processes(sys: &mut System, config: &Config) -> Result<Vec<ProcessInfo>, String> {
+    sys.refresh_processes_specifics(process_refresh_kind(config));
+    let mut watcher = Watcher::new(config, sys)?;
+    watcher.enable_actions = false;
+    let alerts = watcher.check(sys, None);
+    Ok(alerts.into_iter().map(|alert| alert.process).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+
+    fn watcher(config: &Config, processes: &CannedProcesses) -> (Watcher, Arc<ManualClock>) {
+        let mut watcher = Watcher::new(config, processes).unwrap();
+        let clock = ManualClock::new();
+        watcher.set_clock(clock.clone());
+        (watcher, clock)
+    }
+
+    fn alerted_pids(alerts: &[Alert]) -> Vec<u32> {
+        let mut pids: Vec<u32> = alerts.iter().map(|alert| alert.process.pid.as_u32()).collect();
+        pids.sort();
+        pids
+    }
+
+    // Проверка, после которой оповещения считаются отправленными (как в основном цикле)
+    fn check_and_mark(watcher: &mut Watcher, processes: &CannedProcesses) -> Vec<u32> {
+        let alerts = watcher.check(processes, None);
+        watcher.mark_alerted(&alerts);
+        alerted_pids(&alerts)
+    }
+
+    #[test]
+    fn alerts_when_cpu_crosses_threshold() {
+        let config = Config {
+            threshold: 80.0,
+            ..Config::default()
+        };
+        let mut processes = CannedProcesses::new(4, 8 << 30);
+        processes.insert(CannedProcess::new(100, "worker", 20.0));
+        processes.insert(CannedProcess::new(200, "idle", 1.0));
+        let (mut watcher, clock) = watcher(&config, &processes);
+
+        assert!(watcher.check(&processes, None).is_empty());
+
+        clock.advance(1);
+        processes.get_mut(100).unwrap().cpu_usage = 95.0;
+        let alerts = watcher.check(&processes, None);
+        assert_eq!(alerted_pids(&alerts), [100]);
+        assert_eq!(alerts[0].triggers, [AlertKind::Cpu]);
+        assert_eq!(alerts[0].process.cmdline, "worker");
+    }
+
+    #[test]
+    fn sustained_seconds_waits_for_continuous_breach() {
+        let config = Config {
+            threshold: 80.0,
+            sustained_seconds: 30,
+            ..Config::default()
+        };
+        let mut processes = CannedProcesses::new(4, 8 << 30);
+        processes.insert(CannedProcess::new(100, "worker", 95.0));
+        let (mut watcher, clock) = watcher(&config, &processes);
+
+        assert!(watcher.check(&processes, None).is_empty());
+        clock.advance(20);
+        assert!(watcher.check(&processes, None).is_empty());
+
+        // Провал ниже порога начинает отсчёт заново
+        clock.advance(5);
+        processes.get_mut(100).unwrap().cpu_usage = 10.0;
+        assert!(watcher.check(&processes, None).is_empty());
+        clock.advance(5);
+        processes.get_mut(100).unwrap().cpu_usage = 95.0;
+        assert!(watcher.check(&processes, None).is_empty());
+        clock.advance(29);
+        assert!(watcher.check(&processes, None).is_empty());
+
+        clock.advance(1);
+        let alerts = watcher.check(&processes, None);
+        assert_eq!(alerted_pids(&alerts), [100]);
+        assert_eq!(alerts[0].process.over_limit_for, Some(Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn canned_processes_do_not_read_host_proc() {
+        let config = Config {
+            threshold: 80.0,
+            cpu_time_budget: Some(60),
+            ..Config::default()
+        };
+        let mut processes = CannedProcesses::new(4, 8 << 30);
+        // PID 1 есть на любой машине, но его время CPU и контейнер берутся из полей
+        processes.insert(CannedProcess {
+            cpu_time: Some(Duration::from_secs(10)),
+            ..CannedProcess::new(1, "init", 95.0)
+        });
+        let (mut watcher, _clock) = watcher(&config, &processes);
+
+        let alerts = watcher.check(&processes, None);
+        assert_eq!(alerts[0].triggers, [AlertKind::Cpu]);
+        assert_eq!(alerts[0].process.cpu_time, Some(Duration::from_secs(10)));
+        assert_eq!(alerts[0].process.container, None);
+    }
+
+    // Пул воркеров: процесс завершается, и ту же команду продолжает новый PID
+    fn respawn_worker(dedup_by: DedupBy) -> Vec<Vec<u32>> {
+        let config = Config {
+            threshold: 50.0,
+            dedup_by,
+            ..Config::default()
+        };
+        let worker = |pid, cmdline: &str| CannedProcess {
+            cmdline: Some(cmdline.to_string()),
+            ..CannedProcess::new(pid, "python", 95.0)
+        };
+        let mut processes = CannedProcesses::new(1, 8 << 30);
+        processes.insert(worker(100, "python train.py"));
+        let (mut watcher, clock) = watcher(&config, &processes);
+
+        let mut rounds = vec![check_and_mark(&mut watcher, &processes)];
+        for (pid, cmdline) in [(101, "python train.py"), (102, "python eval.py")] {
+            clock.advance(60);
+            processes.processes.clear();
+            processes.insert(worker(pid, cmdline));
+            rounds.push(check_and_mark(&mut watcher, &processes));
+        }
+        rounds
+    }
+
+    #[test]
+    fn dedup_by_pid_alerts_for_every_new_pid() {
+        assert_eq!(respawn_worker(DedupBy::Pid), [vec![100], vec![101], vec![102]]);
+    }
+
+    #[test]
+    fn dedup_by_cmdline_silences_the_same_command_under_new_pids() {
+        assert_eq!(respawn_worker(DedupBy::Cmdline), [vec![100], vec![], vec![102]]);
+    }
+
+    #[test]
+    fn dedup_by_name_silences_any_command_of_the_same_name() {
+        assert_eq!(respawn_worker(DedupBy::Name), [vec![100], vec![], vec![]]);
+    }
+
+    #[test]
+    fn dedup_by_cmdline_alerts_once_per_check_for_parallel_workers() {
+        let config = Config {
+            threshold: 50.0,
+            dedup_by: DedupBy::Cmdline,
+            ..Config::default()
+        };
+        let mut processes = CannedProcesses::new(1, 8 << 30);
+        for pid in [100, 101, 102] {
+            processes.insert(CannedProcess::new(pid, "worker", 95.0));
+        }
+        let (mut watcher, _clock) = watcher(&config, &processes);
+        assert_eq!(check_and_mark(&mut watcher, &processes).len(), 1);
+    }
+
+    #[test]
+    fn pid_is_suppressed_during_cooldown_and_allowed_after() {
+        let config = Config {
+            threshold: 50.0,
+            cooldown_seconds: 600,
+            ..Config::default()
+        };
+        let mut processes = CannedProcesses::new(1, 8 << 30);
+        processes.insert(CannedProcess::new(100, "worker", 95.0));
+        let (mut watcher, clock) = watcher(&config, &processes);
+
+        assert_eq!(check_and_mark(&mut watcher, &processes), [100]);
+        clock.advance(599);
+        assert!(check_and_mark(&mut watcher, &processes).is_empty());
+        // Другой процесс кулдаун первого не задевает
+        processes.insert(CannedProcess::new(200, "worker", 95.0));
+        assert_eq!(check_and_mark(&mut watcher, &processes), [200]);
+        clock.advance(1);
+        assert_eq!(check_and_mark(&mut watcher, &processes), [100]);
+    }
+
+    #[test]
+    fn wall_clock_jump_does_not_move_cooldown() {
+        let config = Config {
+            threshold: 50.0,
+            cooldown_seconds: 600,
+            ..Config::default()
+        };
+        let mut processes = CannedProcesses::new(1, 8 << 30);
+        processes.insert(CannedProcess::new(100, "worker", 95.0));
+        let (mut watcher, clock) = watcher(&config, &processes);
+        let started = clock.now();
+
+        assert_eq!(check_and_mark(&mut watcher, &processes), [100]);
+        // Часы ушли на сутки вперёд: кулдаун не считается истёкшим
+        clock.set_wall(started + Duration::from_secs(86_400));
+        clock.advance(10);
+        assert!(check_and_mark(&mut watcher, &processes).is_empty());
+        // И на час назад: кулдаун не растягивается
+        clock.set_wall(started - Duration::from_secs(3600));
+        clock.advance(590);
+        assert_eq!(check_and_mark(&mut watcher, &processes), [100]);
+    }
+
+    fn rule(pattern: &str, action: Action) -> config::Rule {
+        config::Rule {
+            name: None,
+            pattern: pattern.to_string(),
+            threshold: 50.0,
+            action,
+            nice: None,
+            kill_grace_seconds: None,
+        }
+    }
+
+    fn actions_setup(enable_actions: bool) -> (Watcher, CannedProcesses) {
+        let config = Config {
+            enable_actions,
+            rules: vec![rule("backup", Action::Notify), rule("re:^miner", Action::Kill)],
+            ..Config::default()
+        };
+        let mut processes = CannedProcesses::new(1, 8 << 30);
+        for (pid, name) in [(100, "backup"), (200, "stray"), (300, "miner")] {
+            processes.insert(CannedProcess {
+                start_time: 1_700_000_000,
+                ..CannedProcess::new(pid, name, 95.0)
+            });
+        }
+        let (watcher, _clock) = watcher(&config, &processes);
+        (watcher, processes)
+    }
+
+    #[test]
+    fn only_processes_with_an_action_rule_are_actionable() {
+        let (mut watcher, processes) = actions_setup(false);
+        let mut actionable: Vec<_> = watcher
+            .check(&processes, None)
+            .into_iter()
+            .map(|alert| (alert.process.pid.as_u32(), alert.process.actionable))
+            .collect();
+        actionable.sort();
+        assert_eq!(actionable, [(100, false), (200, false), (300, true)]);
+    }
+
+    #[test]
+    fn button_action_is_checked_against_rules() {
+        let (mut watcher, processes) = actions_setup(true);
+        let start_time = Some(1_700_000_000);
+        let refused = |result: Result<String, String>| result.expect_err("action must be refused");
+
+        assert!(refused(watcher.act(&processes, Pid::from_u32(200), start_time, Action::Kill)).contains("no rule"));
+        // Правило notify действий не разрешает
+        assert!(refused(watcher.act(&processes, Pid::from_u32(100), start_time, Action::Renice)).contains("no rule"));
+        // PID уже у другого процесса
+        let stale = watcher.act(&processes, Pid::from_u32(300), Some(1_600_000_000), Action::Kill);
+        assert!(refused(stale).contains("no longer running"));
+
+        let (mut watcher, processes) = actions_setup(false);
+        assert!(refused(watcher.act(&processes, Pid::from_u32(300), start_time, Action::Kill)).contains("disabled"));
+    }
+
+    #[test]
+    fn process_vanished_before_cmdline_read_is_skipped() {
+        let config = Config {
+            threshold: 50.0,
+            ..Config::default()
+        };
+        let mut processes = CannedProcesses::new(1, 8 << 30);
+        // Был в списке, но /proc/PID/cmdline уже не читается: процесс завершился
+        processes.insert(CannedProcess {
+            cmdline: None,
+            exited: true,
+            ..CannedProcess::new(100, "short-lived", 95.0)
+        });
+        // cmdline тоже не прочитан, но процесс жив (нет прав): оповещение с именем вместо команды
+        processes.insert(CannedProcess {
+            cmdline: None,
+            ..CannedProcess::new(200, "hidden", 95.0)
+        });
+        let (mut watcher, _clock) = watcher(&config, &processes);
+
+        let alerts = watcher.check(&processes, None);
+        assert_eq!(alerted_pids(&alerts), [200]);
+        assert_eq!(alerts[0].process.cmdline, "hidden");
+    }
+
+    #[test]
+    fn cpu_and_memory_cooldowns_are_independent() {
+        let config = Config {
+            threshold: 50.0,
+            memory_threshold: Some(config::MemoryThreshold::Bytes(1 << 30)),
+            cooldown_seconds: 3600,
+            cooldowns: HashMap::from([(AlertKind::Cpu, 600)]),
+            ..Config::default()
+        };
+        let mut processes = CannedProcesses::new(1, 8 << 30);
+        processes.insert(CannedProcess {
+            memory: 2 << 30,
+            ..CannedProcess::new(100, "worker", 95.0)
+        });
+        let (mut watcher, clock) = watcher(&config, &processes);
+        let mut triggers = |advance: u64| {
+            clock.advance(advance);
+            let alerts = watcher.check(&processes, None);
+            watcher.mark_alerted(&alerts);
+            alerts.into_iter().flat_map(|alert| alert.triggers).collect::<Vec<_>>()
+        };
+
+        assert_eq!(triggers(0), [AlertKind::Cpu, AlertKind::Memory]);
+        // CPU — по своему кулдауну в 10 минут, память — по общему COOLDOWN_SECONDS
+        assert_eq!(triggers(600), [AlertKind::Cpu]);
+        assert_eq!(triggers(599), []);
+        assert_eq!(triggers(1), [AlertKind::Cpu]);
+        assert_eq!(triggers(1800), [AlertKind::Cpu]);
+        assert_eq!(triggers(600), [AlertKind::Cpu, AlertKind::Memory]);
+    }
+
+    #[test]
+    fn only_running_skip_keeps_cooldown_streak() {
+        let config = Config {
+            threshold: 50.0,
+            only_running: true,
+            cooldown_seconds: 600,
+            max_cooldown_seconds: 3600,
+            ..Config::default()
+        };
+        let mut processes = CannedProcesses::new(1, 8 << 30);
+        processes.insert(CannedProcess::new(100, "worker", 95.0));
+        let (mut watcher, clock) = watcher(&config, &processes);
+        let streak = |watcher: &Watcher| watcher.alerted().values().map(|cooldown| cooldown.streak).max();
+
+        assert_eq!(check_and_mark(&mut watcher, &processes), [100]);
+        clock.advance(600);
+        assert_eq!(check_and_mark(&mut watcher, &processes), [100]);
+        assert_eq!(streak(&watcher), Some(2));
+
+        // В момент замера процесс ждал ввода-вывода: проверка его пропускает, серия остаётся
+        processes.get_mut(100).unwrap().status = ProcessStatus::Sleep;
+        clock.advance(60);
+        assert!(check_and_mark(&mut watcher, &processes).is_empty());
+        assert_eq!(streak(&watcher), Some(2));
+
+        // Снова работает: кулдаун по-прежнему удвоенный, 1200 секунд
+        processes.get_mut(100).unwrap().status = ProcessStatus::Run;
+        clock.advance(540);
+        assert!(check_and_mark(&mut watcher, &processes).is_empty());
+        clock.advance(600);
+        assert_eq!(check_and_mark(&mut watcher, &processes), [100]);
+        assert_eq!(streak(&watcher), Some(3));
+    }
+
+    #[test]
+    fn only_running_skips_sleeping_processes() {
+        let config = Config {
+            threshold: 50.0,
+            only_running: true,
+            ..Config::default()
+        };
+        let mut processes = CannedProcesses::new(1, 8 << 30);
+        processes.insert(CannedProcess::new(100, "worker", 95.0));
+        processes.insert(CannedProcess {
+            status: ProcessStatus::Sleep,
+            ..CannedProcess::new(200, "waiting", 95.0)
+        });
+        let (mut watcher, _clock) = watcher(&config, &processes);
+        assert_eq!(check_and_mark(&mut watcher, &processes), [100]);
+    }
+}
@@ -0,0 +1,200 @@
+use crate::config::{Config, MessageLocale};
+use crate::markup::Markup;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+/// Тексты сообщений на выбранном языке (`message_locale`), с заменами из `message_strings`.
+///
+/// В строках подстановки вида `{limit}`; `{{` и `}}` — литеральные скобки. Значок уровня и
+/// подписи полей (`Name:`, `PID:`) сюда не входят: подписи задаёт `message_template`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Strings {
+    pub headline_cpu: String,
+    pub headline_memory: String,
+    pub headline_disk_io: String,
+    pub headline_network: String,
+    pub headline_open_files: String,
+    pub headline_threads: String,
+    pub headline_swap: String,
+    pub headline_score: String,
+    pub headline_runtime: String,
+    pub headline_d_state: String,
+    pub headline_cpu_time: String,
+    pub batch: String,
+    pub more: String,
+    pub recovered: String,
+    pub started: String,
+    pub stopping: String,
+    pub exited: String,
+    pub gpu_utilization: String,
+    pub gpu_memory: String,
+    pub system_network: String,
+    pub zombies: String,
+    pub restarts: String,
+    pub digest: String,
+    pub quiet_summary: String,
+    pub suppressed: String,
+    pub proc_unreadable: String,
+    pub heartbeat: String,
+}
+
+impl Strings {
+    /// Встроенные тексты: русские заголовки, как были до MESSAGE_LOCALE.
+    pub fn ru() -> Self {
+        Strings {
+            headline_cpu: "Процесс использует >{threshold}% CPU ({mode})".into(),
+            headline_memory: "Процесс использует >{limit} памяти".into(),
+            headline_disk_io: "Процесс читает и пишет диск быстрее {limit}/s".into(),
+            headline_network: "Сетевой трафик процесса быстрее {limit}/s".into(),
+            headline_open_files: "Процесс держит открытыми больше {limit} файлов".into(),
+            headline_threads: "Процесс запустил больше {limit} потоков".into(),
+            headline_swap: "Процесс держит в свопе больше {limit}".into(),
+            headline_score: "Совокупная нагрузка CPU и памяти выше {limit}".into(),
+            headline_runtime: "Процесс работает дольше {limit}".into(),
+            headline_d_state: "Процесс в состоянии D (непрерываемый сон) дольше {limit}".into(),
+            headline_cpu_time: "Процесс потратил больше {limit} времени CPU с запуска".into(),
+            batch: "{count} процессов превысили порог (CPU {mode})".into(),
+            more: "…и ещё {count}".into(),
+            recovered: "{name} (PID {pid}) снова ниже порога".into(),
+            started: "cpu_watcher запущен на {host}".into(),
+            stopping: "cpu_watcher останавливается на {host}, uptime {uptime}".into(),
+            exited: "Процесс завершился".into(),
+            gpu_utilization: "GPU загружен больше {limit}%".into(),
+            gpu_memory: "Видеопамять GPU выше порога".into(),
+            system_network: "Сетевой трафик системы быстрее {limit}/s".into(),
+            zombies: "В системе {count} зомби-процессов (порог {limit})".into(),
+            restarts: "Процесс перезапускается чаще {limit}/min (окно {window})".into(),
+            digest: "Сводка: {count} процессов".into(),
+            quiet_summary: "За тихие часы ({hours}) порог превышали {count} процессов".into(),
+            suppressed: "Подавлено {count} уведомлений за последнюю минуту (лимит {limit} в минуту)".into(),
+            proc_unreadable: "cpu_watcher не может читать /proc: {failures} из {attempts} чтений командных строк \
+                              завершились ошибкой. Проверьте права и seccomp/AppArmor"
+                .into(),
+            heartbeat: "cpu_watcher работает: {count} процессов, максимум CPU {top_cpu}%".into(),
+        }
+    }
+
+    pub fn en() -> Self {
+        Strings {
+            headline_cpu: "Process uses >{threshold}% CPU ({mode})".into(),
+            headline_memory: "Process uses more than {limit} of memory".into(),
+            headline_disk_io: "Process reads and writes disk faster than {limit}/s".into(),
+            headline_network: "Process network traffic is faster than {limit}/s".into(),
+            headline_open_files: "Process holds more than {limit} open files".into(),
+            headline_threads: "Process runs more than {limit} threads".into(),
+            headline_swap: "Process has more than {limit} in swap".into(),
+            headline_score: "Combined CPU and memory load is above {limit}".into(),
+            headline_runtime: "Process has been running longer than {limit}".into(),
+            headline_d_state: "Process has been in D state (uninterruptible sleep) longer than {limit}".into(),
+            headline_cpu_time: "Process has used more than {limit} of CPU time since start".into(),
+            batch: "{count} processes exceeded the threshold (CPU {mode})".into(),
+            more: "…and {count} more".into(),
+            recovered: "{name} (PID {pid}) is back below the threshold".into(),
+            started: "cpu_watcher started on {host}".into(),
+            stopping: "cpu_watcher is stopping on {host}, uptime {uptime}".into(),
+            exited: "Process exited".into(),
+            gpu_utilization: "GPU utilization above {limit}%".into(),
+            gpu_memory: "GPU memory above the threshold".into(),
+            system_network: "System network traffic is faster than {limit}/s".into(),
+            zombies: "{count} zombie processes in the system (threshold {limit})".into(),
+            restarts: "Process restarts more often than {limit}/min (window {window})".into(),
+            digest: "Digest: {count} processes".into(),
+            quiet_summary: "During quiet hours ({hours}) {count} processes exceeded the threshold".into(),
+            suppressed: "Suppressed {count} notifications in the last minute (limit {limit} per minute)".into(),
+            proc_unreadable: "cpu_watcher cannot read /proc: {failures} of {attempts} command line reads failed. \
+                              Check permissions and seccomp/AppArmor"
+                .into(),
+            heartbeat: "cpu_watcher alive, {count} processes, top CPU {top_cpu}%".into(),
+        }
+    }
+
+    /// Тексты по `message_locale` с заменами из файла `message_strings`. В файле — TOML с
+    /// любыми из ключей [`Strings`]; неизвестный ключ или подстановка, которой нет во
+    /// встроенной строке, — ошибка.
+    pub fn load(config: &Config) -> Result<Self, String> {
+        let strings = match config.message_locale {
+            MessageLocale::Ru => Strings::ru(),
+            MessageLocale::En => Strings::en(),
+        };
+        let Some(path) = &config.message_strings else {
+            return Ok(strings);
+        };
+        let content =
+            fs::read_to_string(path).map_err(|e| format!("cannot read message strings {}: {}", path.display(), e))?;
+        let overrides: toml::Table =
+            toml::from_str(&content).map_err(|e| format!("invalid message strings {}: {}", path.display(), e))?;
+        let mut table = toml::Table::try_from(&strings).map_err(|e| e.to_string())?;
+        for (key, value) in overrides {
+            let Some(builtin) = table.get(&key).and_then(toml::Value::as_str) else {
+                return Err(format!("message strings {}: unknown key {}", path.display(), key));
+            };
+            let Some(text) = value.as_str() else {
+                return Err(format!("message strings {}: {} must be a string", path.display(), key));
+            };
+            let allowed = placeholders(builtin)?;
+            for name in placeholders(text).map_err(|e| format!("message strings {}, {}: {}", path.display(), key, e))? {
+                if !allowed.contains(&name) {
+                    return Err(format!(
+                        "message strings {}, {}: unknown placeholder {{{}}} (expected one of {{{}}})",
+                        path.display(),
+                        key,
+                        name,
+                        allowed.join("}, {")
+                    ));
+                }
+            }
+            table.insert(key, value);
+        }
+        table.try_into().map_err(|e: toml::de::Error| e.to_string())
+    }
+}
+
+// Имена подстановок в строке по порядку
+fn placeholders(pattern: &str) -> Result<Vec<&str>, String> {
+    let mut names = Vec::new();
+    let mut rest = pattern;
+    while let Some(start) = rest.find(['{', '}']) {
+        let after = &rest[start + 1..];
+        if after.starts_with(&rest[start..start + 1]) {
+            rest = &after[1..];
+            continue;
+        }
+        if rest[start..].starts_with('}') {
+            return Err("unmatched '}' (use '}}')".to_string());
+        }
+        let end = after.find('}').ok_or_else(|| "unclosed placeholder".to_string())?;
+        names.push(&after[..end]);
+        rest = &after[end + 1..];
+    }
+    Ok(names)
+}
+
+/// Подставляет в `pattern` уже оформленные значения; литеральный текст экранируется под
+/// `markup`. Подстановка без значения остаётся как есть.
+pub fn fill(pattern: &str, markup: Markup, values: &[(&str, String)]) -> String {
+    let mut out = String::new();
+    let mut text = String::new();
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' | '}' if chars.peek() == Some(&c) => {
+                chars.next();
+                text.push(c);
+            }
+            '{' => {
+                let name: String = chars.by_ref().take_while(|c| *c != '}').collect();
+                match values.iter().find(|(key, _)| *key == name) {
+                    Some((_, value)) => {
+                        out.push_str(&markup.escape(&std::mem::take(&mut text)));
+                        out.push_str(value);
+                    }
+                    None => text.push_str(&format!("{{{}}}", name)),
+                }
+            }
+            c => text.push(c),
+        }
+    }
+    out.push_str(&markup.escape(&text));
+    out
+}
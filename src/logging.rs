@@ -0,0 +1,138 @@
+use chrono::Utc;
+use log::kv::{Key, Value, VisitSource};
+use serde_json::{Map, Value as Json};
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+const DEFAULT_LOG_MAX_MIB: u64 = 10;
+const DEFAULT_LOG_KEEP: usize = 5;
+
+// Собирает структурированные поля записи (event, pid, cpu, ...) в JSON-объект
+struct JsonFields<'a>(&'a mut Map<String, Json>);
+
+impl<'kvs> VisitSource<'kvs> for JsonFields<'_> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), log::kv::Error> {
+        let json = if let Some(v) = value.to_u64() {
+            Json::from(v)
+        } else if let Some(v) = value.to_i64() {
+            Json::from(v)
+        } else if let Some(v) = value.to_f64() {
+            Json::from(v)
+        } else if let Some(v) = value.to_bool() {
+            Json::from(v)
+        } else {
+            Json::from(value.to_string())
+        };
+        self.0.insert(key.to_string(), json);
+        Ok(())
+    }
+}
+
+// Файл лога с ротацией по размеру: app.log -> app.log.1 -> ... -> app.log.N, старший удаляется
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_bytes: u64,
+    keep: usize,
+}
+
+impl RotatingFile {
+    fn open(path: &Path, max_bytes: u64, keep: usize) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let size = file.metadata()?.len();
+        Ok(RotatingFile {
+            path: path.to_path_buf(),
+            file,
+            size,
+            max_bytes,
+            keep,
+        })
+    }
+
+    fn numbered(&self, n: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", n));
+        PathBuf::from(path)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        if self.keep == 0 {
+            self.file = File::create(&self.path)?;
+        } else {
+            for n in (1..self.keep).rev() {
+                let from = self.numbered(n);
+                if from.exists() {
+                    fs::rename(&from, self.numbered(n + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.numbered(1))?;
+            self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        }
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    // env_logger пишет запись одним вызовом, поэтому запись не разрывается между файлами
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+fn env_number<T: std::str::FromStr>(key: &str, default: T) -> Result<T, String> {
+    match env::var(key) {
+        Ok(raw) => raw.trim().parse().map_err(|_| format!("invalid {}: {:?}", key, raw)),
+        Err(_) => Ok(default),
+    }
+}
+
+// LOG_FORMAT=json — одна JSON-строка на запись (для Loki и т.п.), иначе обычный текст env_logger.
+// LOG_LEVEL задаёт фильтр, если не задан RUST_LOG; LOG_FILE пишет в файл с ротацией вместо stderr.
+// Всё читается только из окружения: логгер нужен ещё до загрузки файла конфигурации.
+pub fn init() -> Result<(), String> {
+    let level = env::var("LOG_LEVEL").unwrap_or_else(|_| "error".to_string());
+    let mut builder = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(level));
+
+    if let Some(path) = env::var_os("LOG_FILE").filter(|path| !path.is_empty()) {
+        let max_mib = env_number("LOG_MAX_MIB", DEFAULT_LOG_MAX_MIB)?;
+        let keep = env_number("LOG_KEEP", DEFAULT_LOG_KEEP)?;
+        if max_mib == 0 {
+            return Err("invalid LOG_MAX_MIB: must be positive".to_string());
+        }
+        let path = PathBuf::from(path);
+        let file = RotatingFile::open(&path, max_mib * 1024 * 1024, keep)
+            .map_err(|e| format!("cannot open log file {}: {}", path.display(), e))?;
+        builder.target(env_logger::Target::Pipe(Box::new(file)));
+    }
+
+    if env::var("LOG_FORMAT").is_ok_and(|format| format.eq_ignore_ascii_case("json")) {
+        builder.format(|buf, record| {
+            let mut fields = Map::new();
+            fields.insert("ts".to_string(), Json::from(Utc::now().to_rfc3339()));
+            fields.insert("level".to_string(), Json::from(record.level().as_str()));
+            fields.insert("target".to_string(), Json::from(record.target()));
+            fields.insert("message".to_string(), Json::from(record.args().to_string()));
+            let _ = record.key_values().visit(&mut JsonFields(&mut fields));
+            writeln!(buf, "{}", Json::Object(fields))
+        });
+    } else {
+        // В текстовом режиме поля дублируют сообщение — не печатаем их
+        builder.format_key_values(|_, _| Ok(()));
+    }
+
+    builder.init();
+    Ok(())
+}
@@ -1,199 +1,1147 @@
-use chrono::{DateTime, Utc};
-use log::{error, info, warn};
-use reqwest;
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+mod adaptive;
+mod audit;
+mod circuit;
+mod control;
+mod fallback;
+mod jitter;
+mod logging;
+mod metrics;
+mod quiet;
+mod ratelimit;
+mod reload;
+mod sdnotify;
+mod state;
+mod watch;
+
+use adaptive::AdaptiveInterval;
+use audit::{AuditLog, Undelivered};
+use circuit::CircuitBreaker;
+use control::{Command, Control, ControlRequest};
+use fallback::FallbackNotifier;
+use cpu_watcher::config::{Backend, Config, Output};
+use cpu_watcher::locale::{fill, Strings};
+use cpu_watcher::markup::Markup;
+use cpu_watcher::message::{self, NumberFormat, ScanContext};
+use cpu_watcher::network::SystemNetwork;
+use cpu_watcher::notify::{
+    self, Delivery, DiscordNotifier, DryRunNotifier, FanOutNotifier, Notifier, RetryingNotifier, SentChunks, SeverityRouter,
+    SlackNotifier, StdoutJsonNotifier, TelegramNotifier, TokenCheck, WebhookNotifier,
+};
+#[cfg(unix)]
+use cpu_watcher::notify::UnixSocketNotifier;
+use cpu_watcher::template::{self, Template};
+use cpu_watcher::restart::RestartMonitor;
+use cpu_watcher::zombie::ZombieMonitor;
+use cpu_watcher::{ProcHealth, Severity, Watcher, WatcherError};
+use jitter::Jitter;
+use log::{debug, error, info, warn};
+use metrics::{Endpoints, Metrics};
+use quiet::QuietQueue;
+use ratelimit::RateLimiter;
+use reload::{ConfigWatch, Reload};
+use sdnotify::SdNotify;
+use std::collections::HashSet;
 use std::env;
-use std::fs;
-use std::time::{Duration, SystemTime};
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use sysinfo::{Pid, System};
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::mpsc;
+use watch::{PidWatch, WatchEvent, WatchTarget};
 
-#[derive(Serialize)]
-struct TelegramMessage {
-    chat_id: String,
-    text: String,
-}
-
-#[derive(Deserialize)]
-struct TelegramResponse {
-    ok: bool,
-    #[serde(default)]
-    description: Option<String>,
-}
+// Код выхода, если не хватает обязательных настроек (ошибки в самих настройках — 1)
+const EXIT_MISSING_CONFIG: i32 = 2;
+// Код выхода --once, если хотя бы один процесс превысил порог
+const EXIT_BREACHES: i32 = 10;
 
-struct ProcessInfo {
-    name: String,
-    pid: Pid,
-    cpu_percent: f32,
-    cmdline: String,
-    create_time: Option<DateTime<Utc>>,
+// Аргументы командной строки
+struct Args {
+    config_path: Option<PathBuf>,
+    // --test-notify: отправить пробное сообщение и выйти, не запуская мониторинг
+    test_notify: bool,
+    // --once: одна проверка после прогрева и выход (для cron и CI)
+    once: bool,
+    // --top N: напечатать N процессов с наибольшим CPU и выйти, ничего не отправляя
+    top: Option<usize>,
+    // --validate-config: проверить настройки и выйти, ничего не отправляя и не запуская проверки
+    validate_config: bool,
 }
 
-// Читаем командную строку напрямую из /proc/PID/cmdline
-fn read_cmdline_from_proc(pid: Pid) -> Option<String> {
-    let cmdline_path = format!("/proc/{}/cmdline", pid);
-    match fs::read(&cmdline_path) {
-        Ok(content) => {
-            // В /proc/PID/cmdline аргументы разделены нулевыми байтами
-            let args: Vec<&str> = content
-                .split(|&b| b == 0)
-                .filter(|s| !s.is_empty())
-                .map(|s| std::str::from_utf8(s).unwrap_or_default())
-                .collect();
-            if args.is_empty() {
-                None
-            } else {
-                Some(args.join(" "))
+impl Args {
+    fn parse() -> Result<Args, String> {
+        let mut config_path = None;
+        let mut test_notify = false;
+        let mut once = false;
+        let mut top = None;
+        let mut validate_config = false;
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--config" => {
+                    let path = args.next().ok_or("--config requires a path")?;
+                    config_path = Some(PathBuf::from(path));
+                }
+                "--test-notify" => test_notify = true,
+                "--once" => once = true,
+                "--validate-config" => validate_config = true,
+                "--top" => top = Some(parse_top(args.next().as_deref())?),
+                other => {
+                    if let Some(path) = other.strip_prefix("--config=") {
+                        config_path = Some(PathBuf::from(path));
+                    } else if let Some(count) = other.strip_prefix("--top=") {
+                        top = Some(parse_top(Some(count))?);
+                    } else {
+                        return Err(format!("unknown argument: {}", other));
+                    }
+                }
             }
         }
-        Err(_) => None,
+
+        // Путь из CLI важнее переменной окружения
+        if config_path.is_none() {
+            config_path = env::var_os("CPU_WATCHER_CONFIG").map(PathBuf::from);
+        }
+
+        Ok(Args {
+            config_path,
+            test_notify,
+            once,
+            top,
+            validate_config,
+        })
     }
 }
 
-async fn send_telegram(
-    client: &reqwest::Client,
-    bot_token: &str,
-    chat_id: &str,
-    text: &str,
-) -> Result<bool, Box<dyn std::error::Error>> {
-    let url = format!("https://api.telegram.org/bot{}/sendMessage", bot_token);
-    let message = TelegramMessage {
-        chat_id: chat_id.to_string(),
-        text: text.to_string(),
-    };
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
 
-    let response = client
-        .post(&url)
-        .json(&message)
-        .timeout(Duration::from_secs(10))
-        .send()
-        .await?;
+fn parse_top(count: Option<&str>) -> Result<usize, String> {
+    match count.map(str::parse) {
+        Some(Ok(count)) if count > 0 => Ok(count),
+        _ => Err("--top requires a positive number of processes".to_string()),
+    }
+}
 
-    let response_text = response.text().await?;
-    let telegram_response: TelegramResponse = serde_json::from_str(&response_text)?;
+// Значения, которые не проверить при разборе (пришли из файла или зависят друг от друга)
+fn check_settings(config: &Config) -> Vec<String> {
+    let mut errors = Vec::new();
+    if !(config.send_timeout_seconds.is_finite() && config.send_timeout_seconds > 0.0) {
+        errors.push("SEND_TIMEOUT_SECONDS must be a positive number of seconds".to_string());
+    }
+    // Дальше шестого знака у f32 уже шум
+    if config.cpu_decimals > 6 {
+        errors.push("CPU_DECIMALS must be between 0 and 6".to_string());
+    }
+    // Кнопки под оповещениями выполняют kill/renice, поэтому без ENABLE_ACTIONS их нет
+    if config.telegram_buttons && !config.enable_actions {
+        errors.push("TELEGRAM_BUTTONS requires ENABLE_ACTIONS=true".to_string());
+    }
+    // Замеры чаще MINIMUM_CPU_UPDATE_INTERVAL дают неточный CPU
+    let shortest_interval = if config.adaptive_interval { config.min_interval } else { config.check_interval };
+    if config.samples_per_interval == 0 {
+        errors.push("SAMPLES_PER_INTERVAL must be at least 1".to_string());
+    } else if config.samples_per_interval > 1
+        && shortest_interval / (config.samples_per_interval as f64) < sysinfo::MINIMUM_CPU_UPDATE_INTERVAL.as_secs_f64()
+    {
+        errors.push(format!(
+            "SAMPLES_PER_INTERVAL={} samples more often than every {}ms: raise CHECK_INTERVAL or lower the sample count",
+            config.samples_per_interval,
+            sysinfo::MINIMUM_CPU_UPDATE_INTERVAL.as_millis()
+        ));
+    }
+    if cfg!(target_os = "linux") && !config.proc_path.join("self").exists() {
+        errors.push(format!("PROC_PATH {} is not a mounted procfs", config.proc_path.display()));
+    }
+    // Откат в тот же канал только повторил бы неудачную отправку
+    if config.fallback_backend == Some(config.primary_backend()) {
+        errors.push(format!("FALLBACK_BACKEND must differ from the primary notifier ({})", config.primary_backend()));
+    }
+    errors
+}
 
-    if telegram_response.ok {
-        info!("Telegram sent: {}", text);
-        Ok(true)
-    } else {
-        error!(
-            "Telegram error: {}",
-            telegram_response.description.unwrap_or("Unknown error".to_string())
-        );
-        Ok(false)
+// sysinfo перечисляет процессы всегда из /proc. Если PROC_PATH смотрит в другое пространство
+// имён PID (procfs хоста в контейнере без --pid=host), под одним PID окажутся разные процессы
+fn procfs_mismatch() -> Option<String> {
+    if !cpu_watcher::procfs::is_custom() {
+        return None;
+    }
+    let init = |root: &std::path::Path| std::fs::read_to_string(root.join("1/comm")).ok();
+    let (own, custom) = (init(std::path::Path::new("/proc")), init(cpu_watcher::procfs::root()));
+    (own != custom).then(|| {
+        format!(
+            "PROC_PATH {} shows a different PID namespace than /proc (PID 1 is {:?} there, {:?} here): \
+             processes are listed from /proc, so run the container with --pid=host",
+            cpu_watcher::procfs::root().display(),
+            custom.as_deref().map(str::trim).unwrap_or("?"),
+            own.as_deref().map(str::trim).unwrap_or("?")
+        )
+    })
+}
+
+// --validate-config: всё, что при запуске остановило бы cpu_watcher, кроме сети (getMe,
+// отправка) и файлов (STATE_FILE, AUDIT_LOG). Ошибки собираются все сразу
+fn validate_config(config: &Config) -> Vec<String> {
+    let mut errors = config.invalid_env.clone();
+    errors.extend(
+        config
+            .missing_required()
+            .into_iter()
+            .map(|(env_key, file_key)| format!("missing {} (or {} in the config file)", env_key, file_key)),
+    );
+    errors.extend(check_settings(config));
+    if let Err(e) = Strings::load(config) {
+        errors.push(e);
+    }
+    if let Err(e) = Template::parse(config.message_template.as_deref().unwrap_or(template::DEFAULT_TEMPLATE)) {
+        errors.push(e);
+    }
+    if let Err(e) = notify::build_client(&config.http_options()) {
+        errors.push(e.to_string());
+    }
+    let backends = if config.sends() { config.backends() } else { Vec::new() };
+    if backends.contains(&Backend::Telegram) {
+        if let Err(e) = notify::telegram_api_base(&config.telegram_api_base) {
+            errors.push(e.to_string());
+        }
+    }
+    if backends.contains(&Backend::Webhook) {
+        if let Err(e) = notify::parse_headers(config.webhook_headers.as_deref().unwrap_or_default()) {
+            errors.push(e);
+        }
     }
+    if cfg!(not(unix)) && backends.contains(&Backend::Socket) {
+        errors.push("SOCKET_PATH is only supported on Unix".to_string());
+    }
+    // Фильтры, регулярные выражения, правила и множители уровней; данные о процессах не нужны
+    if let Err(e) = Watcher::new(config, &System::new()) {
+        errors.push(e);
+    }
+    errors
 }
 
-fn format_message(proc_info: &ProcessInfo, threshold: f32) -> String {
-    let started_str = proc_info
-        .create_time
-        .map(|t| t.to_rfc3339())
-        .unwrap_or_else(|| "?".to_string());
-    
-    format!(
-        "⚠ Процесс использует >{:.1}% CPU\nName: {}\nPID: {}\nCPU: {:.1}%\nStarted: {}\nCmd: {}",
-        threshold,
-        proc_info.name,
-        proc_info.pid,
-        proc_info.cpu_percent,
-        started_str,
-        proc_info.cmdline
-    )
+// --top: таблица процессов с наибольшим CPU, как их видит проверка (после того же прогрева)
+async fn print_top(config: &Config, count: usize) -> Result<(), String> {
+    let refresh_kind = cpu_watcher::process_refresh_kind(config);
+    let mut sys = System::new();
+    cpu_watcher::refresh_system(&mut sys, refresh_kind);
+    tokio::time::sleep(Duration::from_millis(config.warmup_ms)).await;
+    cpu_watcher::refresh_system(&mut sys, refresh_kind);
+    let mut watcher = Watcher::new(config, &sys)?;
+    println!("{:>7} {:>7} {:>7}  {:<16} CMDLINE", "PID", "CPU%", "LIMIT%", "NAME");
+    for process in watcher.top(&sys, count) {
+        // Аргументы с переводами строк ломали бы таблицу
+        println!(
+            "{:>7} {:>7.1} {:>7.1}  {:<16} {}",
+            process.pid.as_u32(),
+            process.cpu_percent,
+            process.cpu_threshold,
+            process.name,
+            process.cmdline.replace(['\n', '\r', '\t'], " ")
+        );
+    }
+    Ok(())
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    env_logger::init();
-
-    let threshold = env::var("CPU_THRESHOLD")
-        .unwrap_or_else(|_| "50.0".to_string())
-        .parse::<f32>()
-        .unwrap_or(50.0);
-    
-    let check_interval = env::var("CHECK_INTERVAL")
-        .unwrap_or_else(|_| "1.0".to_string())
-        .parse::<f64>()
-        .unwrap_or(1.0);
-    
-    let cooldown_seconds = env::var("COOLDOWN_SECONDS")
-        .unwrap_or_else(|_| "600".to_string())
-        .parse::<u64>()
-        .unwrap_or(600);
-
-    let bot_token = env::var("TELEGRAM_BOT_TOKEN")
-        .expect("TELEGRAM_BOT_TOKEN must be set");
-    let chat_id = env::var("TELEGRAM_CHAT_ID")
-        .expect("TELEGRAM_CHAT_ID must be set");
-
-    info!("cpu_watcher started (threshold={:.1}%, check_interval={}s, cooldown={}s)", 
-          threshold, check_interval, cooldown_seconds);
-
-    let mut sys = System::new_all();
-    let mut alerted: HashMap<Pid, SystemTime> = HashMap::new();
-    let client = reqwest::Client::new();
-
-    // Инициализация: получить первые измерения CPU
-    sys.refresh_all();
-    std::thread::sleep(Duration::from_millis(100));
-    sys.refresh_all();
+    logging::init().unwrap_or_else(|e| {
+        eprintln!("cpu_watcher: {}", e);
+        std::process::exit(1);
+    });
+
+    let args = Args::parse().unwrap_or_else(|e| {
+        eprintln!("cpu_watcher: {}", e);
+        std::process::exit(1);
+    });
+    let config = Config::load(args.config_path.as_deref()).unwrap_or_else(|e| {
+        eprintln!("cpu_watcher: {}", e);
+        std::process::exit(1);
+    });
+    cpu_watcher::procfs::set_root(&config.proc_path);
+    if args.validate_config {
+        let errors = validate_config(&config);
+        if !errors.is_empty() {
+            eprintln!("cpu_watcher: configuration has {} error(s):", errors.len());
+            for error in &errors {
+                eprintln!("  {}", error);
+            }
+            std::process::exit(1);
+        }
+        let backends: Vec<String> = config.backends().iter().map(Backend::to_string).collect();
+        println!("cpu_watcher: configuration is valid");
+        println!(
+            "  threshold {:.1}% {}, check interval {}s, cooldown {}s",
+            config.threshold, config.threshold_mode, config.check_interval, config.cooldown_seconds
+        );
+        println!(
+            "  {} rule(s), {} only / {} ignore pattern(s)",
+            config.rules.len(),
+            config.only_processes.len(),
+            config.ignore_processes.len()
+        );
+        if config.output == Output::StdoutJson {
+            println!("  notifications: JSON lines to stdout");
+        } else if config.dry_run {
+            println!("  notifications: dry run");
+        } else {
+            println!("  notifications: {}", backends.join(", "));
+        }
+        return Ok(());
+    }
+    // Канал уведомлений для --top не нужен, поэтому он разбирается раньше проверки настроек
+    if let Some(count) = args.top {
+        if let Err(e) = print_top(&config, count).await {
+            eprintln!("cpu_watcher: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    // Сообщаем обо всех недостающих настройках сразу, чтобы не чинить их по одной
+    let missing = config.missing_required();
+    if !missing.is_empty() {
+        eprintln!("cpu_watcher: missing required settings:");
+        for (env_key, file_key) in missing {
+            eprintln!("  {} (or {} in the config file)", env_key, file_key);
+        }
+        eprintln!("Set DISCORD_WEBHOOK_URL, SLACK_WEBHOOK_URL, WEBHOOK_URL or SOCKET_PATH to use another channel, fix ROUTE_INFO/ROUTE_WARNING/ROUTE_CRITICAL, or DRY_RUN=true to only log alerts.");
+        std::process::exit(EXIT_MISSING_CONFIG);
+    }
+    let template = Template::parse(config.message_template.as_deref().unwrap_or(template::DEFAULT_TEMPLATE))
+        .unwrap_or_else(|e| {
+            eprintln!("cpu_watcher: {}", e);
+            std::process::exit(1);
+        });
+
+    let threshold = config.threshold;
+    let check_interval = config.check_interval;
+    let cooldown_seconds = config.cooldown_seconds;
+    let memory_threshold = config.memory_threshold;
+    let sustained_seconds = config.sustained_seconds;
+
+    // Значение из файла не проходит разбор, как переменная окружения, поэтому проверяется здесь
+    if let Some(error) = check_settings(&config).first() {
+        eprintln!("cpu_watcher: {}", error);
+        std::process::exit(1);
+    }
+    if let Some(warning) = procfs_mismatch() {
+        warn!("{}", warning);
+    }
+    let client = notify::build_client(&config.http_options()).unwrap_or_else(|e| {
+        eprintln!("cpu_watcher: {}", e);
+        std::process::exit(1);
+    });
+
+    // Разметка применяется, только если всё уходит в Telegram: у Discord и Slack свой синтаксис,
+    // а текст сообщения один на все каналы
+    let backends = config.backends();
+    let telegram_only = config.sends() && backends == [Backend::Telegram];
+    if config.telegram_parse_mode.is_some() && config.sends() && !telegram_only && backends.contains(&Backend::Telegram) {
+        warn!("TELEGRAM_PARSE_MODE is ignored: alerts also go to channels other than Telegram");
+    }
+    let parse_mode = if telegram_only { config.telegram_parse_mode } else { None };
+    let markup = Markup(parse_mode);
+    // В --once нажатия и команды слушать некому
+    let buttons = config.telegram_buttons && !args.once;
+    let commands = config.telegram_commands && !args.once;
+    let mut requests: Option<mpsc::Receiver<ControlRequest>> = None;
+
+    // Проверка токена и приём нажатий и команд — один раз, сколько бы маршрутов ни вели в Telegram
+    let mut telegram = None;
+    if config.sends() && backends.contains(&Backend::Telegram) {
+        // Наличие токена и чата проверено в missing_required
+        let bot_token = config.bot_token.clone().unwrap_or_default();
+        let api_base = notify::telegram_api_base(&config.telegram_api_base).unwrap_or_else(|e| {
+            eprintln!("cpu_watcher: {}", e);
+            std::process::exit(1);
+        });
+        // Опечатка в токене всплыла бы только на первом оповещении, возможно через часы
+        if !config.skip_token_check {
+            match notify::telegram_get_me(&client, &api_base, &bot_token).await {
+                Ok(TokenCheck::Valid(username)) => info!("Telegram bot @{} authorized", username),
+                Ok(TokenCheck::Rejected(reason)) => {
+                    eprintln!(
+                        "cpu_watcher: Telegram rejected TELEGRAM_BOT_TOKEN: {} (set SKIP_TOKEN_CHECK=true to skip this check)",
+                        reason
+                    );
+                    std::process::exit(1);
+                }
+                Err(e) => warn!("Cannot verify Telegram bot token, continuing: {}", e),
+            }
+        }
+        let chat_ids = config.chat_ids();
+        info!("Telegram alerts go to {} chat(s)", chat_ids.len());
+        if buttons || commands {
+            let (sender, receiver) = mpsc::channel(16);
+            requests = Some(receiver);
+            let control = Control {
+                client: client.clone(),
+                api_base: api_base.clone(),
+                bot_token: bot_token.clone(),
+                chat_ids: chat_ids.clone(),
+                buttons,
+                commands,
+                mute_duration: Duration::from_secs(config.mute_seconds),
+                requests: sender,
+            };
+            tokio::spawn(control.run());
+        }
+        telegram = Some((api_base, bot_token, chat_ids));
+    }
+
+    let retrying = |inner: Box<dyn Notifier>| -> Box<dyn Notifier> {
+        Box::new(RetryingNotifier {
+            inner,
+            max_retries: config.send_max_retries,
+            initial_backoff: Duration::from_millis(500),
+        })
+    };
+    let backend_notifier = |backend: Backend| -> Box<dyn Notifier> {
+        match backend {
+            Backend::Discord => retrying(Box::new(DiscordNotifier {
+                client: client.clone(),
+                webhook_url: config.discord_webhook_url.clone().unwrap_or_default(),
+                sent: SentChunks::default(),
+            })),
+            Backend::Slack => retrying(Box::new(SlackNotifier {
+                client: client.clone(),
+                webhook_url: config.slack_webhook_url.clone().unwrap_or_default(),
+                channel: config.slack_channel.clone(),
+            })),
+            Backend::Webhook => {
+                let headers = notify::parse_headers(config.webhook_headers.as_deref().unwrap_or_default())
+                    .unwrap_or_else(|e| {
+                        eprintln!("cpu_watcher: {}", e);
+                        std::process::exit(1);
+                    });
+                retrying(Box::new(WebhookNotifier {
+                    client: client.clone(),
+                    url: config.webhook_url.clone().unwrap_or_default(),
+                    headers,
+                }))
+            }
+            #[cfg(unix)]
+            Backend::Socket => {
+                let path = config.socket_path.clone().unwrap_or_default();
+                retrying(Box::new(UnixSocketNotifier::new(path, config.send_timeout())))
+            }
+            #[cfg(not(unix))]
+            Backend::Socket => {
+                eprintln!("cpu_watcher: SOCKET_PATH is only supported on Unix");
+                std::process::exit(1);
+            }
+            Backend::Telegram => {
+                let (api_base, bot_token, chat_ids) = telegram.as_ref().expect("Telegram is set up when it is used");
+                let mut targets: Vec<(String, Box<dyn Notifier>)> = chat_ids
+                    .iter()
+                    .map(|chat_id| {
+                        let telegram = TelegramNotifier {
+                            client: client.clone(),
+                            api_base: api_base.clone(),
+                            bot_token: bot_token.clone(),
+                            chat_id: chat_id.clone(),
+                            parse_mode,
+                            buttons,
+                            sent: SentChunks::default(),
+                        };
+                        (format!("chat {}", chat_id), retrying(Box::new(telegram)))
+                    })
+                    .collect();
+                if targets.len() == 1 {
+                    targets.remove(0).1
+                } else {
+                    Box::new(FanOutNotifier { targets, delivery: config.telegram_delivery })
+                }
+            }
+        }
+    };
+
+    // В режиме DRY_RUN ничего не отправляется; иначе всё идёт в основной канал (см.
+    // Config::primary_backend), а оповещения уровней с маршрутом ROUTE_* — в свои каналы
+    let notifier: Box<dyn Notifier> = if config.output == Output::StdoutJson {
+        info!("Writing alerts to stdout as JSON lines, nothing will be sent");
+        Box::new(StdoutJsonNotifier)
+    } else if config.dry_run {
+        // Баннер идёт мимо логгера, чтобы его было видно при любом RUST_LOG
+        eprintln!("************************************************************");
+        eprintln!("* cpu_watcher: DRY RUN, alerts are only logged (info),     *");
+        eprintln!("* nothing will be sent                                     *");
+        eprintln!("************************************************************");
+        warn!(event = "dry_run"; "Dry run mode: notifications are logged instead of sent");
+        Box::new(DryRunNotifier)
+    } else {
+        let primary = config.primary_backend();
+        info!("Using {} notifier", primary);
+        let mut routes = Vec::new();
+        for severity in [Severity::Info, Severity::Warning, Severity::Critical] {
+            let route = config.route(severity);
+            if route.is_empty() {
+                continue;
+            }
+            let names: Vec<String> = route.iter().map(Backend::to_string).collect();
+            info!("Routing {} alerts to {}", severity, names.join(", "));
+            let mut targets: Vec<(String, Box<dyn Notifier>)> =
+                route.iter().map(|backend| (backend.to_string(), backend_notifier(*backend))).collect();
+            // Несколько каналов — рассылка во все, успех только если дошло везде
+            let notifier = if targets.len() == 1 {
+                targets.remove(0).1
+            } else {
+                Box::new(FanOutNotifier { targets, delivery: Delivery::All })
+            };
+            routes.push((severity, notifier));
+        }
+        let primary = backend_notifier(primary);
+        let mut delivery: Box<dyn Notifier> = if routes.is_empty() {
+            primary
+        } else {
+            Box::new(SeverityRouter { primary, routes })
+        };
+        // Предохранитель стоит перед запасным каналом: пока он разомкнут, сообщения уходят туда
+        if config.circuit_failure_threshold > 0 {
+            info!(
+                "Notifier circuit breaker: {} consecutive failures pause sends for {}s",
+                config.circuit_failure_threshold, config.circuit_cooldown_seconds
+            );
+            delivery = Box::new(CircuitBreaker::new(
+                delivery,
+                config.circuit_failure_threshold,
+                Duration::from_secs(config.circuit_cooldown_seconds),
+            ));
+        }
+        match config.fallback_backend {
+            Some(fallback) => {
+                info!("Using {} as fallback notifier (at most {} per hour)", fallback, config.fallback_max_per_hour);
+                let fallback_notifier = backend_notifier(fallback);
+                Box::new(FallbackNotifier::new(delivery, fallback_notifier, fallback.to_string(), config.fallback_max_per_hour))
+            }
+            None => delivery,
+        }
+    };
+
+    if (config.telegram_buttons || config.telegram_commands) && requests.is_none() && !args.once {
+        warn!("TELEGRAM_BUTTONS/TELEGRAM_COMMANDS are set, but alerts do not go to Telegram: ignoring them");
+    }
+
+    // Подпись одна на всё время работы: имя машины и версия не меняются
+    let footer = message::footer(&config);
+    let numbers = NumberFormat::new(&config);
+    let strings = Arc::new(Strings::load(&config).unwrap_or_else(|e| {
+        eprintln!("cpu_watcher: {}", e);
+        std::process::exit(1);
+    }));
+
+    // Тот же путь отправки, что и у оповещений, поэтому это честная проверка токена и чата;
+    // ответ сервиса с ошибкой backend пишет в лог уровня error, который виден по умолчанию
+    if args.test_notify {
+        let text = message::sign(markup.escape("cpu_watcher test message"), markup, footer.as_deref());
+        match notifier.send(&text).await {
+            Ok(true) => {
+                println!("cpu_watcher: test message sent");
+                std::process::exit(0);
+            }
+            Ok(false) => {
+                eprintln!("cpu_watcher: test message was rejected");
+                std::process::exit(1);
+            }
+            Err(e) => {
+                eprintln!("cpu_watcher: cannot send test message: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    info!(
+        event = "startup",
+        threshold = threshold,
+        threshold_mode:% = config.threshold_mode,
+        memory_threshold:? = memory_threshold,
+        sustained_seconds = sustained_seconds,
+        check_interval = check_interval,
+        cooldown_seconds = cooldown_seconds;
+        "cpu_watcher started (threshold={:.1}% {}, memory_threshold={:?}, sustained={}s, check_interval={}s, cooldown={}s)",
+        threshold, config.threshold_mode, memory_threshold, sustained_seconds, check_interval, cooldown_seconds
+    );
+
+    let metrics = Arc::new(Metrics::default());
+    // Запуск считается свежей проверкой, иначе /healthz отвечал бы 503 весь прогрев
+    metrics.record_scan();
+    // Живым считается цикл, завершивший проверку за два самых длинных интервала
+    let longest_interval = if config.adaptive_interval { config.max_interval } else { check_interval };
+    let stale_after = Duration::from_millis((2.0 * longest_interval * 1000.0) as u64 + config.check_jitter_ms);
+    let mut servers: Vec<(std::net::SocketAddr, Endpoints)> = Vec::new();
+    if let Some(addr) = config.metrics_addr {
+        servers.push((addr, Endpoints { metrics: true, health: None }));
+    }
+    if let Some(addr) = config.health_addr {
+        match servers.iter_mut().find(|(bound, _)| *bound == addr) {
+            Some((_, endpoints)) => endpoints.health = Some(stale_after),
+            None => servers.push((addr, Endpoints { metrics: false, health: Some(stale_after) })),
+        }
+    }
+    for (addr, endpoints) in servers {
+        let listener = tokio::net::TcpListener::bind(addr).await.unwrap_or_else(|e| {
+            eprintln!("cpu_watcher: cannot bind HTTP endpoint {}: {}", addr, e);
+            std::process::exit(1);
+        });
+        tokio::spawn(metrics::serve(listener, metrics.clone(), endpoints));
+    }
+
+    let mut sys = System::new();
+    // Общий лимит отправок поверх кулдауна по PID; отброшенные процессы копятся до сводки
+    let mut rate_limiter = RateLimiter::new(config.max_alerts_per_minute, Duration::from_secs(60));
+    let mut suppressed: HashSet<Pid> = HashSet::new();
+
+    let mut quiet = config.quiet_hours.map(|hours| QuietQueue::new(hours, config.quiet_hours_tz));
+    if let Some(hours) = config.quiet_hours {
+        info!("Quiet hours {} ({:?}), critical processes: {:?}", hours, config.quiet_hours_tz, config.critical_processes);
+    }
+
+    let mut pid_watch = WatchTarget::from_config(&config).map(PidWatch::new);
+    if let Some(pid_watch) = &pid_watch {
+        info!("Watching only {} and its children", pid_watch.target());
+    }
+
+    // sysinfo считает CPU процесса как разницу между двумя обновлениями, поэтому первое
+    // обновление даёт только точку отсчёта. Второе делается не раньше, чем через
+    // MINIMUM_CPU_UPDATE_INTERVAL: на более коротком промежутке значения неточны.
+    let refresh_kind = cpu_watcher::process_refresh_kind(&config);
+    cpu_watcher::refresh_system(&mut sys, refresh_kind);
+    tokio::time::sleep(Duration::from_millis(config.warmup_ms)).await;
+    cpu_watcher::refresh_system(&mut sys, refresh_kind);
+
+    #[cfg(feature = "gpu")]
+    let mut gpu_monitor = cpu_watcher::gpu::GpuMonitor::new(&config);
+    #[cfg(not(feature = "gpu"))]
+    if config.gpu_threshold.is_some() || config.gpu_memory_threshold.is_some() {
+        warn!("GPU_THRESHOLD/GPU_MEMORY_THRESHOLD are set, but cpu_watcher was built without the gpu feature");
+    }
+
+    let mut system_network = SystemNetwork::new(&config);
+    let mut zombie_monitor = ZombieMonitor::new(&config);
+    let mut restart_monitor = RestartMonitor::new(&config);
+
+    let mut watcher = Watcher::new(&config, &sys).unwrap_or_else(|e| {
+        eprintln!("cpu_watcher: {}", e);
+        std::process::exit(1);
+    });
+    if let Some(path) = &config.state_file {
+        watcher.restore_alerted(state::load(path, &sys));
+    }
+    if config.swap_threshold.is_some() && watcher.swap_limit().is_none() {
+        warn!("SWAP_THRESHOLD is set, but the system has no swap: swap check disabled");
+    }
+    let mut audit_log = config.audit_log.as_ref().map(|path| {
+        AuditLog::open(path).unwrap_or_else(|e| {
+            eprintln!("cpu_watcher: cannot open AUDIT_LOG {}: {}", path.display(), e);
+            std::process::exit(1);
+        })
+    });
+
+    // Под systemd с Type=notify сервис считается запущенным после прогрева, а WatchdogSec
+    // перезапустит его, если проверки перестали завершаться
+    let sd_notify = SdNotify::from_env();
+    if let Some(sd_notify) = &sd_notify {
+        sd_notify.ready();
+        if let Some(watchdog) = sdnotify::watchdog_interval() {
+            let longest = Duration::from_millis((longest_interval * 1000.0) as u64 + config.check_jitter_ms);
+            if longest * 2 > watchdog {
+                warn!(
+                    "WatchdogSec ({:?}) is less than twice the check interval ({:?}): systemd may restart a healthy watcher",
+                    watchdog, longest
+                );
+            }
+        }
+    }
+
+    // NOTIFY_ON_START: видимое подтверждение выкатки на каждой машине. --once запускается по
+    // расписанию, и сообщение о каждом запуске было бы шумом
+    let notify_lifecycle = config.notify_on_start && !args.once;
+    let started = Instant::now();
+    if notify_lifecycle {
+        let msg = message::format_started(&config, &strings, markup, footer.as_deref(), numbers);
+        match notifier.send(&msg).await {
+            Ok(true) => {}
+            Ok(false) => warn!(event = "start_notify_failed"; "Failed to send startup notification"),
+            Err(e) => error!(event = "start_notify_failed", error:% = e; "Error sending startup notification: {}", e),
+        }
+    }
+
+    // Сигналы проверяются только во время паузы между проверками,
+    // поэтому начатая отправка уведомления всегда завершается
+    let mut sigterm = signal(SignalKind::terminate())?;
+    let mut sigint = signal(SignalKind::interrupt())?;
+    // В режиме --once отдельная пустая проверка не нужна: единственная идёт сразу за прогревом
+    let mut warming_up = !args.once;
+    let mut breaches = 0;
+    // После перезагрузки всё занято запуском служб и прогревом кэшей: до конца окна
+    // BOOT_GRACE_SECONDS проверки идут, но оповещения не отправляются и в кулдаун не попадают
+    let mut boot_grace_until = Some(System::boot_time() + config.boot_grace_seconds)
+        .filter(|until| config.boot_grace_seconds > 0 && unix_now() < *until);
+    if let Some(until) = boot_grace_until {
+        info!(event = "boot_grace"; "Boot grace: alerts suppressed for another {}s (BOOT_GRACE_SECONDS)", until - unix_now());
+    }
+    if args.once && sustained_seconds > 0 {
+        warn!("--once with SUSTAINED_SECONDS={}: CPU alerts never fire in a single scan", sustained_seconds);
+    }
+    let heartbeat = Duration::from_secs(config.heartbeat_seconds);
+    let mut last_heartbeat = Instant::now();
+    let digest = Duration::from_secs(config.digest_seconds);
+    let mut last_digest = Instant::now();
+    let mut jitter = Jitter::new(config.check_jitter_ms);
+    let mut interval = check_interval;
+    let samples_per_interval = config.samples_per_interval.max(1);
+    // Правки файла конфигурации применяются на ходу; --once читает его один раз
+    let mut config_watch = match args.config_path.clone().filter(|_| !args.once) {
+        Some(path) => Some(ConfigWatch::new(path).await),
+        None => None,
+    };
+    let mut adaptive = if config.adaptive_interval {
+        let adaptive = AdaptiveInterval::new(config.min_interval, config.max_interval).unwrap_or_else(|e| {
+            eprintln!("cpu_watcher: {}", e);
+            std::process::exit(1);
+        });
+        info!("Adaptive check interval: {}s to {}s depending on system CPU", config.min_interval, config.max_interval);
+        Some(adaptive)
+    } else {
+        None
+    };
 
     loop {
-        tokio::time::sleep(Duration::from_millis((check_interval * 1000.0) as u64)).await;
-
-        sys.refresh_processes();
-        
-        for (pid, process) in sys.processes() {
-            let cpu = process.cpu_usage();
-            
-            if cpu >= threshold {
-                let now = SystemTime::now();
-                
-                if let Some(last_alert_time) = alerted.get(pid) {
-                    if let Ok(elapsed) = now.duration_since(*last_alert_time) {
-                        if elapsed.as_secs() < cooldown_seconds {
-                            continue; // Уже оповещали недавно
+        // --once: CPU считается между обновлениями, поэтому перед единственной проверкой
+        // тоже выдерживаем WARMUP_MS после прогрева
+        if args.once {
+            tokio::time::sleep(Duration::from_millis(config.warmup_ms)).await;
+        } else {
+            // SAMPLES_PER_INTERVAL: пауза делится на равные части, после каждой, кроме последней,
+            // CPU процессов обновляется и запоминается. Последний замер — обычное обновление ниже
+            let pause = jitter.apply(Duration::from_millis((interval * 1000.0) as u64));
+            let mut stop = None;
+            for sample in 1..=samples_per_interval {
+                tokio::select! {
+                    _ = tokio::time::sleep(pause / samples_per_interval) => {}
+                    _ = sigterm.recv() => stop = Some("SIGTERM"),
+                    _ = sigint.recv() => stop = Some("SIGINT"),
+                }
+                if stop.is_some() {
+                    break;
+                }
+                // Замеры до первой проверки выбросила бы пропущенная проверка прогрева
+                if sample < samples_per_interval && !warming_up {
+                    sys.refresh_processes_specifics(watcher.refresh_kind());
+                    watcher.record_sample(&sys);
+                }
+            }
+            if let Some(signal) = stop {
+                info!("Received {}", signal);
+                break;
+            }
+        }
+
+        // Файл изменился: пороги, фильтры и правила перечитываются, кулдауны сохраняются.
+        // Файл с ошибкой не применяется, работа продолжается с прежними настройками; файл,
+        // который не удалось прочитать, читается снова на следующей проверке
+        if let Some(config_watch) = &mut config_watch {
+            let reloaded = match config_watch.poll().await {
+                Reload::Unchanged => None,
+                Reload::Loaded(config) => Some(watcher.reload(&config, &sys)),
+                Reload::Failed(e) => Some(Err(e)),
+            };
+            match reloaded {
+                None => {}
+                Some(Ok(())) => info!(event = "config_reloaded"; "Reloaded thresholds, filters and rules from {}", config_watch.path().display()),
+                Some(Err(e)) => error!(event = "config_reload_failed", error:% = e; "Config reload rejected, keeping previous settings: {}", e),
+            }
+        }
+
+        // В режиме WATCH_PID/WATCH_PIDFILE обновляем только дерево наблюдаемого процесса
+        let watched = match &mut pid_watch {
+            Some(pid_watch) => {
+                let (tree, events) = pid_watch.refresh(&mut sys, watcher.refresh_kind());
+                for event in events {
+                    match event {
+                        WatchEvent::Started(pid, name) => info!("Watched process {} ({}) is running", pid, name),
+                        WatchEvent::Exited(pid, name) => {
+                            warn!("Watched process {} ({}) exited", pid, name);
+                            if config.alert_on_exit {
+                                let msg = message::sign(
+                                    format!(
+                                        "{} {}\nName: {}\nPID: {}",
+                                        markup.escape("⛔"),
+                                        fill(&strings.exited, markup, &[]),
+                                        markup.bold(&name),
+                                        pid
+                                    ),
+                                    markup,
+                                    footer.as_deref(),
+                                );
+                                if let Err(e) = notifier.send(&msg).await {
+                                    error!("Error sending exit notification: {}", e);
+                                }
+                            }
                         }
                     }
                 }
+                Some(tree)
+            }
+            None => {
+                sys.refresh_processes_specifics(watcher.refresh_kind());
+                None
+            }
+        };
+
+        // Mute и действия из кнопок и команд: Telegram слушает отдельная задача, а кулдауны и
+        // правила живут в Watcher
+        if let Some(requests) = &mut requests {
+            while let Ok(request) = requests.try_recv() {
+                let result = match request.command {
+                    Command::Mute(duration) => watcher.mute(&sys, request.pid, request.start_time, duration),
+                    Command::Unmute => watcher.unmute(&sys, request.pid),
+                    Command::Act(action) => watcher.act(&sys, request.pid, request.start_time, action),
+                };
+                let _ = request.reply.send(result);
+            }
+        }
+
+        let max_cpu = sys
+            .processes()
+            .values()
+            .map(|process| process.cpu_usage())
+            .fold(0.0, f32::max);
+        metrics.set_max_cpu(max_cpu);
+
+        sys.refresh_cpu_usage();
+        let context = ScanContext {
+            markup,
+            memory_limit: watcher.memory_limit(),
+            swap_limit: watcher.swap_limit(),
+            system_cpu: sys.global_cpu_info().cpu_usage(),
+            load_average: message::load_average(),
+            cores: if config.include_per_core {
+                sys.cpus().iter().map(|cpu| cpu.cpu_usage()).collect()
+            } else {
+                Vec::new()
+            },
+            footer: footer.clone(),
+            numbers,
+            strings: strings.clone(),
+        };
+        if let Some(adaptive) = &mut adaptive {
+            interval = adaptive.next(context.system_cpu);
+            debug!("System CPU {:.1}%, next check in {:.2}s", context.system_cpu, interval);
+        }
+
+        if boot_grace_until.is_some_and(|until| unix_now() >= until) {
+            boot_grace_until = None;
+            info!(event = "boot_grace_over"; "Boot grace period is over, alerting resumes");
+        }
+        let booting = boot_grace_until.is_some();
+
+        // Первую проверку после запуска пропускаем: в ней ещё шум прогрева
+        let mut alerts = if warming_up {
+            warming_up = false;
+            Vec::new()
+        } else {
+            watcher.check(&sys, watched.as_ref())
+        };
+        if booting && !alerts.is_empty() {
+            debug!("Boot grace: dropping {} alert(s)", alerts.len());
+            alerts.clear();
+        }
+        breaches = alerts.len();
+
+        // Самодиагностика: если /proc перестал читаться (seccomp, AppArmor, hidepid), командные
+        // строки из уведомлений молча пропадают — сообщаем об этом один раз
+        match watcher.take_proc_health() {
+            Some(ProcHealth::Degraded { failures, attempts }) => {
+                warn!(event = "proc_degraded", failures = failures, attempts = attempts;
+                    "Cannot read /proc: {} of {} cmdline reads failed", failures, attempts);
+                let values = [("failures", failures.to_string()), ("attempts", attempts.to_string())];
+                let msg = context.sign(format!("{} {}", markup.escape("🩺"), fill(&strings.proc_unreadable, markup, &values)));
+                if let Err(e) = notifier.send(&msg).await {
+                    error!("Error sending diagnostic notification: {}", e);
+                }
+            }
+            Some(ProcHealth::Recovered) => info!(event = "proc_recovered"; "Reading /proc works again"),
+            None => {}
+        }
+        // С OUTPUT=stdout-json в stdout только JSON-строки
+        if args.once && config.output != Output::StdoutJson {
+            for alert in &alerts {
+                println!(
+                    "{} (PID {}): CPU {:.1}%, {}, triggers {:?}",
+                    alert.process.name,
+                    alert.process.pid,
+                    alert.process.cpu_percent,
+                    alert.process.severity,
+                    alert.triggers
+                );
+            }
+        }
 
-                // Получаем полную командную строку как в psutil
-                let cmdline = read_cmdline_from_proc(*pid)
-                    .unwrap_or_else(|| process.name().to_string());
+        // NOTIFY_RECOVERY: закрываем оповещения, чьи процессы вернулись ниже порогов. В тихие
+        // часы восстановления только пишутся в лог
+        let recoveries = watcher.take_recoveries();
+        if !recoveries.is_empty() {
+            for recovery in &recoveries {
+                info!(event = "recovered", pid = recovery.pid.as_u32(); "Process {} (PID {}) is back below threshold", recovery.name, recovery.pid);
+            }
+            let quiet_now = quiet.as_ref().is_some_and(|quiet| quiet.is_quiet(chrono::Utc::now()));
+            if !quiet_now {
+                let msg = message::format_recovery(&recoveries, &context);
+                if let Err(e) = notifier.send(&msg).await {
+                    error!("Error sending recovery notification: {}", e);
+                }
+            }
+        }
 
-                let create_time = match process.start_time() {
-                    0 => None,
-                    start_time => {
-                        Some(DateTime::<Utc>::from(SystemTime::UNIX_EPOCH + Duration::from_secs(start_time as u64)))
+        // Тихие часы: некритичные оповещения копятся до конца окна (и сразу попадают в кулдаун),
+        // процессы из CRITICAL_PROCESSES уходят как обычно
+        if let Some(quiet) = &mut quiet {
+            if quiet.is_quiet(chrono::Utc::now()) {
+                let (critical, held): (Vec<_>, Vec<_>) = alerts.into_iter().partition(|alert| alert.process.critical);
+                if !held.is_empty() {
+                    info!(event = "quiet_hold", count = held.len(); "Quiet hours: holding {} alert(s)", held.len());
+                    quiet.hold(&held);
+                    watcher.mark_alerted(&held);
+                    if let Some(audit_log) = &mut audit_log {
+                        audit_log.record(&held, Some(Undelivered::QuietHours));
                     }
-                };
+                }
+                alerts = critical;
+            } else if let Some(summary) = quiet.summary(numbers, &strings) {
+                match notifier.send(&context.sign(markup.escape(&summary))).await {
+                    Ok(true) => quiet.clear(),
+                    Ok(false) => warn!("Failed to send quiet hours summary"),
+                    Err(e) => error!("Error sending quiet hours summary: {}", e),
+                }
+            }
+        }
 
-                let proc_info = ProcessInfo {
-                    name: process.name().to_string(),
-                    pid: *pid,
-                    cpu_percent: cpu,
-                    cmdline,
-                    create_time,
-                };
+        if !suppressed.is_empty() && rate_limiter.try_acquire(Instant::now()) {
+            let values = [("count", suppressed.len().to_string()), ("limit", config.max_alerts_per_minute.to_string())];
+            let summary = context.sign(format!("{} {}", markup.escape("⏸"), fill(&strings.suppressed, markup, &values)));
+            match notifier.send(&summary).await {
+                Ok(true) => suppressed.clear(),
+                Ok(false) => warn!("Failed to send suppression summary"),
+                Err(e) => error!("Error sending suppression summary: {}", e),
+            }
+        }
 
-                let msg = format_message(&proc_info, threshold);
-                
-                match send_telegram(&client, &bot_token, &chat_id, &msg).await {
-                    Ok(success) => {
-                        if success {
-                            alerted.insert(*pid, now);
-                        } else {
-                            warn!("Failed to send notification for PID {}", pid);
+        if !alerts.is_empty() && !rate_limiter.try_acquire(Instant::now()) {
+            warn!("Alert rate limit reached, suppressing {} alert(s)", alerts.len());
+            // Подавленные остаются нарушителями и повторяются каждую проверку: в журнал — один раз
+            let newly: Vec<_> = alerts.into_iter().filter(|alert| suppressed.insert(alert.process.pid)).collect();
+            metrics.alerts_suppressed.fetch_add(newly.len() as u64, Ordering::Relaxed);
+            if let Some(audit_log) = &mut audit_log {
+                audit_log.record(&newly, Some(Undelivered::RateLimited));
+            }
+        } else if !alerts.is_empty() {
+            // Все нарушители за один проход уходят одним сообщением, самые прожорливые сверху
+            alerts.sort_by(|a, b| b.process.cpu_percent.total_cmp(&a.process.cpu_percent));
+            let total = alerts.len();
+            alerts.truncate(config.max_batch_lines.max(1));
+
+            for alert in &alerts {
+                info!(
+                    event = "alert",
+                    pid = alert.process.pid.as_u32(),
+                    name = alert.process.name.as_str(),
+                    cpu = alert.process.cpu_percent,
+                    memory_bytes = alert.process.memory_bytes,
+                    triggers:? = alert.triggers;
+                    "Process {} (PID {}) breached {:?}",
+                    alert.process.name, alert.process.pid, alert.triggers
+                );
+            }
+
+            let msg = if total == 1 {
+                message::format_message(&alerts[0].process, &alerts[0].triggers, &config, &context, &template)
+            } else {
+                message::format_batch(&alerts, total, &config, &context)
+            };
+
+            let undelivered = match notifier.send_alerts(&alerts, &msg).await {
+                Ok(true) => {
+                    metrics.alerts_sent.fetch_add(1, Ordering::Relaxed);
+                    watcher.mark_alerted(&alerts);
+                    None
+                }
+                Ok(false) => {
+                    metrics.send_failures.fetch_add(1, Ordering::Relaxed);
+                    warn!(event = "send_failed", count = alerts.len(); "Failed to send notification for {} process(es)", alerts.len());
+                    Some(Undelivered::SendFailed)
+                }
+                // Предохранитель разомкнут: отправки не было, оповещение остаётся в логе и журнале
+                Err(e @ WatcherError::CircuitOpen { .. }) => {
+                    warn!(event = "send_skipped", count = alerts.len(), error:% = e; "Not sending notification for {} process(es): {}", alerts.len(), e);
+                    Some(Undelivered::CircuitOpen)
+                }
+                Err(e) => {
+                    metrics.send_failures.fetch_add(1, Ordering::Relaxed);
+                    error!(event = "send_failed", error:% = e; "Error sending notification: {}", e);
+                    Some(Undelivered::SendFailed)
+                }
+            };
+            if let Some(audit_log) = &mut audit_log {
+                audit_log.record(&alerts, undelivered);
+            }
+        }
+
+        // Видеокарты проверяются отдельно от процессов; лимит отправок на них не действует
+        #[cfg(feature = "gpu")]
+        if let Some(gpu_monitor) = gpu_monitor.as_mut().filter(|_| !booting) {
+            let now = Instant::now();
+            for alert in gpu_monitor.check(&sys, now) {
+                breaches += 1;
+                let msg = message::format_gpu_alert(&alert, &config, &context);
+                match notifier.send(&msg).await {
+                    Ok(true) => {
+                        metrics.alerts_sent.fetch_add(1, Ordering::Relaxed);
+                        gpu_monitor.mark_alerted(&alert, now);
+                    }
+                    Ok(false) => {
+                        metrics.send_failures.fetch_add(1, Ordering::Relaxed);
+                        warn!(event = "send_failed"; "Failed to send GPU {} notification", alert.index);
+                    }
+                    Err(e) => {
+                        metrics.send_failures.fetch_add(1, Ordering::Relaxed);
+                        error!(event = "send_failed", error:% = e; "Error sending GPU notification: {}", e);
+                    }
+                }
+            }
+        }
+
+        // Трафик хоста не делится по процессам и проверяется целиком, как видеокарты
+        if let Some(system_network) = system_network.as_mut().filter(|_| !booting) {
+            let now = Instant::now();
+            if let Some(alert) = system_network.check(&sys, now) {
+                breaches += 1;
+                let msg = message::format_network_alert(&alert, &config, &context);
+                match notifier.send(&msg).await {
+                    Ok(true) => {
+                        metrics.alerts_sent.fetch_add(1, Ordering::Relaxed);
+                        system_network.mark_alerted(now);
+                    }
+                    Ok(false) => {
+                        metrics.send_failures.fetch_add(1, Ordering::Relaxed);
+                        warn!(event = "send_failed"; "Failed to send network notification");
+                    }
+                    Err(e) => {
+                        metrics.send_failures.fetch_add(1, Ordering::Relaxed);
+                        error!(event = "send_failed", error:% = e; "Error sending network notification: {}", e);
+                    }
+                }
+            }
+        }
+
+        // Зомби — признак родителя, который не забирает потомков; кулдаун по родителю
+        if let Some(zombie_monitor) = zombie_monitor.as_mut().filter(|_| !booting) {
+            let now = Instant::now();
+            if let Some(alert) = zombie_monitor.check(&sys, now) {
+                breaches += 1;
+                let msg = message::format_zombie_alert(&alert, &config, &context);
+                match notifier.send(&msg).await {
+                    Ok(true) => {
+                        metrics.alerts_sent.fetch_add(1, Ordering::Relaxed);
+                        zombie_monitor.mark_alerted(&alert, now);
+                    }
+                    Ok(false) => {
+                        metrics.send_failures.fetch_add(1, Ordering::Relaxed);
+                        warn!(event = "send_failed"; "Failed to send zombie notification");
+                    }
+                    Err(e) => {
+                        metrics.send_failures.fetch_add(1, Ordering::Relaxed);
+                        error!(event = "send_failed", error:% = e; "Error sending zombie notification: {}", e);
+                    }
+                }
+            }
+        }
+
+        // Цикл перезапуска не виден по CPU: каждый экземпляр живёт недолго
+        if let Some(restart_monitor) = restart_monitor.as_mut().filter(|_| !booting) {
+            let now = Instant::now();
+            let alerts = restart_monitor.check(&sys, now);
+            if !alerts.is_empty() {
+                breaches += alerts.len();
+                let msg = message::format_restart_alert(&alerts, &config, &context);
+                match notifier.send(&msg).await {
+                    Ok(true) => {
+                        metrics.alerts_sent.fetch_add(1, Ordering::Relaxed);
+                        for alert in &alerts {
+                            restart_monitor.mark_alerted(alert, now);
                         }
                     }
+                    Ok(false) => {
+                        metrics.send_failures.fetch_add(1, Ordering::Relaxed);
+                        warn!(event = "send_failed"; "Failed to send restart loop notification");
+                    }
                     Err(e) => {
-                        error!("Error sending Telegram message: {}", e);
+                        metrics.send_failures.fetch_add(1, Ordering::Relaxed);
+                        error!(event = "send_failed", error:% = e; "Error sending restart loop notification: {}", e);
                     }
                 }
             }
         }
 
+        // Пульс: подтверждает, что watcher жив и канал доставки работает; лимит отправок не учитывается
+        if !heartbeat.is_zero() && last_heartbeat.elapsed() >= heartbeat {
+            last_heartbeat = Instant::now();
+            let values = [("count", sys.processes().len().to_string()), ("top_cpu", markup.escape(&numbers.percent(max_cpu)))];
+            let msg = context.sign(format!("{} {}", markup.escape("💓"), fill(&strings.heartbeat, markup, &values)));
+            match notifier.send(&msg).await {
+                Ok(true) => {}
+                Ok(false) => warn!(event = "heartbeat_failed"; "Failed to send heartbeat"),
+                Err(e) => error!(event = "heartbeat_failed", error:% = e; "Error sending heartbeat: {}", e),
+            }
+        }
+
+        // Сводка по расписанию не зависит от порогов и оповещений; лимит отправок не учитывается
+        if !digest.is_zero() && last_digest.elapsed() >= digest {
+            last_digest = Instant::now();
+            let msg = message::format_digest(&sys, &config, &context);
+            match notifier.send(&msg).await {
+                Ok(true) => {}
+                Ok(false) => warn!(event = "digest_failed"; "Failed to send digest"),
+                Err(e) => error!(event = "digest_failed", error:% = e; "Error sending digest: {}", e),
+            }
+        }
+
         // Очистка старых записей (чтобы не накапливались)
-        let cutoff = SystemTime::now() - Duration::from_secs(cooldown_seconds * 5);
-        alerted.retain(|_, time| *time > cutoff);
+        watcher.prune(&sys);
+
+        metrics.checks.fetch_add(1, Ordering::Relaxed);
+        metrics.record_scan();
+        if let Some(sd_notify) = &sd_notify {
+            sd_notify.watchdog();
+        }
+        if args.once {
+            break;
+        }
+    }
+
+    info!(event = "shutdown"; "shutting down");
+    if let Some(sd_notify) = &sd_notify {
+        sd_notify.stopping();
+    }
+    if notify_lifecycle {
+        let msg = message::format_stopping(started.elapsed(), &strings, markup, footer.as_deref());
+        match notifier.send(&msg).await {
+            Ok(true) => {}
+            Ok(false) => warn!(event = "stop_notify_failed"; "Failed to send shutdown notification"),
+            Err(e) => error!(event = "stop_notify_failed", error:% = e; "Error sending shutdown notification: {}", e),
+        }
+    }
+    if let Some(path) = &config.state_file {
+        if let Err(e) = state::save(path, watcher.alerted(), &sys) {
+            error!("Cannot save state file {}: {}", path.display(), e);
+        }
+    }
+    if args.once && breaches > 0 {
+        std::process::exit(EXIT_BREACHES);
     }
+    Ok(())
 }
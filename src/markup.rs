@@ -0,0 +1,80 @@
+use serde::Deserialize;
+use std::str::FromStr;
+
+// Режим разметки Telegram (parse_mode в sendMessage)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum ParseMode {
+    MarkdownV2,
+    #[serde(rename = "HTML")]
+    Html,
+}
+
+impl ParseMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ParseMode::MarkdownV2 => "MarkdownV2",
+            ParseMode::Html => "HTML",
+        }
+    }
+}
+
+impl FromStr for ParseMode {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, ()> {
+        match value.to_lowercase().as_str() {
+            "markdownv2" => Ok(ParseMode::MarkdownV2),
+            "html" => Ok(ParseMode::Html),
+            _ => Err(()),
+        }
+    }
+}
+
+// Экранирование и оформление текста под выбранный режим; без режима текст идёт как есть.
+// В MarkdownV2 весь текст вне разметки должен быть экранирован, иначе Telegram отклонит сообщение.
+#[derive(Clone, Copy)]
+pub struct Markup(pub Option<ParseMode>);
+
+impl Markup {
+    pub fn escape(&self, text: &str) -> String {
+        match self.0 {
+            None => text.to_string(),
+            Some(ParseMode::MarkdownV2) => escape_with(text, "_*[]()~`>#+-=|{}.!\\"),
+            Some(ParseMode::Html) => escape_html(text),
+        }
+    }
+
+    pub fn bold(&self, text: &str) -> String {
+        match self.0 {
+            None => text.to_string(),
+            Some(ParseMode::MarkdownV2) => format!("*{}*", self.escape(text)),
+            Some(ParseMode::Html) => format!("<b>{}</b>", self.escape(text)),
+        }
+    }
+
+    pub fn code(&self, text: &str) -> String {
+        match self.0 {
+            None => text.to_string(),
+            // Внутри `...` экранируются только обратная кавычка и обратный слеш
+            Some(ParseMode::MarkdownV2) => format!("`{}`", escape_with(text, "`\\")),
+            Some(ParseMode::Html) => format!("<code>{}</code>", self.escape(text)),
+        }
+    }
+}
+
+fn escape_with(text: &str, special: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if special.contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
@@ -0,0 +1,688 @@
+use crate::config::{Config, Output, ThresholdMode};
+use crate::locale::{fill, Strings};
+use crate::markup::Markup;
+use crate::network::SystemNetAlert;
+use crate::restart::RestartAlert;
+use crate::zombie::ZombieAlert;
+use crate::template::{Field, Template};
+use crate::{Alert, AlertKind, DiskRate, NetRate, ProcessInfo, Recovery, Severity, Trend};
+use std::borrow::Cow;
+use std::sync::Arc;
+use std::time::Duration;
+use sysinfo::{LoadAvg, System};
+
+// Общие для всей проверки данные и оформление, с которыми собирается сообщение
+pub struct ScanContext {
+    pub markup: Markup,
+    pub memory_limit: Option<u64>,
+    pub swap_limit: Option<u64>,
+    pub system_cpu: f32,
+    pub load_average: Option<LoadAvg>,
+    /// Загрузка каждого ядра по порядку номеров; пусто без INCLUDE_PER_CORE.
+    pub cores: Vec<f32>,
+    /// Подпись в конце каждого сообщения (см. [`footer`]).
+    pub footer: Option<String>,
+    pub numbers: NumberFormat,
+    pub strings: Arc<Strings>,
+}
+
+impl ScanContext {
+    pub fn sign(&self, text: String) -> String {
+        sign(text, self.markup, self.footer.as_deref())
+    }
+
+    // "…и ещё N" под обрезанным списком
+    fn more(&self, count: usize) -> String {
+        fill(&self.strings.more, self.markup, &[("count", count.to_string())])
+    }
+
+    fn system_line(&self) -> String {
+        let load = match &self.load_average {
+            Some(load) => [load.one, load.five, load.fifteen]
+                .iter()
+                .map(|value| self.numbers.localize(format!("{:.2}", value)))
+                .collect::<Vec<_>>()
+                .join(" "),
+            None => "n/a".to_string(),
+        };
+        let mut line = format!("System: CPU {}%, load {}", self.numbers.percent(self.system_cpu), load);
+        if !self.cores.is_empty() {
+            line.push_str(&format!(", busiest cores {}", self.busiest_cores()));
+        }
+        line
+    }
+
+    // Однопоточный процесс занимает одно ядро целиком, а в общем CPU это незаметно
+    fn busiest_cores(&self) -> String {
+        let mut cores: Vec<(usize, f32)> = self.cores.iter().copied().enumerate().collect();
+        cores.sort_by(|a, b| b.1.total_cmp(&a.1));
+        cores
+            .iter()
+            .take(BUSIEST_CORES)
+            .map(|(index, usage)| format!("#{} {}%", index, self.numbers.percent(*usage)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+// Сколько ядер показывать с INCLUDE_PER_CORE: на больших машинах полный список слишком длинный
+const BUSIEST_CORES: usize = 3;
+
+// Языки, в которых дробная часть отделяется запятой
+const COMMA_LANGUAGES: [&str; 22] = [
+    "be", "bg", "cs", "da", "de", "el", "es", "fi", "fr", "hr", "hu", "id", "it", "kk", "nb", "nl", "pl", "pt", "ro",
+    "ru", "sv", "uk",
+];
+
+/// Оформление процентов в сообщениях: `cpu_decimals` знаков после запятой и десятичный
+/// разделитель по `number_locale`. Разделитель разрядов не ставится.
+#[derive(Clone, Copy, Debug)]
+pub struct NumberFormat {
+    pub decimals: usize,
+    pub separator: char,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        NumberFormat {
+            decimals: 1,
+            separator: '.',
+        }
+    }
+}
+
+impl NumberFormat {
+    pub fn new(config: &Config) -> Self {
+        let locale = match config.number_locale.as_deref() {
+            Some("system") => ["LC_ALL", "LC_NUMERIC", "LANG"]
+                .iter()
+                .filter_map(|name| std::env::var(name).ok())
+                .find(|value| !value.is_empty()),
+            locale => locale.map(str::to_string),
+        };
+        NumberFormat {
+            decimals: config.cpu_decimals,
+            separator: locale.map_or('.', |locale| decimal_separator(&locale)),
+        }
+    }
+
+    pub fn percent(&self, value: f32) -> String {
+        self.localize(format!("{:.*}", self.decimals, value))
+    }
+
+    // Только разделитель, без изменения точности: для load average
+    fn localize(&self, text: String) -> String {
+        if self.separator == '.' {
+            text
+        } else {
+            text.replace('.', &self.separator.to_string())
+        }
+    }
+
+    fn trend(&self, trend: Trend) -> String {
+        format!("{} (peak {}%)", trend.arrow(), self.percent(trend.peak))
+    }
+}
+
+// "ru_RU.UTF-8", "de-AT", "fr" → язык до '_', '-' или '.'; C, POSIX и неизвестные языки — точка
+fn decimal_separator(locale: &str) -> char {
+    let language = locale.split(['_', '-', '.', '@']).next().unwrap_or_default().to_lowercase();
+    if COMMA_LANGUAGES.contains(&language.as_str()) {
+        ','
+    } else {
+        '.'
+    }
+}
+
+/// Подпись сообщений: имя машины (INCLUDE_HOSTNAME) и версия cpu_watcher (INCLUDE_VERSION),
+/// чтобы в общем канале было видно, откуда пришло оповещение. None, если обе выключены.
+pub fn footer(config: &Config) -> Option<String> {
+    let mut parts = Vec::new();
+    if config.include_hostname {
+        if let Some(host) = System::host_name() {
+            parts.push(format!("Host: {}", host));
+        }
+    }
+    if config.include_version {
+        parts.push(format!("cpu_watcher {}", env!("CARGO_PKG_VERSION")));
+    }
+    (!parts.is_empty()).then(|| parts.join(", "))
+}
+
+/// Дописывает подпись последней строкой.
+pub fn sign(text: String, markup: Markup, footer: Option<&str>) -> String {
+    match footer {
+        Some(footer) => format!("{}\n{}", text, markup.escape(footer)),
+        None => text,
+    }
+}
+
+// На Windows sysinfo не умеет load average и возвращает нули
+pub fn load_average() -> Option<LoadAvg> {
+    if cfg!(target_os = "windows") {
+        None
+    } else {
+        Some(System::load_average())
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (days, hours, minutes, seconds) = (secs / 86400, secs / 3600 % 24, secs / 60 % 60, secs % 60);
+    if days > 0 {
+        format!("{}d {}h {}m", days, hours, minutes)
+    } else if hours > 0 {
+        format!("{}h {}m {}s", hours, minutes, seconds)
+    } else if minutes > 0 {
+        format!("{}m {}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+fn format_disk_rate(rate: DiskRate) -> String {
+    format!(
+        "read {}/s, write {}/s",
+        format_bytes(rate.read_per_sec),
+        format_bytes(rate.write_per_sec)
+    )
+}
+
+fn format_net_rate(rate: NetRate) -> String {
+    format!(
+        "rx {}/s, tx {}/s",
+        format_bytes(rate.rx_per_sec),
+        format_bytes(rate.tx_per_sec)
+    )
+}
+
+// wchan — функция ядра, где процесс ждёт; без неё видно только сколько
+fn format_d_state(duration: Duration, wchan: Option<&str>) -> String {
+    match wchan {
+        Some(wchan) => format!("{}, wchan {}", format_duration(duration), wchan),
+        None => format_duration(duration),
+    }
+}
+
+// Командная строка длиннее этого обрезается: java с длинным classpath даёт десятки тысяч
+// символов, а такое сообщение пришлось бы резать на несколько
+const MAX_CMDLINE_CHARS: usize = 1000;
+
+fn short_cmdline(cmdline: &str) -> Cow<'_, str> {
+    match cmdline.char_indices().nth(MAX_CMDLINE_CHARS) {
+        Some((end, _)) => Cow::Owned(format!("{}…", &cmdline[..end])),
+        None => Cow::Borrowed(cmdline),
+    }
+}
+
+// Короткий ID, как в `docker ps`
+fn short_container_id(id: &str) -> &str {
+    id.get(..crate::container::SHORT_ID_LEN).unwrap_or(id)
+}
+
+pub fn format_message(
+    proc_info: &ProcessInfo,
+    triggers: &[AlertKind],
+    config: &Config,
+    context: &ScanContext,
+    template: &Template,
+) -> String {
+    let m = context.markup;
+    let strings = &context.strings;
+    let text = template.render(m, |field| match field {
+        Field::Headline => triggers
+            .iter()
+            .map(|kind| {
+                let (pattern, limit) = match kind {
+                    AlertKind::Cpu => (&strings.headline_cpu, context.numbers.percent(proc_info.cpu_threshold)),
+                    AlertKind::Memory => (&strings.headline_memory, format_bytes(context.memory_limit.unwrap_or_default())),
+                    AlertKind::DiskIo => {
+                        (&strings.headline_disk_io, format_bytes(config.disk_io_threshold.unwrap_or_default()))
+                    }
+                    AlertKind::Network => (&strings.headline_network, format_bytes(config.net_threshold.unwrap_or_default())),
+                    AlertKind::OpenFiles => (&strings.headline_open_files, config.fd_threshold.unwrap_or_default().to_string()),
+                    AlertKind::Threads => (&strings.headline_threads, config.thread_threshold.unwrap_or_default().to_string()),
+                    AlertKind::Swap => (&strings.headline_swap, format_bytes(context.swap_limit.unwrap_or_default())),
+                    AlertKind::Score => {
+                        (&strings.headline_score, context.numbers.percent(config.score_threshold.unwrap_or_default()))
+                    }
+                    AlertKind::Runtime => (
+                        &strings.headline_runtime,
+                        format_duration(Duration::from_secs(config.max_runtime_seconds.unwrap_or_default())),
+                    ),
+                    AlertKind::DState => (
+                        &strings.headline_d_state,
+                        format_duration(Duration::from_secs(config.d_state_seconds.unwrap_or_default())),
+                    ),
+                    AlertKind::CpuTime => (
+                        &strings.headline_cpu_time,
+                        format_duration(Duration::from_secs(config.cpu_time_budget.unwrap_or_default())),
+                    ),
+                };
+                let values = [
+                    ("limit", m.escape(&limit)),
+                    ("threshold", m.escape(&limit)),
+                    ("mode", m.escape(&config.threshold_mode.to_string())),
+                ];
+                format!("{} {}", m.escape(proc_info.severity.emoji()), fill(pattern, m, &values))
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Field::Severity => proc_info.severity.to_string(),
+        Field::Name => m.bold(&proc_info.name),
+        Field::Exe => proc_info.exe_path.as_deref().map(|path| m.code(path)).unwrap_or_default(),
+        Field::Env => proc_info.matched_env.iter().map(|entry| m.code(entry)).collect::<Vec<_>>().join(", "),
+        Field::Pid => proc_info.pid.to_string(),
+        Field::Rule => proc_info.rule.as_deref().map(|rule| m.escape(rule)).unwrap_or_default(),
+        Field::Cpu => m.escape(&context.numbers.percent(proc_info.cpu_percent)),
+        Field::Aggregate => proc_info
+            .children
+            .map(|children| m.escape(&format!("aggregate CPU across {} child processes", children)))
+            .unwrap_or_default(),
+        Field::Threshold => m.escape(&context.numbers.percent(proc_info.cpu_threshold)),
+        Field::Mode => match proc_info.cpu_samples {
+            Some(samples) => m.escape(&format!("{}, average of {} samples", config.threshold_mode, samples)),
+            None => m.escape(&config.threshold_mode.to_string()),
+        },
+        Field::Trend => proc_info.trend.map(|trend| m.escape(&context.numbers.trend(trend))).unwrap_or_default(),
+        Field::CpuTime => proc_info.cpu_time.map(|time| m.escape(&format_duration(time))).unwrap_or_default(),
+        Field::Mem => m.escape(&format_bytes(proc_info.memory_bytes)),
+        Field::OverLimit => proc_info
+            .over_limit_for
+            .map(|duration| m.escape(&format_duration(duration)))
+            .unwrap_or_default(),
+        Field::Disk => proc_info.disk_io.map(|rate| m.escape(&format_disk_rate(rate))).unwrap_or_default(),
+        Field::Net => proc_info
+            .network
+            .map(|rate| m.escape(&format!("{} (whole network namespace)", format_net_rate(rate))))
+            .unwrap_or_default(),
+        Field::OpenFiles => proc_info.open_files.map(|count| count.to_string()).unwrap_or_default(),
+        Field::Threads => proc_info.threads.map(|count| count.to_string()).unwrap_or_default(),
+        Field::Swap => proc_info.swap_bytes.map(|bytes| m.escape(&format_bytes(bytes))).unwrap_or_default(),
+        Field::DState => proc_info
+            .d_state_for
+            .map(|duration| m.escape(&format_d_state(duration, proc_info.wchan.as_deref())))
+            .unwrap_or_default(),
+        Field::Score => proc_info
+            .score
+            .map(|score| {
+                let numbers = context.numbers;
+                m.escape(&format!(
+                    "{} (CPU {}% × {} + Mem {}% × {})",
+                    numbers.percent(score.value),
+                    numbers.percent(score.cpu_percent),
+                    config.score_cpu_weight,
+                    numbers.percent(score.memory_percent),
+                    config.score_memory_weight
+                ))
+            })
+            .unwrap_or_default(),
+        Field::Age => proc_info.age.map(|age| m.escape(&format_duration(age))).unwrap_or_default(),
+        Field::Action => proc_info.action.as_deref().map(|action| m.escape(action)).unwrap_or_default(),
+        Field::Started => m.escape(
+            &proc_info
+                .create_time
+                .map(|t| t.to_rfc3339())
+                .unwrap_or_else(|| "?".to_string()),
+        ),
+        Field::Container => proc_info
+            .container
+            .as_deref()
+            .map(|id| m.code(short_container_id(id)))
+            .unwrap_or_default(),
+        Field::Cmd => m.code(&short_cmdline(&proc_info.cmdline)),
+        Field::System => m.escape(&context.system_line()),
+    });
+    context.sign(text)
+}
+
+// Оповещение о видеокарте; шаблон MESSAGE_TEMPLATE к нему не применяется
+#[cfg(feature = "gpu")]
+pub fn format_gpu_alert(alert: &crate::gpu::GpuAlert, config: &Config, context: &ScanContext) -> String {
+    use crate::gpu::GpuAlertKind;
+
+    let m = context.markup;
+    let strings = &context.strings;
+    let mut lines: Vec<String> = alert
+        .triggers
+        .iter()
+        .map(|kind| match kind {
+            GpuAlertKind::Utilization => fill(
+                &strings.gpu_utilization,
+                m,
+                &[("limit", m.escape(&context.numbers.percent(config.gpu_threshold.unwrap_or_default())))],
+            ),
+            GpuAlertKind::Memory => fill(&strings.gpu_memory, m, &[]),
+        })
+        .map(|headline| format!("{} {}", m.escape("⚠"), headline))
+        .collect();
+    lines.push(format!("GPU: {}", m.bold(&format!("{} ({})", alert.index, alert.name))));
+    lines.push(m.escape(&format!("Util: {}%", alert.utilization)));
+    lines.push(m.escape(&format!(
+        "Mem: {} / {}",
+        format_bytes(alert.memory_used),
+        format_bytes(alert.memory_total)
+    )));
+    if let Some((pid, name, used)) = &alert.top_process {
+        lines.push(format!(
+            "Process: {}{}",
+            m.bold(name),
+            m.escape(&format!(" (PID {}, {})", pid, format_bytes(*used)))
+        ));
+    }
+    lines.push(m.escape(&context.system_line()));
+    context.sign(lines.join("\n"))
+}
+
+// Трафик всей системы: по процессам его не разделить, поэтому показан процесс с наибольшим CPU
+pub fn format_network_alert(alert: &SystemNetAlert, config: &Config, context: &ScanContext) -> String {
+    let m = context.markup;
+    let limit = format_bytes(config.net_threshold.unwrap_or_default());
+    let mut lines = vec![format!("{} {}", m.escape("⚠"), fill(&context.strings.system_network, m, &[("limit", m.escape(&limit))]))];
+    lines.push(m.escape(&format!(
+        "Net: {} (system-wide, per-process data unavailable)",
+        format_net_rate(alert.rate)
+    )));
+    if let Some((pid, name, cpu)) = &alert.top_process {
+        lines.push(format!(
+            "Top CPU: {}{}",
+            m.bold(name),
+            m.escape(&format!(" (PID {}, {}%)", pid, context.numbers.percent(*cpu)))
+        ));
+    }
+    lines.push(m.escape(&context.system_line()));
+    context.sign(lines.join("\n"))
+}
+
+// Сколько PID зомби перечислять у одного родителя
+const MAX_ZOMBIE_PIDS: usize = 10;
+
+// Зомби по родителям: чинить надо родителя, который не вызывает wait()
+pub fn format_zombie_alert(alert: &ZombieAlert, config: &Config, context: &ScanContext) -> String {
+    let m = context.markup;
+    let values = [
+        ("count", alert.total.to_string()),
+        ("limit", config.zombie_threshold.unwrap_or_default().to_string()),
+    ];
+    let mut lines = vec![format!("{} {}", m.escape("⚠"), fill(&context.strings.zombies, m, &values))];
+    for parent in alert.parents.iter().take(config.max_batch_lines) {
+        let mut zombies: Vec<String> = parent.zombies.iter().take(MAX_ZOMBIE_PIDS).map(ToString::to_string).collect();
+        if parent.zombies.len() > MAX_ZOMBIE_PIDS {
+            zombies.push(format!("…+{}", parent.zombies.len() - MAX_ZOMBIE_PIDS));
+        }
+        let parent_pid = parent.pid.map_or_else(|| "?".to_string(), |pid| pid.to_string());
+        lines.push(format!(
+            "Parent: {}{}",
+            m.bold(&parent.name),
+            m.escape(&format!(" (PID {}), zombies: {}", parent_pid, zombies.join(", ")))
+        ));
+    }
+    if alert.parents.len() > config.max_batch_lines {
+        lines.push(context.more(alert.parents.len() - config.max_batch_lines));
+    }
+    lines.push(m.escape(&context.system_line()));
+    context.sign(lines.join("\n"))
+}
+
+// Циклы перезапуска: частота запусков и командная строка, по которой их сосчитали
+pub fn format_restart_alert(alerts: &[RestartAlert], config: &Config, context: &ScanContext) -> String {
+    let m = context.markup;
+    let window = alerts.first().map_or(Duration::ZERO, |alert| alert.window);
+    let values = [
+        ("limit", m.escape(&config.restart_threshold.unwrap_or_default().to_string())),
+        ("window", m.escape(&format_duration(window))),
+    ];
+    let mut lines = vec![format!("{} {}", m.escape("🔁"), fill(&context.strings.restarts, m, &values))];
+    for alert in alerts.iter().take(config.max_batch_lines) {
+        lines.push(format!(
+            "{}{}",
+            m.bold(&alert.name),
+            m.escape(&format!(
+                ": {} restarts ({:.1}/min), last PID {}",
+                alert.restarts, alert.per_minute, alert.pid
+            ))
+        ));
+        lines.push(format!("Cmd: {}", m.code(&short_cmdline(&alert.cmdline))));
+    }
+    if alerts.len() > config.max_batch_lines {
+        lines.push(context.more(alerts.len() - config.max_batch_lines));
+    }
+    lines.push(m.escape(&context.system_line()));
+    context.sign(lines.join("\n"))
+}
+
+// Сколько процессов в каждом списке сводки DIGEST_SECONDS
+const DIGEST_TOP: usize = 5;
+
+/// Сводка по расписанию, без порогов: топ процессов по CPU и по памяти из уже обновлённого `sys`.
+pub fn format_digest(sys: &System, config: &Config, context: &ScanContext) -> String {
+    let m = context.markup;
+    let cpu_count = sys.cpus().len().max(1) as f32;
+    // Потоки повторяли бы память своего процесса
+    let mut processes: Vec<_> = sys.processes().iter().filter(|(_, process)| process.thread_kind().is_none()).collect();
+    let line = |position: usize, name: &str, details: String| {
+        format!("{}. {}{}", position + 1, m.bold(name), m.escape(&details))
+    };
+
+    let mut lines = vec![format!(
+        "{} {}",
+        m.escape("📊"),
+        fill(&context.strings.digest, m, &[("count", processes.len().to_string())])
+    )];
+    lines.push(m.escape(&format!("Top CPU ({}):", config.threshold_mode)));
+    processes.sort_by(|a, b| b.1.cpu_usage().total_cmp(&a.1.cpu_usage()));
+    for (position, (pid, process)) in processes.iter().take(DIGEST_TOP).enumerate() {
+        let cpu = match config.threshold_mode {
+            ThresholdMode::PerCore => process.cpu_usage(),
+            ThresholdMode::Normalized => process.cpu_usage() / cpu_count,
+        };
+        lines.push(line(position, process.name(), format!(" (PID {}): {}%", pid, context.numbers.percent(cpu))));
+    }
+    lines.push(m.escape("Top memory:"));
+    processes.sort_by_key(|(_, process)| std::cmp::Reverse(process.memory()));
+    for (position, (pid, process)) in processes.iter().take(DIGEST_TOP).enumerate() {
+        lines.push(line(position, process.name(), format!(" (PID {}): {}", pid, format_bytes(process.memory()))));
+    }
+    lines.push(m.escape(&context.system_line()));
+    context.sign(lines.join("\n"))
+}
+
+fn host_name() -> String {
+    System::host_name().unwrap_or_else(|| "unknown host".to_string())
+}
+
+/// Сообщение о запуске (NOTIFY_ON_START): машина и действующие настройки. Каналы
+/// перечисляются только по названию — токен и адреса вебхуков в сообщение не попадают.
+pub fn format_started(
+    config: &Config,
+    strings: &Strings,
+    markup: Markup,
+    footer: Option<&str>,
+    numbers: NumberFormat,
+) -> String {
+    let m = markup;
+    let backends: Vec<String> = if config.output == Output::StdoutJson {
+        vec!["stdout-json".to_string()]
+    } else if config.dry_run {
+        vec!["dry-run".to_string()]
+    } else {
+        config.backends().iter().map(ToString::to_string).collect()
+    };
+    let lines = [
+        format!("{} {}", m.escape("🟢"), fill(&strings.started, m, &[("host", m.bold(&host_name()))])),
+        m.escape(&format!("Threshold: CPU {}% ({})", numbers.percent(config.threshold), config.threshold_mode)),
+        m.escape(&format!("Check interval: {}s, cooldown {}s", config.check_interval, config.cooldown_seconds)),
+        m.escape(&format!("Notifiers: {}", backends.join(", "))),
+    ];
+    sign(lines.join("\n"), markup, footer)
+}
+
+/// Сообщение об остановке по SIGTERM/SIGINT, парное к [`format_started`].
+pub fn format_stopping(uptime: Duration, strings: &Strings, markup: Markup, footer: Option<&str>) -> String {
+    let m = markup;
+    let values = [("host", m.bold(&host_name())), ("uptime", m.escape(&format_duration(uptime)))];
+    let text = format!("{} {}", m.escape("🔴"), fill(&strings.stopping, m, &values));
+    sign(text, markup, footer)
+}
+
+// Закрытие оповещений: по строке на процесс, вернувшийся ниже порогов
+pub fn format_recovery(recoveries: &[Recovery], context: &ScanContext) -> String {
+    let m = context.markup;
+    let text = recoveries
+        .iter()
+        .map(|recovery| {
+            let values = [("name", m.bold(&recovery.name)), ("pid", recovery.pid.to_string())];
+            format!("{} {}", m.escape("✅"), fill(&context.strings.recovered, m, &values))
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    context.sign(text)
+}
+
+// Сводка по нескольким процессам: по строке на процесс, остальные (сверх MAX_BATCH_LINES) — числом
+pub fn format_batch(alerts: &[Alert], total: usize, config: &Config, context: &ScanContext) -> String {
+    let m = context.markup;
+    let severity = alerts.iter().map(|alert| alert.process.severity).max().unwrap_or(Severity::Warning);
+    let values = [("count", total.to_string()), ("mode", m.escape(&config.threshold_mode.to_string()))];
+    let mut lines = vec![format!("{} {}", m.escape(severity.emoji()), fill(&context.strings.batch, m, &values))];
+    for alert in alerts {
+        let mut details = format!(" (PID {}): CPU {}%", alert.process.pid, context.numbers.percent(alert.process.cpu_percent));
+        if let Some(id) = &alert.process.container {
+            details.push_str(&format!(" in container {}", short_container_id(id)));
+        }
+        if let Some(children) = alert.process.children {
+            details.push_str(&format!(" across {} children", children));
+        }
+        if let Some(trend) = alert.process.trend {
+            details.push_str(&format!(" {}", context.numbers.trend(trend)));
+        }
+        if alert.triggers.contains(&AlertKind::Memory) {
+            details.push_str(&format!(", Mem {}", format_bytes(alert.process.memory_bytes)));
+        }
+        if let Some(rate) = alert.process.disk_io.filter(|_| alert.triggers.contains(&AlertKind::DiskIo)) {
+            details.push_str(&format!(", disk {}", format_disk_rate(rate)));
+        }
+        if let Some(rate) = alert.process.network.filter(|_| alert.triggers.contains(&AlertKind::Network)) {
+            details.push_str(&format!(", net {}", format_net_rate(rate)));
+        }
+        if let Some(count) = alert.process.open_files.filter(|_| alert.triggers.contains(&AlertKind::OpenFiles)) {
+            details.push_str(&format!(", {} open files", count));
+        }
+        if let Some(count) = alert.process.threads.filter(|_| alert.triggers.contains(&AlertKind::Threads)) {
+            details.push_str(&format!(", {} threads", count));
+        }
+        if let Some(bytes) = alert.process.swap_bytes.filter(|_| alert.triggers.contains(&AlertKind::Swap)) {
+            details.push_str(&format!(", swap {}", format_bytes(bytes)));
+        }
+        if let Some(duration) = alert.process.d_state_for.filter(|_| alert.triggers.contains(&AlertKind::DState)) {
+            details.push_str(&format!(", D state {}", format_d_state(duration, alert.process.wchan.as_deref())));
+        }
+        if let Some(score) = alert.process.score.filter(|_| alert.triggers.contains(&AlertKind::Score)) {
+            details.push_str(&format!(", score {}", context.numbers.percent(score.value)));
+        }
+        if let Some(time) = alert.process.cpu_time.filter(|_| alert.triggers.contains(&AlertKind::CpuTime)) {
+            details.push_str(&format!(", CPU time {}", format_duration(time)));
+        }
+        if let Some(age) = alert.process.age.filter(|_| alert.triggers.contains(&AlertKind::Runtime)) {
+            details.push_str(&format!(", running {}", format_duration(age)));
+        }
+        if let Some(action) = &alert.process.action {
+            details.push_str(&format!(", action: {}", action));
+        }
+        lines.push(format!(
+            "{} {}{}",
+            alert.process.severity.emoji(),
+            m.bold(&alert.process.name),
+            m.escape(&details)
+        ));
+    }
+    if total > alerts.len() {
+        lines.push(context.more(total - alerts.len()));
+    }
+    lines.push(m.escape(&context.system_line()));
+    context.sign(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markup::ParseMode;
+    use crate::{CannedProcess, CannedProcesses, Watcher};
+
+    fn process_info(name: &str, cmdline: &str) -> ProcessInfo {
+        let config = Config::default();
+        let mut processes = CannedProcesses::new(1, 8 << 30);
+        processes.insert(CannedProcess {
+            cmdline: Some(cmdline.to_string()),
+            ..CannedProcess::new(100, name, 95.0)
+        });
+        let mut watcher = Watcher::new(&config, &processes).unwrap();
+        watcher.enable_actions = false;
+        watcher.check(&processes, None).remove(0).process
+    }
+
+    fn render(template: &str, name: &str, cmdline: &str, parse_mode: Option<ParseMode>) -> String {
+        let context = ScanContext {
+            markup: Markup(parse_mode),
+            memory_limit: None,
+            swap_limit: None,
+            system_cpu: 0.0,
+            load_average: None,
+            cores: Vec::new(),
+            footer: None,
+            numbers: NumberFormat::default(),
+            strings: Arc::new(Strings::en()),
+        };
+        let template = Template::parse(template).unwrap();
+        format_message(&process_info(name, cmdline), &[AlertKind::Cpu], &Config::default(), &context, &template)
+    }
+
+    fn render_cmd(cmdline: &str, parse_mode: Option<ParseMode>) -> String {
+        render("{cmd}", "worker", cmdline, parse_mode)
+    }
+
+    #[test]
+    fn markdown_escapes_text_outside_code() {
+        let special = "_*[]()~>#+-=|{}.!";
+        let text = render("{name} ({pid})", &format!("w{}", special), "w", Some(ParseMode::MarkdownV2));
+        assert_eq!(text, "*w\\_\\*\\[\\]\\(\\)\\~\\>\\#\\+\\-\\=\\|\\{\\}\\.\\!* \\(100\\)");
+    }
+
+    #[test]
+    fn markdown_cmdline_is_a_code_span() {
+        let special = "_*[]()~>#+-=|{}.!";
+        let text = render_cmd(&format!("run {} `x` \\", special), Some(ParseMode::MarkdownV2));
+        // Внутри `...` экранируются только обратная кавычка и обратный слеш
+        assert_eq!(text, format!("`run {} \\`x\\` \\\\`", special));
+    }
+
+    #[test]
+    fn html_cmdline_is_escaped() {
+        let text = render_cmd("grep '<a>' && cat a&b > out", Some(ParseMode::Html));
+        assert_eq!(text, "<code>grep '&lt;a&gt;' &amp;&amp; cat a&amp;b &gt; out</code>");
+    }
+
+    #[test]
+    fn long_cmdline_is_cut() {
+        let cmdline = format!("java -cp {}", "ж".repeat(5000));
+        let text = render_cmd(&cmdline, None);
+        assert_eq!(text.chars().count(), MAX_CMDLINE_CHARS + 1);
+        assert!(text.ends_with('…'));
+        assert!(cmdline.starts_with(text.trim_end_matches('…')));
+        assert_eq!(render_cmd("short", None), "short");
+    }
+}
@@ -0,0 +1,121 @@
+use log::{info, warn};
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+// Счётчики, которые основной цикл обновляет, а HTTP-сервер отдаёт в формате Prometheus
+#[derive(Default)]
+pub struct Metrics {
+    pub checks: AtomicU64,
+    pub alerts_sent: AtomicU64,
+    pub send_failures: AtomicU64,
+    pub alerts_suppressed: AtomicU64,
+    // f32 хранится как биты, чтобы обойтись атомиком без мьютекса
+    max_cpu_bits: AtomicU32,
+    // Когда основной цикл последний раз завершил проверку (для /healthz)
+    last_scan: Mutex<Option<Instant>>,
+}
+
+// Какие эндпоинты отдаёт сервер на своём адресе: METRICS_ADDR и HEALTH_ADDR могут совпадать
+#[derive(Clone, Copy, Default)]
+pub struct Endpoints {
+    pub metrics: bool,
+    // Проверка считается зависшей, если последняя завершилась раньше этого срока
+    pub health: Option<Duration>,
+}
+
+impl Metrics {
+    pub fn set_max_cpu(&self, cpu: f32) {
+        self.max_cpu_bits.store(cpu.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn record_scan(&self) {
+        if let Ok(mut last_scan) = self.last_scan.lock() {
+            *last_scan = Some(Instant::now());
+        }
+    }
+
+    // Время с последней завершённой проверки
+    fn since_last_scan(&self) -> Option<Duration> {
+        self.last_scan.lock().ok().and_then(|last_scan| last_scan.map(|at| at.elapsed()))
+    }
+
+    fn render(&self) -> String {
+        let mut out = String::new();
+        let counters = [
+            ("cpu_watcher_checks_total", "Completed process scans", &self.checks),
+            ("cpu_watcher_alerts_sent_total", "Alerts delivered successfully", &self.alerts_sent),
+            ("cpu_watcher_send_failures_total", "Alerts that failed to deliver", &self.send_failures),
+            ("cpu_watcher_alerts_suppressed_total", "Alerts dropped by the global rate limit", &self.alerts_suppressed),
+        ];
+        for (name, help, value) in counters {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} counter", name);
+            let _ = writeln!(out, "{} {}", name, value.load(Ordering::Relaxed));
+        }
+
+        let max_cpu = f32::from_bits(self.max_cpu_bits.load(Ordering::Relaxed));
+        let _ = writeln!(out, "# HELP cpu_watcher_max_process_cpu_percent Highest process CPU usage in the last scan");
+        let _ = writeln!(out, "# TYPE cpu_watcher_max_process_cpu_percent gauge");
+        let _ = writeln!(out, "cpu_watcher_max_process_cpu_percent {}", max_cpu);
+        out
+    }
+}
+
+// Минимальный HTTP-сервер: нам нужны только GET /metrics и GET /healthz
+pub async fn serve(listener: TcpListener, metrics: Arc<Metrics>, endpoints: Endpoints) {
+    if let Ok(addr) = listener.local_addr() {
+        if endpoints.metrics {
+            info!("Metrics endpoint listening on http://{}/metrics", addr);
+        }
+        if endpoints.health.is_some() {
+            info!("Health endpoint listening on http://{}/healthz", addr);
+        }
+    }
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                let metrics = metrics.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle(stream, &metrics, endpoints).await {
+                        warn!("Metrics request failed: {}", e);
+                    }
+                });
+            }
+            Err(e) => warn!("Metrics accept failed: {}", e),
+        }
+    }
+}
+
+async fn handle(mut stream: TcpStream, metrics: &Metrics, endpoints: Endpoints) -> std::io::Result<()> {
+    let mut buf = [0u8; 1024];
+    let n = stream.read(&mut buf).await?;
+    let request = String::from_utf8_lossy(&buf[..n]);
+    let path = request.split_whitespace().nth(1).unwrap_or("");
+
+    let (status, content_type, body) = match (path, endpoints.health) {
+        ("/metrics", _) if endpoints.metrics => ("200 OK", "text/plain; version=0.0.4", metrics.render()),
+        ("/healthz", Some(stale_after)) => match metrics.since_last_scan() {
+            Some(elapsed) if elapsed <= stale_after => ("200 OK", "text/plain", "ok\n".to_string()),
+            Some(elapsed) => (
+                "503 Service Unavailable",
+                "text/plain",
+                format!("stale: last scan finished {:.1}s ago\n", elapsed.as_secs_f64()),
+            ),
+            None => ("503 Service Unavailable", "text/plain", "no scan yet\n".to_string()),
+        },
+        _ => ("404 Not Found", "text/plain", "not found\n".to_string()),
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
@@ -0,0 +1,204 @@
+use crate::config::Config;
+use crate::source::ProcessView;
+use crate::NetRate;
+use std::collections::{HashMap, HashSet};
+use std::time::Instant;
+use sysinfo::{Networks, Pid, System};
+
+// Loopback не уходит с машины и в порог не считается
+fn is_loopback(interface: &str) -> bool {
+    interface == "lo" || interface == "lo0"
+}
+
+fn per_second(delta: u64, elapsed: f64) -> u64 {
+    (delta as f64 / elapsed) as u64
+}
+
+// Трафик сетевых пространств имён (только Linux). /proc/PID/net/dev показывает счётчики
+// всего пространства, а не процесса, поэтому трафик приписывается только процессу, который
+// живёт в отдельном пространстве, а его родитель — нет (обычно это init контейнера).
+// Пространство самого cpu_watcher (хост) не учитывается: там это трафик всей системы
+pub(crate) struct NamespaceTraffic {
+    host: Option<String>,
+    // Пространство имён по PID, только в пределах одной проверки
+    namespaces: HashMap<Pid, Option<String>>,
+    // Принятые и отправленные байты на прошлой проверке и её время
+    samples: HashMap<String, (u64, u64, Instant)>,
+    seen: HashSet<String>,
+}
+
+impl NamespaceTraffic {
+    pub(crate) fn new() -> Self {
+        NamespaceTraffic {
+            host: namespace_of("self"),
+            namespaces: HashMap::new(),
+            samples: HashMap::new(),
+            seen: HashSet::new(),
+        }
+    }
+
+    // Начало проверки: забываем пространства, которых не было на прошлой
+    pub(crate) fn begin(&mut self) {
+        let seen = std::mem::take(&mut self.seen);
+        self.samples.retain(|namespace, _| seen.contains(namespace));
+        self.namespaces.clear();
+    }
+
+    // Скорость трафика пространства, если процесс — его первый процесс. Первая проверка
+    // для пространства даёт только точку отсчёта
+    pub(crate) fn rate(
+        &mut self,
+        pid: Pid,
+        process: &dyn ProcessView,
+        parent: Option<(Pid, &dyn ProcessView)>,
+        now: Instant,
+    ) -> Option<NetRate> {
+        let namespace = self.namespace(pid, process)?;
+        if self.host.as_ref() == Some(&namespace) {
+            return None;
+        }
+        if let Some((ppid, parent)) = parent {
+            if self.namespace(ppid, parent).as_ref() == Some(&namespace) {
+                return None;
+            }
+        }
+        let (received, transmitted) = process.net_traffic()?;
+        self.seen.insert(namespace.clone());
+        let (last_received, last_transmitted, then) = self.samples.insert(namespace, (received, transmitted, now))?;
+        let elapsed = now.saturating_duration_since(then).as_secs_f64();
+        if elapsed <= 0.0 {
+            return None;
+        }
+        Some(NetRate {
+            rx_per_sec: per_second(received.saturating_sub(last_received), elapsed),
+            tx_per_sec: per_second(transmitted.saturating_sub(last_transmitted), elapsed),
+        })
+    }
+
+    fn namespace(&mut self, pid: Pid, process: &dyn ProcessView) -> Option<String> {
+        self.namespaces.entry(pid).or_insert_with(|| process.net_namespace()).clone()
+    }
+}
+
+// Ссылка вида net:[4026531840]; читается только для своих процессов или от root
+#[cfg(target_os = "linux")]
+pub(crate) fn namespace_of(pid: &str) -> Option<String> {
+    let link = std::fs::read_link(crate::procfs::process_file(pid, "ns/net")).ok()?;
+    Some(link.to_string_lossy().into_owned())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn namespace_of(_pid: &str) -> Option<String> {
+    None
+}
+
+// Сумма принятых и отправленных байт по всем интерфейсам пространства, кроме loopback
+#[cfg(target_os = "linux")]
+pub(crate) fn read_net_dev(pid: Pid) -> Option<(u64, u64)> {
+    let content = std::fs::read_to_string(crate::procfs::process_file(pid, "net/dev")).ok()?;
+    let mut totals = (0, 0);
+    // Две первые строки — заголовок таблицы
+    for line in content.lines().skip(2) {
+        let (interface, counters) = line.split_once(':')?;
+        if is_loopback(interface.trim()) {
+            continue;
+        }
+        let counters: Vec<u64> = counters.split_whitespace().filter_map(|value| value.parse().ok()).collect();
+        // Столбец 0 — принятые байты, столбец 8 — отправленные
+        totals.0 += counters.first()?;
+        totals.1 += counters.get(8)?;
+    }
+    Some(totals)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn read_net_dev(_pid: Pid) -> Option<(u64, u64)> {
+    None
+}
+
+/// Трафик всей системы выше `net_threshold` и процесс с наибольшим CPU как первый подозреваемый.
+pub struct SystemNetAlert {
+    pub rate: NetRate,
+    /// PID, имя и CPU процесса, который сейчас больше всех нагружает процессор.
+    pub top_process: Option<(Pid, String, f32)>,
+}
+
+/// Общий трафик системы по интерфейсам sysinfo — запасной вариант для трафика, который нельзя
+/// приписать отдельному процессу (хост и не-Linux). Кулдаун свой, длиной `cooldown_seconds`.
+pub struct SystemNetwork {
+    networks: Networks,
+    threshold: u64,
+    cooldown_seconds: u64,
+    // Счётчики интерфейсов на прошлой проверке: новые интерфейсы не дают скачка
+    totals: HashMap<String, (u64, u64)>,
+    refreshed_at: Instant,
+    alerted_at: Option<Instant>,
+}
+
+impl SystemNetwork {
+    /// None, если `net_threshold` не задан.
+    pub fn new(config: &Config) -> Option<SystemNetwork> {
+        let threshold = config.net_threshold?;
+        let mut network = SystemNetwork {
+            networks: Networks::new_with_refreshed_list(),
+            threshold,
+            cooldown_seconds: config.cooldown_seconds,
+            totals: HashMap::new(),
+            refreshed_at: Instant::now(),
+            alerted_at: None,
+        };
+        network.totals = network.read_totals();
+        Some(network)
+    }
+
+    fn read_totals(&self) -> HashMap<String, (u64, u64)> {
+        self.networks
+            .iter()
+            .filter(|(interface, _)| !is_loopback(interface))
+            .map(|(interface, data)| (interface.clone(), (data.total_received(), data.total_transmitted())))
+            .collect()
+    }
+
+    /// Оповещение, если трафик с прошлой проверки выше порога и кулдаун истёк.
+    pub fn check(&mut self, sys: &System, now: Instant) -> Option<SystemNetAlert> {
+        self.networks.refresh_list();
+        let totals = self.read_totals();
+        let (mut received, mut transmitted) = (0, 0);
+        for (interface, (rx, tx)) in &totals {
+            if let Some((last_rx, last_tx)) = self.totals.get(interface) {
+                received += rx.saturating_sub(*last_rx);
+                transmitted += tx.saturating_sub(*last_tx);
+            }
+        }
+        self.totals = totals;
+        let elapsed = now.saturating_duration_since(self.refreshed_at).as_secs_f64();
+        self.refreshed_at = now;
+        if elapsed <= 0.0 {
+            return None;
+        }
+        let rate = NetRate {
+            rx_per_sec: per_second(received, elapsed),
+            tx_per_sec: per_second(transmitted, elapsed),
+        };
+        if rate.rx_per_sec + rate.tx_per_sec < self.threshold {
+            return None;
+        }
+        if self
+            .alerted_at
+            .is_some_and(|last| now.saturating_duration_since(last).as_secs() < self.cooldown_seconds)
+        {
+            return None;
+        }
+        let top_process = sys
+            .processes()
+            .iter()
+            .max_by(|a, b| a.1.cpu_usage().total_cmp(&b.1.cpu_usage()))
+            .map(|(pid, process)| (*pid, process.name().to_string(), process.cpu_usage()));
+        Some(SystemNetAlert { rate, top_process })
+    }
+
+    /// Запоминает отправленное оповещение: до конца кулдауна оно не повторится.
+    pub fn mark_alerted(&mut self, now: Instant) {
+        self.alerted_at = Some(now);
+    }
+}
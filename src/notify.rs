@@ -0,0 +1,1320 @@
+use crate::error::WatcherError;
+use crate::markup::ParseMode;
+use crate::{Alert, AlertKind, Severity};
+use async_trait::async_trait;
+use chrono::Utc;
+use log::{error, info, warn};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
+use sysinfo::Pid;
+
+pub const DEFAULT_TELEGRAM_API_BASE: &str = "https://api.telegram.org";
+
+/// Таймаут одной отправки по умолчанию (SEND_TIMEOUT_SECONDS).
+pub const DEFAULT_SEND_TIMEOUT: Duration = Duration::from_secs(10);
+
+const USER_AGENT: &str = concat!("cpu_watcher/", env!("CARGO_PKG_VERSION"));
+
+// Переменные, из которых reqwest сам берёт прокси
+const PROXY_ENV_VARS: [&str; 6] = ["HTTPS_PROXY", "https_proxy", "HTTP_PROXY", "http_proxy", "ALL_PROXY", "all_proxy"];
+
+pub type SendResult = Result<bool, WatcherError>;
+
+// Канал доставки уведомлений. Ok(false) — сервис окончательно отказал (например, 4xx из-за
+// неверного токена), Err — временный сбой (сеть, 5xx, 429), который имеет смысл повторить
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    async fn send(&self, text: &str) -> SendResult;
+
+    // Оповещение о процессах. Каналы, которым нужны структурированные поля, а не готовый
+    // текст (JSON-вебхук), переопределяют этот метод; остальные отправляют text
+    async fn send_alerts(&self, _alerts: &[Alert], text: &str) -> SendResult {
+        self.send(text).await
+    }
+
+    // Начало новой отправки, а не повтор прошлой (см. RetryingNotifier): каналы, которые помнят,
+    // сколько частей длинного текста уже дошло, здесь это забывают
+    fn begin(&self) {}
+}
+
+fn is_retryable(status: reqwest::StatusCode) -> bool {
+    status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+}
+
+#[derive(Serialize)]
+struct TelegramMessage {
+    chat_id: String,
+    text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parse_mode: Option<&'static str>,
+    #[serde(flatten)]
+    extras: Extras,
+}
+
+// Необязательные поля sendMessage: кнопки под сообщением и ответ на другое сообщение
+#[derive(Clone, Default, Serialize)]
+struct Extras {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reply_markup: Option<InlineKeyboard>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reply_to_message_id: Option<i64>,
+}
+
+#[derive(Clone, Serialize)]
+struct InlineKeyboard {
+    inline_keyboard: Vec<Vec<InlineButton>>,
+}
+
+#[derive(Clone, Serialize)]
+struct InlineButton {
+    text: String,
+    callback_data: String,
+}
+
+// Больше строк кнопок под сводкой не рисуем: остальные процессы — только в тексте
+const MAX_BUTTON_ROWS: usize = 10;
+
+/// Кнопка под оповещением в Telegram (TELEGRAM_BUTTONS). В callback_data кроме PID лежит
+/// время старта процесса: кнопка не сработает по PID, уже выданному другому процессу.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Button {
+    Kill,
+    Renice,
+    Mute,
+}
+
+impl Button {
+    const ALL: [Button; 3] = [Button::Kill, Button::Renice, Button::Mute];
+
+    fn prefix(self) -> &'static str {
+        match self {
+            Button::Kill => "kill",
+            Button::Renice => "renice",
+            Button::Mute => "mute",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Button::Kill => "🛑 Kill",
+            Button::Renice => "🐢 Renice",
+            Button::Mute => "🔕 Mute",
+        }
+    }
+
+    /// callback_data кнопки, например `kill:1234:1700000000`.
+    pub fn callback_data(self, pid: Pid, start_time: u64) -> String {
+        format!("{}:{}:{}", self.prefix(), pid, start_time)
+    }
+
+    /// Кнопка, PID и время старта из callback_data; None — данные не от cpu_watcher.
+    pub fn parse(data: &str) -> Option<(Button, Pid, u64)> {
+        let mut parts = data.split(':');
+        let prefix = parts.next()?;
+        let button = Button::ALL.into_iter().find(|button| button.prefix() == prefix)?;
+        let pid = parts.next()?.parse::<u32>().ok()?;
+        let start_time = parts.next()?.parse().ok()?;
+        if parts.next().is_some() {
+            return None;
+        }
+        Some((button, Pid::from_u32(pid), start_time))
+    }
+}
+
+// По строке кнопок на процесс; время старта процесса без него неизвестно — тогда 0.
+// Kill и Renice — только у процессов, чьё правило разрешает действия, Mute — у всех
+fn keyboard(alerts: &[Alert]) -> Option<InlineKeyboard> {
+    let rows: Vec<Vec<InlineButton>> = alerts
+        .iter()
+        .take(MAX_BUTTON_ROWS)
+        .map(|alert| {
+            let pid = alert.process.pid;
+            let start_time = alert.process.create_time.map_or(0, |time| time.timestamp().max(0) as u64);
+            Button::ALL
+                .into_iter()
+                .filter(|button| alert.process.actionable || *button == Button::Mute)
+                .map(|button| InlineButton {
+                    text: format!("{} {}", button.label(), pid),
+                    callback_data: button.callback_data(pid, start_time),
+                })
+                .collect()
+        })
+        .collect();
+    (!rows.is_empty()).then_some(InlineKeyboard { inline_keyboard: rows })
+}
+
+#[derive(Deserialize)]
+struct TelegramResponse {
+    ok: bool,
+    #[serde(default)]
+    description: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GetMeResponse {
+    ok: bool,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    result: Option<TelegramUser>,
+}
+
+/// Пользователь или бот Telegram.
+#[derive(Debug, Deserialize)]
+pub struct TelegramUser {
+    pub id: i64,
+    #[serde(default)]
+    pub username: Option<String>,
+}
+
+impl fmt::Display for TelegramUser {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.username {
+            Some(username) => write!(f, "@{}", username),
+            None => write!(f, "user {}", self.id),
+        }
+    }
+}
+
+/// Чат, из которого пришло сообщение или нажатие.
+#[derive(Debug, Deserialize)]
+pub struct Chat {
+    pub id: i64,
+    #[serde(default)]
+    pub username: Option<String>,
+}
+
+impl Chat {
+    /// Совпадает ли чат с записью из TELEGRAM_CHAT_ID: числовой ID или @username.
+    pub fn matches(&self, chat_id: &str) -> bool {
+        let chat_id = chat_id.trim();
+        match chat_id.strip_prefix('@') {
+            Some(username) => self.username.as_deref() == Some(username),
+            None => chat_id == self.id.to_string(),
+        }
+    }
+}
+
+/// Входящее сообщение: команда в чате или сообщение, под которым нажали кнопку.
+#[derive(Debug, Deserialize)]
+pub struct IncomingMessage {
+    pub message_id: i64,
+    pub chat: Chat,
+    #[serde(default)]
+    pub from: Option<TelegramUser>,
+    #[serde(default)]
+    pub text: Option<String>,
+}
+
+/// Нажатие кнопки под оповещением.
+#[derive(Debug, Deserialize)]
+pub struct CallbackQuery {
+    pub id: String,
+    pub from: TelegramUser,
+    #[serde(default)]
+    pub message: Option<IncomingMessage>,
+    #[serde(default)]
+    pub data: Option<String>,
+}
+
+/// Обновление из getUpdates.
+#[derive(Debug, Deserialize)]
+pub struct Update {
+    pub update_id: i64,
+    #[serde(default)]
+    pub callback_query: Option<CallbackQuery>,
+    #[serde(default)]
+    pub message: Option<IncomingMessage>,
+}
+
+#[derive(Deserialize)]
+struct UpdatesResponse {
+    ok: bool,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    result: Vec<Update>,
+}
+
+// Запас к таймауту длинного опроса: Telegram держит запрос до timeout и отвечает пустым списком
+const LONG_POLL_MARGIN: Duration = Duration::from_secs(10);
+
+// Тело ответа Bot API; HTML от прокси или обрезанный ответ — сбой Telegram, а не отказ
+fn parse_response<T: serde::de::DeserializeOwned>(body: &str) -> Result<T, WatcherError> {
+    serde_json::from_str(body).map_err(|e| WatcherError::TelegramApi(format!("invalid response: {}", e)))
+}
+
+/// Длинный опрос getUpdates: обновления типов `allowed` (`message`, `callback_query`) после
+/// `offset`, ожидание до `wait`. Err — сбой сети или отказ Telegram (например, 409, если
+/// у бота настроен webhook).
+pub async fn telegram_get_updates(
+    client: &reqwest::Client,
+    api_base: &str,
+    bot_token: &str,
+    offset: i64,
+    wait: Duration,
+    allowed: &[&str],
+) -> Result<Vec<Update>, WatcherError> {
+    let url = format!("{}/bot{}/getUpdates", api_base, bot_token);
+    let request = serde_json::json!({
+        "offset": offset,
+        "timeout": wait.as_secs(),
+        "allowed_updates": allowed,
+    });
+    let response = client
+        .post(&url)
+        .json(&request)
+        .timeout(wait + LONG_POLL_MARGIN)
+        .send()
+        .await?;
+    let body = response.text().await?;
+    let response: UpdatesResponse = parse_response(&body)?;
+    if response.ok {
+        Ok(response.result)
+    } else {
+        Err(WatcherError::TelegramApi(format!("getUpdates failed: {}", response.description.unwrap_or_default())))
+    }
+}
+
+/// Ответ на нажатие кнопки: убирает индикатор загрузки и показывает короткий `text`.
+pub async fn telegram_answer_callback(
+    client: &reqwest::Client,
+    api_base: &str,
+    bot_token: &str,
+    callback_id: &str,
+    text: &str,
+) -> SendResult {
+    let url = format!("{}/bot{}/answerCallbackQuery", api_base, bot_token);
+    let request = serde_json::json!({ "callback_query_id": callback_id, "text": text });
+    let response = client.post(&url).json(&request).send().await?;
+    let body = response.text().await?;
+    let response: TelegramResponse = parse_response(&body)?;
+    Ok(response.ok)
+}
+
+/// Ответ обычным текстом на сообщение `reply_to` в чате `chat_id`.
+pub async fn telegram_reply(
+    client: &reqwest::Client,
+    api_base: &str,
+    bot_token: &str,
+    chat_id: &str,
+    reply_to: i64,
+    text: &str,
+) -> SendResult {
+    let extras = Extras {
+        reply_to_message_id: Some(reply_to),
+        ..Extras::default()
+    };
+    send_telegram_with(client, api_base, bot_token, chat_id, None, text, extras).await
+}
+
+/// Ответ Telegram на проверку токена: имя бота или причина отказа.
+pub enum TokenCheck {
+    Valid(String),
+    Rejected(String),
+}
+
+/// Проверяет токен через getMe. Err — временный сбой (сеть, 5xx, 429), по которому о токене
+/// ничего не известно.
+pub async fn telegram_get_me(
+    client: &reqwest::Client,
+    api_base: &str,
+    bot_token: &str,
+) -> Result<TokenCheck, WatcherError> {
+    let url = format!("{}/bot{}/getMe", api_base, bot_token);
+    // URL содержит токен — в текст ошибки он попасть не должен
+    let response = client
+        .get(&url)
+        .send()
+        .await?;
+    let status = response.status();
+    if is_retryable(status) {
+        return Err(WatcherError::Server { service: "Telegram", status });
+    }
+    let body = response.text().await?;
+    let response: GetMeResponse = parse_response(&body)?;
+    if response.ok {
+        let username = response.result.and_then(|user| user.username).unwrap_or_default();
+        Ok(TokenCheck::Valid(username))
+    } else {
+        Ok(TokenCheck::Rejected(response.description.unwrap_or(status.to_string())))
+    }
+}
+
+// Лимит Telegram на длину одного сообщения
+const TELEGRAM_MAX_CHARS: usize = 4096;
+
+// Лимит Discord на поле content
+const DISCORD_MAX_CHARS: usize = 2000;
+
+// Место в конце куска под закрывающую разметку (</code></b> и т.п.)
+const MARKUP_RESERVE: usize = 16;
+
+// Режет текст по границам строк на куски не длиннее limit символов; слишком длинная строка
+// режется внутри (см. split_line)
+fn split_message(text: &str, limit: usize, parse_mode: Option<ParseMode>) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut current_len = 0;
+
+    for line in text.split('\n') {
+        let mut pieces = split_line(line, limit, parse_mode);
+        let line = pieces.pop().unwrap_or_default();
+        if !pieces.is_empty() {
+            if !current.is_empty() {
+                chunks.push(std::mem::take(&mut current));
+                current_len = 0;
+            }
+            chunks.extend(pieces);
+        }
+
+        let line_len = line.chars().count();
+        let separator = usize::from(!current.is_empty());
+        if current_len + separator + line_len > limit {
+            chunks.push(std::mem::take(&mut current));
+            current_len = 0;
+        }
+        if !current.is_empty() {
+            current.push('\n');
+            current_len += 1;
+        }
+        current.push_str(&line);
+        current_len += line_len;
+    }
+    if !current.is_empty() || chunks.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+// Куски одной строки не длиннее limit символов. В режиме разметки разрез не попадает внутрь
+// экранирования или HTML-сущности, а открытые на разрезе `...`, *...* и теги закрываются в
+// конце куска и открываются заново в начале следующего — иначе Telegram ответит 400
+fn split_line(line: &str, limit: usize, parse_mode: Option<ParseMode>) -> Vec<String> {
+    if line.chars().count() <= limit {
+        return vec![line.to_string()];
+    }
+    let budget = if parse_mode.is_some() { limit.saturating_sub(MARKUP_RESERVE).max(1) } else { limit };
+    let mut pieces = Vec::new();
+    let mut current = String::new();
+    let mut current_len = 0;
+    // Открытая разметка: открывающая и закрывающая части
+    let mut open: Vec<(String, String)> = Vec::new();
+    for token in markup_tokens(line, parse_mode) {
+        let token_len = token.chars().count();
+        if current_len + token_len > budget && !current.is_empty() {
+            current.extend(open.iter().rev().map(|(_, close)| close.as_str()));
+            pieces.push(std::mem::take(&mut current));
+            current.extend(open.iter().map(|(start, _)| start.as_str()));
+            current_len = current.chars().count();
+        }
+        current.push_str(token);
+        current_len += token_len;
+        track_markup(&mut open, token, parse_mode);
+    }
+    pieces.push(current);
+    pieces
+}
+
+// Строка по неделимым частям: экранированный символ MarkdownV2, HTML-тег или сущность,
+// остальное — по символу
+fn markup_tokens(line: &str, parse_mode: Option<ParseMode>) -> Vec<&str> {
+    // Тег или сущность длиннее этого — не разметка, а обычный текст
+    const MAX_TAG: usize = 16;
+    let mut tokens = Vec::new();
+    let mut rest = line;
+    while let Some(c) = rest.chars().next() {
+        let closing = |end: char| rest.find(end).filter(|&i| i < MAX_TAG).map(|i| i + 1);
+        let end = match (parse_mode, c) {
+            (Some(ParseMode::MarkdownV2), '\\') => rest.char_indices().nth(2).map_or(rest.len(), |(i, _)| i),
+            (Some(ParseMode::Html), '<') => closing('>').unwrap_or(1),
+            (Some(ParseMode::Html), '&') => closing(';').unwrap_or(1),
+            _ => c.len_utf8(),
+        };
+        tokens.push(&rest[..end]);
+        rest = &rest[end..];
+    }
+    tokens
+}
+
+fn track_markup(open: &mut Vec<(String, String)>, token: &str, parse_mode: Option<ParseMode>) {
+    match parse_mode {
+        // Внутри `...` звёздочка — просто символ
+        Some(ParseMode::MarkdownV2)
+            if token == "`" || (token == "*" && !open.iter().any(|(start, _)| start == "`")) =>
+        {
+            if open.last().is_some_and(|(start, _)| start == token) {
+                open.pop();
+            } else {
+                open.push((token.to_string(), token.to_string()));
+            }
+        }
+        Some(ParseMode::Html) if token.starts_with("</") => {
+            open.pop();
+        }
+        Some(ParseMode::Html) if token.len() > 2 && token.starts_with('<') && token.ends_with('>') => {
+            let name = token[1..token.len() - 1].split_whitespace().next().unwrap_or_default();
+            open.push((token.to_string(), format!("</{}>", name)));
+        }
+        _ => {}
+    }
+}
+
+/// Сколько частей длинного текста уже дошло. RetryingNotifier повторяет отправку целиком, и
+/// после сбоя на середине повтор продолжает с первой неотправленной части, а не шлёт начало
+/// заново. Новая отправка (Notifier::begin) или другой текст начинают с первой части.
+#[derive(Default)]
+pub struct SentChunks(Mutex<Option<(String, usize)>>);
+
+impl SentChunks {
+    fn reset(&self) {
+        *self.0.lock().unwrap() = None;
+    }
+
+    async fn send<F, Fut>(&self, text: &str, chunks: Vec<String>, mut send_chunk: F) -> SendResult
+    where
+        F: FnMut(usize, String) -> Fut,
+        Fut: Future<Output = SendResult>,
+    {
+        let first = match &*self.0.lock().unwrap() {
+            Some((sent_text, sent)) if sent_text == text => *sent,
+            _ => 0,
+        };
+        for (index, chunk) in chunks.into_iter().enumerate().skip(first) {
+            match send_chunk(index, chunk).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    self.reset();
+                    return Ok(false);
+                }
+                Err(e) => {
+                    *self.0.lock().unwrap() = Some((text.to_string(), index));
+                    return Err(e);
+                }
+            }
+        }
+        self.reset();
+        Ok(true)
+    }
+}
+
+// Прокси принимается только http(s): без схемы или с опечаткой reqwest молча обошёлся бы без него
+fn check_proxy_url(source: &str, raw: &str) -> Result<reqwest::Url, String> {
+    let url = reqwest::Url::parse(raw).map_err(|e| format!("invalid proxy URL in {} ({:?}): {}", source, raw, e))?;
+    if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+        return Err(format!(
+            "invalid proxy URL in {} ({:?}): expected http://host:port or https://host:port",
+            source, raw
+        ));
+    }
+    Ok(url)
+}
+
+/// Настройки HTTP-клиента; None — значение reqwest по умолчанию.
+#[derive(Clone, Debug, Default)]
+pub struct HttpOptions {
+    /// Явный прокси (TELEGRAM_PROXY), важнее HTTPS_PROXY/HTTP_PROXY.
+    pub proxy: Option<String>,
+    /// Сколько простаивающих соединений держать на хост; 0 — не переиспользовать соединения.
+    pub pool_max_idle: Option<usize>,
+    pub pool_idle_timeout: Option<Duration>,
+    pub connect_timeout: Option<Duration>,
+    /// Таймаут всего запроса, от соединения до ответа; по умолчанию [`DEFAULT_SEND_TIMEOUT`].
+    pub timeout: Option<Duration>,
+    pub ip_family: IpFamily,
+}
+
+/// Семейство адресов для исходящих соединений: при нестабильном IPv6 можно оставить только IPv4.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IpFamily {
+    #[default]
+    Any,
+    Ipv4,
+    Ipv6,
+}
+
+impl IpFamily {
+    // Привязка к «любому» адресу нужного семейства: адреса другого семейства hyper не пробует
+    fn local_address(self) -> Option<std::net::IpAddr> {
+        match self {
+            IpFamily::Any => None,
+            IpFamily::Ipv4 => Some(std::net::Ipv4Addr::UNSPECIFIED.into()),
+            IpFamily::Ipv6 => Some(std::net::Ipv6Addr::UNSPECIFIED.into()),
+        }
+    }
+}
+
+impl FromStr for IpFamily {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, ()> {
+        match value.to_lowercase().as_str() {
+            "any" => Ok(IpFamily::Any),
+            "ipv4" | "4" => Ok(IpFamily::Ipv4),
+            "ipv6" | "6" => Ok(IpFamily::Ipv6),
+            _ => Err(()),
+        }
+    }
+}
+
+// HTTP-клиент для всех каналов: один на процесс, его клоны делят пул соединений.
+// Прокси из HTTPS_PROXY/HTTP_PROXY reqwest подхватывает сам
+pub fn build_client(options: &HttpOptions) -> Result<reqwest::Client, String> {
+    for var in PROXY_ENV_VARS {
+        if let Some(raw) = std::env::var(var).ok().filter(|raw| !raw.trim().is_empty()) {
+            check_proxy_url(var, raw.trim())?;
+        }
+    }
+    let mut builder = reqwest::Client::builder().user_agent(USER_AGENT);
+    if let Some(raw) = &options.proxy {
+        let url = check_proxy_url("TELEGRAM_PROXY", raw.trim())?;
+        let proxy = reqwest::Proxy::all(url).map_err(|e| format!("invalid proxy URL in TELEGRAM_PROXY: {}", e))?;
+        builder = builder.proxy(proxy);
+    }
+    if let Some(max_idle) = options.pool_max_idle {
+        builder = builder.pool_max_idle_per_host(max_idle);
+    }
+    if let Some(timeout) = options.pool_idle_timeout {
+        builder = builder.pool_idle_timeout(timeout);
+    }
+    if let Some(timeout) = options.connect_timeout {
+        builder = builder.connect_timeout(timeout);
+    }
+    builder = builder
+        .timeout(options.timeout.unwrap_or(DEFAULT_SEND_TIMEOUT))
+        .local_address(options.ip_family.local_address());
+    builder.build().map_err(|e| format!("cannot build HTTP client: {}", e))
+}
+
+// Адрес Bot API без завершающего "/"; проверяется при запуске, чтобы опечатка не всплыла
+// только при первом оповещении
+pub fn telegram_api_base(raw: &str) -> Result<String, String> {
+    let base = raw.trim().trim_end_matches('/');
+    let url = reqwest::Url::parse(base).map_err(|e| format!("invalid TELEGRAM_API_BASE {:?}: {}", raw, e))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(format!("invalid TELEGRAM_API_BASE {:?}: expected an http(s) URL", raw));
+    }
+    Ok(base.to_string())
+}
+
+// Длинный текст уходит несколькими сообщениями; успех — только если дошли все части
+pub async fn send_telegram(
+    client: &reqwest::Client,
+    api_base: &str,
+    bot_token: &str,
+    chat_id: &str,
+    parse_mode: Option<ParseMode>,
+    text: &str,
+) -> SendResult {
+    send_telegram_with(client, api_base, bot_token, chat_id, parse_mode, text, Extras::default()).await
+}
+
+// Кнопки и ответ прикрепляются к последней части длинного текста
+async fn send_telegram_with(
+    client: &reqwest::Client,
+    api_base: &str,
+    bot_token: &str,
+    chat_id: &str,
+    parse_mode: Option<ParseMode>,
+    text: &str,
+    extras: Extras,
+) -> SendResult {
+    let chunks = split_message(text, TELEGRAM_MAX_CHARS, parse_mode);
+    let last = chunks.len() - 1;
+    for (index, chunk) in chunks.into_iter().enumerate() {
+        let extras = if index == last { extras.clone() } else { Extras::default() };
+        if !send_telegram_chunk(client, api_base, bot_token, chat_id, parse_mode, &chunk, extras).await? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+async fn send_telegram_chunk(
+    client: &reqwest::Client,
+    api_base: &str,
+    bot_token: &str,
+    chat_id: &str,
+    parse_mode: Option<ParseMode>,
+    text: &str,
+    extras: Extras,
+) -> SendResult {
+    let url = format!("{}/bot{}/sendMessage", api_base, bot_token);
+    let message = TelegramMessage {
+        chat_id: chat_id.to_string(),
+        text: text.to_string(),
+        parse_mode: parse_mode.map(|mode| mode.as_str()),
+        extras,
+    };
+
+    let response = client
+        .post(&url)
+        .json(&message)
+        .send()
+        .await?;
+
+    let status = response.status();
+    if is_retryable(status) {
+        return Err(WatcherError::Server { service: "Telegram", status });
+    }
+
+    let response_text = response.text().await?;
+    let telegram_response: TelegramResponse = parse_response(&response_text)?;
+
+    if telegram_response.ok {
+        info!(event = "send_ok", backend = "telegram"; "Telegram sent: {}", text);
+        Ok(true)
+    } else {
+        let description = telegram_response.description.unwrap_or("Unknown error".to_string());
+        error!(event = "send_failed", backend = "telegram", error = description.as_str(); "Telegram error: {}", description);
+        Ok(false)
+    }
+}
+
+pub struct TelegramNotifier {
+    pub client: reqwest::Client,
+    pub api_base: String,
+    pub bot_token: String,
+    pub chat_id: String,
+    pub parse_mode: Option<ParseMode>,
+    /// Кнопки Kill/Renice/Mute под оповещениями (TELEGRAM_BUTTONS).
+    pub buttons: bool,
+    pub sent: SentChunks,
+}
+
+impl TelegramNotifier {
+    // Кнопки и ответ прикрепляются к последней части длинного текста
+    async fn send_with(&self, text: &str, extras: Extras) -> SendResult {
+        let chunks = split_message(text, TELEGRAM_MAX_CHARS, self.parse_mode);
+        let last = chunks.len() - 1;
+        self.sent
+            .send(text, chunks, |index, chunk| {
+                let extras = if index == last { extras.clone() } else { Extras::default() };
+                async move {
+                    send_telegram_chunk(&self.client, &self.api_base, &self.bot_token, &self.chat_id, self.parse_mode, &chunk, extras)
+                        .await
+                }
+            })
+            .await
+    }
+}
+
+#[async_trait]
+impl Notifier for TelegramNotifier {
+    async fn send(&self, text: &str) -> SendResult {
+        self.send_with(text, Extras::default()).await
+    }
+
+    async fn send_alerts(&self, alerts: &[Alert], text: &str) -> SendResult {
+        let extras = Extras {
+            reply_markup: if self.buttons { keyboard(alerts) } else { None },
+            ..Extras::default()
+        };
+        self.send_with(text, extras).await
+    }
+
+    fn begin(&self) {
+        self.sent.reset();
+    }
+}
+
+#[derive(Serialize)]
+struct DiscordMessage<'a> {
+    content: &'a str,
+}
+
+pub struct DiscordNotifier {
+    pub client: reqwest::Client,
+    pub webhook_url: String,
+    pub sent: SentChunks,
+}
+
+// Длинный текст уходит несколькими сообщениями; успех — только если дошли все части
+#[async_trait]
+impl Notifier for DiscordNotifier {
+    async fn send(&self, text: &str) -> SendResult {
+        let chunks = split_message(text, DISCORD_MAX_CHARS, None);
+        self.sent.send(text, chunks, |_, chunk| async move { self.send_chunk(&chunk).await }).await
+    }
+
+    fn begin(&self) {
+        self.sent.reset();
+    }
+}
+
+impl DiscordNotifier {
+    async fn send_chunk(&self, text: &str) -> SendResult {
+        let response = self
+            .client
+            .post(&self.webhook_url)
+            .json(&DiscordMessage { content: text })
+            .send()
+            .await?;
+
+        // Discord отвечает 204 No Content на успешную отправку
+        let status = response.status();
+        if is_retryable(status) {
+            return Err(WatcherError::Server { service: "Discord", status });
+        }
+        if status.is_success() {
+            info!(event = "send_ok", backend = "discord"; "Discord sent: {}", text);
+            Ok(true)
+        } else {
+            let body = response.text().await.unwrap_or_default();
+            error!(event = "send_failed", backend = "discord", status = status.as_u16(); "Discord error: {} {}", status, body);
+            Ok(false)
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct SlackMessage<'a> {
+    text: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    channel: Option<&'a str>,
+}
+
+pub struct SlackNotifier {
+    pub client: reqwest::Client,
+    pub webhook_url: String,
+    pub channel: Option<String>,
+}
+
+// В тексте Slack служебные только &, < и >; остальное выводится как есть
+fn slack_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+#[async_trait]
+impl Notifier for SlackNotifier {
+    async fn send(&self, text: &str) -> SendResult {
+        let escaped = slack_escape(text);
+        let response = self
+            .client
+            .post(&self.webhook_url)
+            .json(&SlackMessage {
+                text: &escaped,
+                channel: self.channel.as_deref(),
+            })
+            .send()
+            .await?;
+
+        // Slack отвечает 200 с телом "ok"; ошибки приходят 4xx с кодом в теле (invalid_payload и т.п.)
+        let status = response.status();
+        if is_retryable(status) {
+            return Err(WatcherError::Server { service: "Slack", status });
+        }
+        let body = response.text().await.unwrap_or_default();
+        if status.is_success() && body.trim() == "ok" {
+            info!(event = "send_ok", backend = "slack"; "Slack sent: {}", text);
+            Ok(true)
+        } else {
+            error!(event = "send_failed", backend = "slack", status = status.as_u16(); "Slack error: {} {}", status, body);
+            Ok(false)
+        }
+    }
+}
+
+// OUTPUT=stdout-json: вместо отправки — JSON Lines в stdout для jq и сборщиков логов. Логи
+// идут в stderr, поэтому stdout остаётся чистым; каждая пачка сбрасывается сразу
+pub struct StdoutJsonNotifier;
+
+#[async_trait]
+impl Notifier for StdoutJsonNotifier {
+    async fn send(&self, text: &str) -> SendResult {
+        self.send_alerts(&[], text).await
+    }
+
+    async fn send_alerts(&self, alerts: &[Alert], text: &str) -> SendResult {
+        use std::io::Write;
+
+        let buffer = json_lines(alerts, text)?;
+        let mut stdout = std::io::stdout().lock();
+        stdout
+            .write_all(&buffer)
+            .and_then(|()| stdout.flush())
+            .map_err(|e| WatcherError::io("write to stdout failed", e))?;
+        Ok(true)
+    }
+}
+
+// DRY_RUN: вместо отправки пишет сообщение в лог и считает его доставленным,
+// чтобы кулдауны работали как в бою
+pub struct DryRunNotifier;
+
+#[async_trait]
+impl Notifier for DryRunNotifier {
+    async fn send(&self, text: &str) -> SendResult {
+        info!(event = "send_ok", backend = "dry-run"; "[dry-run] would send:\n{}", text);
+        Ok(true)
+    }
+}
+
+#[derive(Serialize)]
+struct WebhookAlert<'a> {
+    name: &'a str,
+    pid: u32,
+    cpu: f32,
+    threshold: f32,
+    severity: Severity,
+    memory_bytes: u64,
+    cpu_time_seconds: Option<f64>,
+    triggers: &'a [AlertKind],
+    rule: Option<&'a str>,
+    action: Option<&'a str>,
+    cmdline: &'a str,
+    exe_path: Option<&'a str>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    env: &'a [String],
+    started: Option<String>,
+    container: Option<&'a str>,
+}
+
+impl<'a> WebhookAlert<'a> {
+    fn new(alert: &'a Alert) -> Self {
+        WebhookAlert {
+            name: &alert.process.name,
+            pid: alert.process.pid.as_u32(),
+            cpu: alert.process.cpu_percent,
+            threshold: alert.process.cpu_threshold,
+            severity: alert.process.severity,
+            memory_bytes: alert.process.memory_bytes,
+            cpu_time_seconds: alert.process.cpu_time.map(|time| time.as_secs_f64()),
+            triggers: &alert.triggers,
+            rule: alert.process.rule.as_deref(),
+            action: alert.process.action.as_deref(),
+            cmdline: &alert.process.cmdline,
+            exe_path: alert.process.exe_path.as_deref(),
+            env: &alert.process.matched_env,
+            started: alert.process.create_time.map(|t| t.to_rfc3339()),
+            container: alert.process.container.as_deref(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct WebhookPayload<'a> {
+    timestamp: String,
+    text: &'a str,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    alerts: Vec<WebhookAlert<'a>>,
+}
+
+// Разбирает WEBHOOK_HEADERS: "Name: value" через точку с запятой
+pub fn parse_headers(raw: &str) -> Result<HeaderMap, String> {
+    let mut headers = HeaderMap::new();
+    for pair in raw.split(';').map(str::trim).filter(|pair| !pair.is_empty()) {
+        let (name, value) = pair
+            .split_once(':')
+            .ok_or_else(|| format!("invalid webhook header {:?}, expected \"Name: value\"", pair))?;
+        let name = HeaderName::from_str(name.trim()).map_err(|e| format!("invalid webhook header name {:?}: {}", name, e))?;
+        let value = HeaderValue::from_str(value.trim()).map_err(|e| format!("invalid webhook header value for {}: {}", name, e))?;
+        headers.insert(name, value);
+    }
+    Ok(headers)
+}
+
+// Произвольный HTTP-приёмник: POST JSON с полями процессов, оформление — на стороне получателя
+pub struct WebhookNotifier {
+    pub client: reqwest::Client,
+    pub url: String,
+    pub headers: HeaderMap,
+}
+
+impl WebhookNotifier {
+    async fn post(&self, payload: &WebhookPayload<'_>) -> SendResult {
+        let response = self
+            .client
+            .post(&self.url)
+            .headers(self.headers.clone())
+            .json(payload)
+            .send()
+            .await?;
+
+        let status = response.status();
+        if is_retryable(status) {
+            return Err(WatcherError::Server { service: "Webhook", status });
+        }
+        if status.is_success() {
+            info!(event = "send_ok", backend = "webhook"; "Webhook sent: {}", payload.text);
+            Ok(true)
+        } else {
+            let body = response.text().await.unwrap_or_default();
+            error!(event = "send_failed", backend = "webhook", status = status.as_u16(); "Webhook error: {} {}", status, body);
+            Ok(false)
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for WebhookNotifier {
+    async fn send(&self, text: &str) -> SendResult {
+        self.send_alerts(&[], text).await
+    }
+
+    async fn send_alerts(&self, alerts: &[Alert], text: &str) -> SendResult {
+        let alerts = alerts.iter().map(WebhookAlert::new).collect();
+        self.post(&WebhookPayload {
+            timestamp: Utc::now().to_rfc3339(),
+            text,
+            alerts,
+        })
+        .await
+    }
+}
+
+// Строка для локального сокета и stdout: поля процесса (как у JSON-вебхука) рядом с текстом
+#[derive(Serialize)]
+struct JsonLine<'a> {
+    timestamp: &'a str,
+    text: &'a str,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    alert: Option<WebhookAlert<'a>>,
+}
+
+// JSON Lines: по строке на процесс, служебное сообщение — одной строкой без полей процесса
+fn json_lines(alerts: &[Alert], text: &str) -> Result<Vec<u8>, WatcherError> {
+    let timestamp = Utc::now().to_rfc3339();
+    let lines: Vec<JsonLine> = if alerts.is_empty() {
+        vec![JsonLine { timestamp: &timestamp, text, alert: None }]
+    } else {
+        alerts
+            .iter()
+            .map(|alert| JsonLine {
+                timestamp: &timestamp,
+                text,
+                alert: Some(WebhookAlert::new(alert)),
+            })
+            .collect()
+    };
+    let mut buffer = Vec::new();
+    for line in &lines {
+        serde_json::to_writer(&mut buffer, line)?;
+        buffer.push(b'\n');
+    }
+    Ok(buffer)
+}
+
+// Локальный агрегатор на Unix-сокете: по JSON-строке на процесс (служебные сообщения — одной
+// строкой без полей процесса). Соединение держится между отправками; после ошибки записи оно
+// закрывается, и следующая попытка подключается заново
+#[cfg(unix)]
+pub struct UnixSocketNotifier {
+    pub path: std::path::PathBuf,
+    pub stream: tokio::sync::Mutex<Option<tokio::net::UnixStream>>,
+    pub timeout: Duration,
+}
+
+#[cfg(unix)]
+impl UnixSocketNotifier {
+    pub fn new(path: std::path::PathBuf, timeout: Duration) -> Self {
+        UnixSocketNotifier {
+            path,
+            stream: tokio::sync::Mutex::new(None),
+            timeout,
+        }
+    }
+
+    async fn write_lines(&self, lines: &[u8]) -> SendResult {
+        use tokio::io::AsyncWriteExt;
+
+        let mut stream = self.stream.lock().await;
+        // Очередь accept у зависшего агрегатора может быть полна: тогда connect ждёт бесконечно
+        if stream.is_none() {
+            let connected = match tokio::time::timeout(self.timeout, tokio::net::UnixStream::connect(&self.path)).await {
+                Ok(connected) => connected,
+                Err(_) => Err(std::io::ErrorKind::TimedOut.into()),
+            };
+            let connected =
+                connected.map_err(|e| WatcherError::io(format!("cannot connect to {}", self.path.display()), e))?;
+            *stream = Some(connected);
+        }
+        let written = match stream.as_mut() {
+            Some(connection) => tokio::time::timeout(self.timeout, connection.write_all(lines)).await,
+            None => return Ok(false),
+        };
+        match written {
+            Ok(Ok(())) => Ok(true),
+            Ok(Err(e)) => {
+                *stream = None;
+                Err(WatcherError::io(format!("write to {} failed", self.path.display()), e))
+            }
+            Err(_) => {
+                *stream = None;
+                Err(WatcherError::io(format!("write to {}", self.path.display()), std::io::ErrorKind::TimedOut.into()))
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+#[async_trait]
+impl Notifier for UnixSocketNotifier {
+    async fn send(&self, text: &str) -> SendResult {
+        self.send_alerts(&[], text).await
+    }
+
+    async fn send_alerts(&self, alerts: &[Alert], text: &str) -> SendResult {
+        let buffer = json_lines(alerts, text)?;
+        let sent = self.write_lines(&buffer).await?;
+        if sent {
+            info!(event = "send_ok", backend = "socket"; "Socket message sent: {}", text);
+        }
+        Ok(sent)
+    }
+}
+
+// Когда рассылка по нескольким адресатам считается успешной
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Delivery {
+    #[default]
+    All,
+    Any,
+}
+
+impl FromStr for Delivery {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, ()> {
+        match value.to_lowercase().as_str() {
+            "all" => Ok(Delivery::All),
+            "any" => Ok(Delivery::Any),
+            _ => Err(()),
+        }
+    }
+}
+
+// Отправляет одно сообщение каждому адресату (у каждого свои повторы, чтобы сбой одного
+// не приводил к дублям у остальных) и сводит результаты по политике Delivery
+pub struct FanOutNotifier {
+    pub targets: Vec<(String, Box<dyn Notifier>)>,
+    pub delivery: Delivery,
+}
+
+#[async_trait]
+impl Notifier for FanOutNotifier {
+    async fn send(&self, text: &str) -> SendResult {
+        self.send_alerts(&[], text).await
+    }
+
+    async fn send_alerts(&self, alerts: &[Alert], text: &str) -> SendResult {
+        let mut delivered = 0;
+        for (target, notifier) in &self.targets {
+            match notifier.send_alerts(alerts, text).await {
+                Ok(true) => delivered += 1,
+                Ok(false) => {
+                    warn!(event = "send_failed", target = target.as_str(); "Delivery to {} failed", target)
+                }
+                Err(e) => {
+                    warn!(event = "send_failed", target = target.as_str(), error:% = e; "Delivery to {} failed: {}", target, e)
+                }
+            }
+        }
+        Ok(match self.delivery {
+            Delivery::All => delivered == self.targets.len(),
+            Delivery::Any => delivered > 0,
+        })
+    }
+}
+
+// Выбирает канал по самому высокому уровню в пачке оповещений (ROUTE_INFO/WARNING/CRITICAL);
+// служебные сообщения и уровни без маршрута уходят в основной канал
+pub struct SeverityRouter {
+    pub primary: Box<dyn Notifier>,
+    pub routes: Vec<(Severity, Box<dyn Notifier>)>,
+}
+
+#[async_trait]
+impl Notifier for SeverityRouter {
+    async fn send(&self, text: &str) -> SendResult {
+        self.primary.send(text).await
+    }
+
+    async fn send_alerts(&self, alerts: &[Alert], text: &str) -> SendResult {
+        let severity = alerts.iter().map(|alert| alert.process.severity).max();
+        let route = self.routes.iter().find(|(route, _)| Some(*route) == severity);
+        match route {
+            Some((_, notifier)) => notifier.send_alerts(alerts, text).await,
+            None => self.primary.send_alerts(alerts, text).await,
+        }
+    }
+}
+
+// Повторяет временные сбои с экспоненциальной задержкой; окончательный отказ не повторяется
+pub struct RetryingNotifier {
+    pub inner: Box<dyn Notifier>,
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+}
+
+impl RetryingNotifier {
+    async fn retry<F, Fut>(&self, mut attempt_send: F) -> SendResult
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = SendResult>,
+    {
+        self.inner.begin();
+        let mut attempt = 0;
+        loop {
+            match attempt_send().await {
+                Err(e) if attempt < self.max_retries => {
+                    attempt += 1;
+                    let delay = self.initial_backoff.saturating_mul(2u32.saturating_pow(attempt - 1));
+                    warn!(
+                        event = "send_retry",
+                        attempt = attempt,
+                        error:% = e;
+                        "Send failed ({}), retry {}/{} in {:?}",
+                        e, attempt, self.max_retries, delay
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for RetryingNotifier {
+    async fn send(&self, text: &str) -> SendResult {
+        self.retry(|| self.inner.send(text)).await
+    }
+
+    async fn send_alerts(&self, alerts: &[Alert], text: &str) -> SendResult {
+        self.retry(|| self.inner.send_alerts(alerts, text)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markup::Markup;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    fn lengths(chunks: &[String]) -> Vec<usize> {
+        chunks.iter().map(|chunk| chunk.chars().count()).collect()
+    }
+
+    #[test]
+    fn splits_long_message_on_line_boundaries() {
+        // 100 строк по 99 символов с переводами строк — ровно 10 000 символов
+        let text = vec!["x".repeat(99); 100].join("\n") + "\n";
+        assert_eq!(text.chars().count(), 10_000);
+        let chunks = split_message(&text, TELEGRAM_MAX_CHARS, None);
+        // В кусок входит 40 строк: 40 * 99 + 39 = 3999, а 41-я уже не влезает; в последнем
+        // ещё пустая строка после завершающего перевода строки
+        assert_eq!(lengths(&chunks), [3999, 3999, 2000]);
+        assert_eq!(chunks.join("\n"), text);
+    }
+
+    #[test]
+    fn cuts_overlong_line_by_characters() {
+        let text = "я".repeat(10_000);
+        let chunks = split_message(&text, TELEGRAM_MAX_CHARS, None);
+        assert_eq!(lengths(&chunks), [4096, 4096, 1808]);
+        assert_eq!(chunks.concat(), text);
+    }
+
+    #[test]
+    fn discord_limit_is_smaller() {
+        let text = "x".repeat(10_000);
+        assert_eq!(split_message(&text, DISCORD_MAX_CHARS, None).len(), 5);
+    }
+
+    #[test]
+    fn markdown_code_span_is_closed_and_reopened() {
+        let line = format!("*Команда:* {}", Markup(Some(ParseMode::MarkdownV2)).code(&"a\\`".repeat(3000)));
+        let chunks = split_message(&line, TELEGRAM_MAX_CHARS, Some(ParseMode::MarkdownV2));
+        // После экранирования "a\\\`" — 5 символов, всего около 15 000
+        assert_eq!(chunks.len(), 4);
+        for chunk in &chunks {
+            assert!(chunk.chars().count() <= TELEGRAM_MAX_CHARS);
+            assert!(chunk.ends_with('`'), "{:?}", &chunk[chunk.len() - 10..]);
+            // Каждое экранирование целиком в одном куске: неэкранированные ` только по краям
+            let unescaped = markup_tokens(chunk, Some(ParseMode::MarkdownV2)).into_iter().filter(|token| *token == "`");
+            assert_eq!(unescaped.count(), 2);
+        }
+        assert!(chunks[1].starts_with('`'));
+    }
+
+    #[test]
+    fn html_entities_and_tags_survive_the_cut() {
+        let line = format!("<b>Команда:</b> {}", Markup(Some(ParseMode::Html)).code(&"<&>".repeat(2000)));
+        let chunks = split_message(&line, TELEGRAM_MAX_CHARS, Some(ParseMode::Html));
+        assert!(chunks.len() > 1);
+        for (index, chunk) in chunks.iter().enumerate() {
+            assert!(chunk.chars().count() <= TELEGRAM_MAX_CHARS);
+            assert!(chunk.ends_with("</code>"));
+            if index > 0 {
+                assert!(chunk.starts_with("<code>"));
+            }
+            assert_eq!(chunk.matches('&').count(), chunk.matches(';').count());
+        }
+    }
+
+    #[tokio::test]
+    async fn retry_resumes_after_last_sent_chunk() {
+        let sent = SentChunks::default();
+        let chunks = || vec!["first".to_string(), "second".to_string(), "third".to_string()];
+        let delivered = Mutex::new(Vec::new());
+        let fail_second = AtomicBool::new(true);
+        let send = |_, chunk: String| {
+            let result = if chunk == "second" && fail_second.swap(false, Ordering::SeqCst) {
+                Err(WatcherError::Server { service: "test", status: reqwest::StatusCode::BAD_GATEWAY })
+            } else {
+                delivered.lock().unwrap().push(chunk);
+                Ok(true)
+            };
+            async move { result }
+        };
+
+        assert!(sent.send("text", chunks(), send).await.is_err());
+        assert!(sent.send("text", chunks(), send).await.unwrap());
+        assert_eq!(*delivered.lock().unwrap(), ["first", "second", "third"]);
+
+        // После неудачи новая отправка того же текста (begin) идёт с начала
+        delivered.lock().unwrap().clear();
+        fail_second.store(true, Ordering::SeqCst);
+        assert!(sent.send("text", chunks(), send).await.is_err());
+        sent.reset();
+        assert!(sent.send("text", chunks(), send).await.unwrap());
+        assert_eq!(*delivered.lock().unwrap(), ["first", "first", "second", "third"]);
+    }
+
+    #[test]
+    fn kill_and_renice_buttons_only_for_actionable_alerts() {
+        let config = crate::Config {
+            rules: vec![crate::config::Rule {
+                name: None,
+                pattern: "miner".to_string(),
+                threshold: 50.0,
+                action: crate::Action::Kill,
+                nice: None,
+                kill_grace_seconds: None,
+            }],
+            ..crate::Config::default()
+        };
+        let mut processes = crate::CannedProcesses::new(1, 8 << 30);
+        processes.insert(crate::CannedProcess::new(100, "miner", 95.0));
+        processes.insert(crate::CannedProcess::new(200, "stray", 95.0));
+        let mut watcher = crate::Watcher::new(&config, &processes).unwrap();
+        let mut alerts = watcher.check(&processes, None);
+        alerts.sort_by_key(|alert| alert.process.pid);
+
+        let keyboard = keyboard(&alerts).unwrap();
+        let rows: Vec<Vec<&str>> = keyboard
+            .inline_keyboard
+            .iter()
+            .map(|row| row.iter().map(|button| button.callback_data.split(':').next().unwrap()).collect())
+            .collect();
+        assert_eq!(rows, [vec!["kill", "renice", "mute"], vec!["mute"]]);
+    }
+}
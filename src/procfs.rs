@@ -0,0 +1,35 @@
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+const DEFAULT_ROOT: &str = "/proc";
+
+// PROC_PATH задаётся один раз при запуске: читатели /proc разбросаны по модулям, и
+// протаскивать путь в каждый из них незачем — без перезапуска он не меняется
+static ROOT: OnceLock<PathBuf> = OnceLock::new();
+
+/// Задаёт, где смонтирован procfs (PROC_PATH), например `/host/proc` в контейнере. Действует
+/// только первый вызов: до него файлы процессов читаются из `/proc`.
+pub fn set_root(path: &Path) {
+    let _ = ROOT.set(path.to_path_buf());
+}
+
+/// Каталог procfs, из которого читаются файлы процессов.
+pub fn root() -> &'static Path {
+    ROOT.get().map_or(Path::new(DEFAULT_ROOT), PathBuf::as_path)
+}
+
+/// Задан ли procfs, отличный от `/proc`.
+pub fn is_custom() -> bool {
+    root() != Path::new(DEFAULT_ROOT)
+}
+
+/// Каталог процесса: `<root>/PID`.
+pub fn process_dir(pid: impl Display) -> PathBuf {
+    root().join(pid.to_string())
+}
+
+/// Файл процесса: `<root>/PID/<name>`, например `cmdline` или `ns/net`.
+pub fn process_file(pid: impl Display, name: &str) -> PathBuf {
+    process_dir(pid).join(name)
+}
@@ -0,0 +1,73 @@
+use chrono::{DateTime, Utc};
+use cpu_watcher::config::{QuietHours, QuietTimezone};
+use cpu_watcher::locale::{fill, Strings};
+use cpu_watcher::markup::Markup;
+use cpu_watcher::message::NumberFormat;
+use cpu_watcher::Alert;
+use std::collections::BTreeMap;
+
+// Сколько процессов перечислять в сводке за тихие часы
+const MAX_SUMMARY_LINES: usize = 20;
+
+// Что накопилось за тихие часы по одному имени процесса
+struct Held {
+    peak_cpu: f32,
+    alerts: usize,
+}
+
+// Оповещения, придержанные в тихие часы, копятся по имени процесса и уходят одной
+// сводкой, как только окно закончилось
+pub struct QuietQueue {
+    hours: QuietHours,
+    timezone: QuietTimezone,
+    held: BTreeMap<String, Held>,
+}
+
+impl QuietQueue {
+    pub fn new(hours: QuietHours, timezone: QuietTimezone) -> Self {
+        QuietQueue {
+            hours,
+            timezone,
+            held: BTreeMap::new(),
+        }
+    }
+
+    pub fn is_quiet(&self, now: DateTime<Utc>) -> bool {
+        self.hours.contains(self.timezone.time_of(now))
+    }
+
+    pub fn hold(&mut self, alerts: &[Alert]) {
+        for alert in alerts {
+            let held = self.held.entry(alert.process.name.clone()).or_insert(Held {
+                peak_cpu: 0.0,
+                alerts: 0,
+            });
+            held.peak_cpu = held.peak_cpu.max(alert.process.cpu_percent);
+            held.alerts += 1;
+        }
+    }
+
+    // Текст сводки, если за окно что-то накопилось; очищать — только после успешной отправки
+    pub fn summary(&self, numbers: NumberFormat, strings: &Strings) -> Option<String> {
+        if self.held.is_empty() {
+            return None;
+        }
+        let mut held: Vec<_> = self.held.iter().collect();
+        held.sort_by(|a, b| b.1.peak_cpu.total_cmp(&a.1.peak_cpu));
+        // Текст без разметки: его экранирует отправитель
+        let plain = Markup(None);
+        let values = [("hours", self.hours.to_string()), ("count", held.len().to_string())];
+        let mut lines = vec![format!("🌙 {}", fill(&strings.quiet_summary, plain, &values))];
+        for (name, held) in held.iter().take(MAX_SUMMARY_LINES) {
+            lines.push(format!("{}: peak CPU {}%, {} alert(s)", name, numbers.percent(held.peak_cpu), held.alerts));
+        }
+        if held.len() > MAX_SUMMARY_LINES {
+            lines.push(fill(&strings.more, plain, &[("count", (held.len() - MAX_SUMMARY_LINES).to_string())]));
+        }
+        Some(lines.join("\n"))
+    }
+
+    pub fn clear(&mut self) {
+        self.held.clear();
+    }
+}
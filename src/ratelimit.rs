@@ -0,0 +1,37 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+// Скользящее окно: не больше max_per_window отправок за window. max_per_window = 0 — без лимита
+pub struct RateLimiter {
+    max_per_window: usize,
+    window: Duration,
+    sent: VecDeque<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(max_per_window: usize, window: Duration) -> Self {
+        RateLimiter {
+            max_per_window,
+            window,
+            sent: VecDeque::new(),
+        }
+    }
+
+    pub fn try_acquire(&mut self, now: Instant) -> bool {
+        if self.max_per_window == 0 {
+            return true;
+        }
+        while self
+            .sent
+            .front()
+            .is_some_and(|sent_at| now.duration_since(*sent_at) >= self.window)
+        {
+            self.sent.pop_front();
+        }
+        if self.sent.len() >= self.max_per_window {
+            return false;
+        }
+        self.sent.push_back(now);
+        true
+    }
+}
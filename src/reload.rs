@@ -0,0 +1,211 @@
+use cpu_watcher::{Config, WatcherError};
+use log::warn;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tokio::task::JoinHandle;
+
+// Сколько ждать stat и чтения файла на проверке. Зависший NFS держит поток чтения сколько
+// угодно, но цикл проверок после этого срока идёт дальше с прежними настройками
+const READ_TIMEOUT: Duration = Duration::from_secs(2);
+
+// Результат одной проверки файла в отдельном потоке
+struct Probe {
+    modified: Option<SystemTime>,
+    // None — файл не менялся, перечитывать нечего
+    loaded: Option<Result<Config, WatcherError>>,
+}
+
+pub enum Reload {
+    Unchanged,
+    Loaded(Box<Config>),
+    Failed(String),
+}
+
+// Следит за файлом конфигурации по времени изменения: проверка mtime раз в цикл дешевле
+// отдельного потока с inotify и работает и на сетевых ФС. stat и чтение идут в потоке
+// spawn_blocking с таймаутом, чтобы зависшая сетевая ФС не останавливала проверки
+pub struct ConfigWatch {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    // Чтение, не уложившееся в READ_TIMEOUT: пока оно не закончится, новое не начинается
+    pending: Option<JoinHandle<Probe>>,
+    // Сбой чтения пишется в лог один раз до следующего успешного
+    failing: bool,
+}
+
+impl ConfigWatch {
+    // Время изменения при запуске читается так же, в потоке и с таймаутом: без ответа ФС оно
+    // остаётся неизвестным, и первая удачная проверка перечитает файл
+    pub async fn new(path: PathBuf) -> Self {
+        let mut watch = ConfigWatch {
+            path,
+            modified: None,
+            pending: None,
+            failing: false,
+        };
+        let path = watch.path.clone();
+        let mut handle = tokio::task::spawn_blocking(move || modified(&path));
+        match tokio::time::timeout(READ_TIMEOUT, &mut handle).await {
+            Ok(Ok(modified)) => watch.modified = modified,
+            Ok(Err(e)) => {
+                watch.read_failed(format!("config read task failed: {}", e));
+            }
+            Err(_) => {
+                watch.read_failed(format!("no answer from the file system in {}s", READ_TIMEOUT.as_secs()));
+            }
+        }
+        watch
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Перечитывает файл, если он изменился с прошлой проверки. Пока файла нет (редактор
+    // сохраняет через удаление и переименование), перечитывать нечего. Ошибка чтения —
+    // временная: mtime не запоминается, и файл читается снова на следующей проверке
+    pub async fn poll(&mut self) -> Reload {
+        let mut handle = match self.pending.take() {
+            Some(handle) if !handle.is_finished() => {
+                self.pending = Some(handle);
+                return Reload::Unchanged;
+            }
+            Some(handle) => handle,
+            None => {
+                let path = self.path.clone();
+                let known = self.modified;
+                tokio::task::spawn_blocking(move || probe(&path, known))
+            }
+        };
+        let probe = match tokio::time::timeout(READ_TIMEOUT, &mut handle).await {
+            Ok(Ok(probe)) => probe,
+            Ok(Err(e)) => return self.read_failed(format!("config read task failed: {}", e)),
+            Err(_) => {
+                self.pending = Some(handle);
+                return self.read_failed(format!("no answer from the file system in {}s", READ_TIMEOUT.as_secs()));
+            }
+        };
+
+        if probe.modified == self.modified {
+            self.failing = false;
+            return Reload::Unchanged;
+        }
+        match probe.loaded {
+            None => {
+                self.modified = probe.modified;
+                Reload::Unchanged
+            }
+            Some(Err(WatcherError::Io { context, source })) => self.read_failed(format!("{}: {}", context, source)),
+            Some(loaded) => {
+                self.modified = probe.modified;
+                self.failing = false;
+                match loaded {
+                    Ok(config) => Reload::Loaded(Box::new(config)),
+                    Err(e) => Reload::Failed(e.to_string()),
+                }
+            }
+        }
+    }
+
+    fn read_failed(&mut self, reason: String) -> Reload {
+        if !self.failing {
+            self.failing = true;
+            warn!(event = "config_read_failed", error:% = reason;
+                "Cannot read config file {} ({}), keeping the last good settings and retrying", self.path.display(), reason);
+        }
+        Reload::Unchanged
+    }
+}
+
+fn probe(path: &Path, known: Option<SystemTime>) -> Probe {
+    let modified = modified(path);
+    let loaded = (modified.is_some() && modified != known).then(|| Config::load(Some(path)));
+    Probe { modified, loaded }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Свой каталог на тест во временном каталоге системы
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("cpu_watcher-reload-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn loaded_threshold(reload: Reload) -> Option<f32> {
+        match reload {
+            Reload::Loaded(config) => Some(config.threshold),
+            _ => None,
+        }
+    }
+
+    #[tokio::test]
+    async fn read_error_keeps_last_good_config() {
+        let dir = scratch("read-error");
+        let path = dir.join("config.toml");
+        fs::write(&path, "threshold = 70.0\n").unwrap();
+        let mut watch = ConfigWatch::new(path.clone()).await;
+        assert!(matches!(watch.poll().await, Reload::Unchanged));
+
+        // На месте файла каталог: чтение падает, прежние настройки остаются
+        fs::remove_file(&path).unwrap();
+        fs::create_dir(&path).unwrap();
+        assert!(matches!(watch.poll().await, Reload::Unchanged));
+        assert!(watch.failing);
+        assert!(matches!(watch.poll().await, Reload::Unchanged));
+
+        fs::remove_dir(&path).unwrap();
+        fs::write(&path, "threshold = 90.0\n").unwrap();
+        assert_eq!(loaded_threshold(watch.poll().await), Some(90.0));
+        assert!(!watch.failing);
+
+        // Ошибка в самом файле — не временная: о ней сообщают один раз
+        fs::write(&path, "threshold = \"high\"\n").unwrap();
+        assert!(matches!(watch.poll().await, Reload::Failed(_)));
+        assert!(matches!(watch.poll().await, Reload::Unchanged));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn hung_read_times_out_and_is_picked_up_later() {
+        let dir = scratch("timeout");
+        let path = dir.join("config.toml");
+        fs::write(&path, "threshold = 70.0\n").unwrap();
+        let mut watch = ConfigWatch::new(path.clone()).await;
+
+        // Чтение из FIFO без писателя висит, как зависший NFS
+        fs::remove_file(&path).unwrap();
+        let fifo = std::ffi::CString::new(path.to_str().unwrap()).unwrap();
+        assert_eq!(unsafe { libc::mkfifo(fifo.as_ptr(), 0o600) }, 0);
+        assert!(matches!(watch.poll().await, Reload::Unchanged));
+        assert!(watch.failing);
+        assert!(watch.pending.is_some());
+        // Пока прежнее чтение не закончилось, новое не начинается
+        assert!(matches!(watch.poll().await, Reload::Unchanged));
+
+        let writer = std::thread::spawn({
+            let path = path.clone();
+            move || fs::write(path, "threshold = 90.0\n").unwrap()
+        });
+        writer.join().unwrap();
+        let mut threshold = None;
+        for _ in 0..50 {
+            threshold = loaded_threshold(watch.poll().await);
+            if threshold.is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(threshold, Some(90.0));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,122 @@
+use crate::config::Config;
+use crate::filter::{LazyCmdline, ProcessFilter};
+use crate::{read_cmdline, ProcReads};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant};
+use sysinfo::{Pid, System};
+
+/// Команда, которая за окно `restart_window_seconds` запускалась с новыми PID чаще порога.
+pub struct RestartAlert {
+    pub cmdline: String,
+    pub name: String,
+    /// PID последнего запуска.
+    pub pid: Pid,
+    /// Сколько новых PID с этой командной строкой появилось за окно.
+    pub restarts: usize,
+    pub per_minute: f64,
+    pub window: Duration,
+}
+
+/// Поиск циклов перезапуска: новые PID с той же командной строкой, которые появляются раз
+/// за разом. Считаются только PID, которых не было на прошлой проверке; процессы, живущие
+/// меньше интервала проверки, не видны вовсе. Кулдаун ведётся по командной строке.
+pub struct RestartMonitor {
+    threshold: f64,
+    window: Duration,
+    cooldown_seconds: u64,
+    filter: ProcessFilter,
+    // PID на прошлой проверке; None до первой — тогда все процессы считаются старыми
+    known: Option<HashSet<Pid>>,
+    // Время появления новых PID по командной строке, только в пределах окна
+    launches: HashMap<String, VecDeque<Instant>>,
+    // PID и имя последнего запуска
+    latest: HashMap<String, (Pid, String)>,
+    alerted: HashMap<String, Instant>,
+}
+
+impl RestartMonitor {
+    /// None, если `restart_threshold` не задан.
+    pub fn new(config: &Config) -> Option<RestartMonitor> {
+        let threshold = config.restart_threshold?;
+        // Шаблоны уже проверил Watcher::new, ошибка здесь невозможна
+        let filter = ProcessFilter::new(&config.only_processes, &config.ignore_processes).ok()?;
+        Some(RestartMonitor {
+            threshold,
+            window: Duration::from_secs(config.restart_window_seconds.max(1)),
+            cooldown_seconds: config.cooldown_seconds,
+            filter,
+            known: None,
+            launches: HashMap::new(),
+            latest: HashMap::new(),
+            alerted: HashMap::new(),
+        })
+    }
+
+    /// Команды, перезапускавшиеся за окно чаще порога и ещё не оповещённые в пределах кулдауна.
+    pub fn check(&mut self, sys: &System, now: Instant) -> Vec<RestartAlert> {
+        let mut current = HashSet::new();
+        let reads = ProcReads::default();
+        for (pid, process) in sys.processes() {
+            // Потоки делят командную строку с процессом и выглядели бы как перезапуски
+            if process.thread_kind().is_some() {
+                continue;
+            }
+            current.insert(*pid);
+            match &self.known {
+                Some(known) if !known.contains(pid) => {}
+                _ => continue,
+            }
+            // Командная строка — из /proc, как у Watcher: sysinfo у новых PID её часто не успевает
+            // прочитать. У потоков ядра и зомби её нет
+            let Some(cmdline) = read_cmdline(process, &reads) else {
+                continue;
+            };
+            let name = process.name();
+            if !self.filter.allows(name, &mut LazyCmdline::new(*pid, process, || Some(cmdline.clone()))) {
+                continue;
+            }
+            self.launches.entry(cmdline.clone()).or_default().push_back(now);
+            self.latest.insert(cmdline, (*pid, name.to_string()));
+        }
+        self.known = Some(current);
+
+        let window = self.window;
+        for launches in self.launches.values_mut() {
+            while launches.front().is_some_and(|at| now.saturating_duration_since(*at) > window) {
+                launches.pop_front();
+            }
+        }
+        self.launches.retain(|_, launches| !launches.is_empty());
+        let launches = &self.launches;
+        self.latest.retain(|cmdline, _| launches.contains_key(cmdline));
+        self.alerted.retain(|cmdline, _| launches.contains_key(cmdline));
+
+        let minutes = window.as_secs_f64() / 60.0;
+        // BTreeMap — команды в сообщениях всегда в одном порядке
+        let sorted: BTreeMap<&String, &VecDeque<Instant>> = self.launches.iter().collect();
+        sorted
+            .into_iter()
+            .filter(|(_, launches)| launches.len() as f64 / minutes >= self.threshold)
+            .filter(|(cmdline, _)| match self.alerted.get(*cmdline) {
+                Some(last) => now.saturating_duration_since(*last).as_secs() >= self.cooldown_seconds,
+                None => true,
+            })
+            .filter_map(|(cmdline, launches)| {
+                let (pid, name) = self.latest.get(cmdline)?;
+                Some(RestartAlert {
+                    cmdline: cmdline.clone(),
+                    name: name.clone(),
+                    pid: *pid,
+                    restarts: launches.len(),
+                    per_minute: launches.len() as f64 / minutes,
+                    window,
+                })
+            })
+            .collect()
+    }
+
+    /// Запоминает отправленное оповещение: до конца кулдауна эта команда не повторится.
+    pub fn mark_alerted(&mut self, alert: &RestartAlert, now: Instant) {
+        self.alerted.insert(alert.cmdline.clone(), now);
+    }
+}
@@ -0,0 +1,117 @@
+use crate::actions::{Action, RuleAction, DEFAULT_KILL_GRACE_SECONDS, DEFAULT_NICE};
+use crate::config::Rule;
+use crate::filter::{LazyCmdline, Pattern};
+use std::time::Duration;
+
+pub struct CompiledRule {
+    pub label: String,
+    pattern: Pattern,
+    pub threshold: f32,
+    pub action: RuleAction,
+}
+
+// Правила с собственными порогами CPU. Проверяются по порядку, срабатывает первое подходящее;
+// если ни одно не подошло, действует глобальный CPU_THRESHOLD.
+pub struct RuleSet {
+    rules: Vec<CompiledRule>,
+}
+
+impl RuleSet {
+    pub fn new(rules: &[Rule]) -> Result<Self, String> {
+        let rules = rules
+            .iter()
+            .map(|rule| {
+                let pattern = Pattern::parse(&rule.pattern)
+                    .map_err(|e| format!("rule {:?}: {}", rule.pattern, e))?;
+                Ok(CompiledRule {
+                    label: rule.name.clone().unwrap_or_else(|| rule.pattern.clone()),
+                    pattern,
+                    threshold: rule.threshold,
+                    action: RuleAction {
+                        action: rule.action,
+                        nice: rule.nice.unwrap_or(DEFAULT_NICE),
+                        kill_grace: Duration::from_secs(rule.kill_grace_seconds.unwrap_or(DEFAULT_KILL_GRACE_SECONDS)),
+                    },
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(RuleSet { rules })
+    }
+
+    // Правила с действием, отличным от notify — для предупреждения, если ENABLE_ACTIONS выключен
+    pub fn with_actions(&self) -> impl Iterator<Item = &CompiledRule> {
+        self.rules.iter().filter(|rule| rule.action.action != Action::Notify)
+    }
+
+    pub fn find<F: FnOnce() -> Option<String>>(
+        &self,
+        name: &str,
+        cmdline: &mut LazyCmdline<'_, F>,
+    ) -> Option<&CompiledRule> {
+        self.rules.iter().find(|rule| rule.pattern.matches(name, cmdline))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::{CannedProcess, CannedProcesses, Watcher};
+
+    fn rule(pattern: &str, threshold: f32) -> Rule {
+        Rule {
+            name: None,
+            pattern: pattern.to_string(),
+            threshold,
+            action: Action::Notify,
+            nice: None,
+            kill_grace_seconds: None,
+        }
+    }
+
+    fn rules() -> Vec<Rule> {
+        vec![rule("re:^postgres", 90.0), rule("postgres", 70.0), rule("re:backup", 20.0)]
+    }
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let rules = RuleSet::new(&rules()).unwrap();
+        let process = CannedProcess::new(100, "postgres", 0.0);
+        let mut cmdline = LazyCmdline::new(process.pid, &process, || Some("postgres: backup".to_string()));
+        let found = rules.find("postgres", &mut cmdline).unwrap();
+        assert_eq!(found.label, "re:^postgres");
+        assert_eq!(found.threshold, 90.0);
+    }
+
+    #[test]
+    fn global_threshold_applies_without_matching_rule() {
+        let config = Config {
+            threshold: 50.0,
+            rules: rules(),
+            ..Config::default()
+        };
+        let mut processes = CannedProcesses::new(1, 8 << 30);
+        processes.insert(CannedProcess::new(100, "postgres", 80.0));
+        processes.insert(CannedProcess::new(200, "nginx", 60.0));
+        processes.insert(CannedProcess {
+            cmdline: Some("tar czf /backup/db.tgz".to_string()),
+            ..CannedProcess::new(300, "tar", 30.0)
+        });
+        let mut watcher = Watcher::new(&config, &processes).unwrap();
+
+        let mut alerts: Vec<_> = watcher
+            .check(&processes, None)
+            .into_iter()
+            .map(|alert| (alert.process.name, alert.process.rule, alert.process.cpu_threshold))
+            .collect();
+        alerts.sort_by(|a, b| a.0.cmp(&b.0));
+        // postgres (80%) ниже своего правила в 90%, nginx — по глобальному порогу
+        assert_eq!(
+            alerts,
+            [
+                ("nginx".to_string(), None, 50.0),
+                ("tar".to_string(), Some("re:backup".to_string()), 20.0),
+            ]
+        );
+    }
+}
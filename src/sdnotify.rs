@@ -0,0 +1,88 @@
+// Протокол sd_notify: строка состояния одной датаграммой в сокет из NOTIFY_SOCKET. Без
+// systemd (переменной нет) и вне Linux уведомления не отправляются
+use std::time::Duration;
+
+#[cfg(target_os = "linux")]
+pub struct SdNotify {
+    socket: std::os::unix::net::UnixDatagram,
+    addr: std::os::unix::net::SocketAddr,
+    // Ошибка пишется в лог один раз, а не на каждой проверке
+    failed: std::cell::Cell<bool>,
+}
+
+#[cfg(target_os = "linux")]
+impl SdNotify {
+    pub fn from_env() -> Option<Self> {
+        use std::os::linux::net::SocketAddrExt;
+        use std::os::unix::net::{SocketAddr, UnixDatagram};
+
+        let path = std::env::var_os("NOTIFY_SOCKET")?;
+        let path = path.to_string_lossy();
+        // "@name" — сокет в абстрактном пространстве имён
+        let addr = match path.strip_prefix('@') {
+            Some(name) => SocketAddr::from_abstract_name(name.as_bytes()),
+            None => SocketAddr::from_pathname(path.as_ref()),
+        };
+        let addr = match addr {
+            Ok(addr) => addr,
+            Err(e) => {
+                log::warn!("Invalid NOTIFY_SOCKET {:?}: {}", path, e);
+                return None;
+            }
+        };
+        let socket = match UnixDatagram::unbound() {
+            Ok(socket) => socket,
+            Err(e) => {
+                log::warn!("Cannot create sd_notify socket: {}", e);
+                return None;
+            }
+        };
+        Some(SdNotify {
+            socket,
+            addr,
+            failed: std::cell::Cell::new(false),
+        })
+    }
+
+    fn send(&self, state: &str) {
+        if let Err(e) = self.socket.send_to_addr(state.as_bytes(), &self.addr) {
+            if !self.failed.replace(true) {
+                log::warn!(event = "sd_notify_failed", error:% = e; "sd_notify {} failed: {}", state, e);
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub struct SdNotify;
+
+#[cfg(not(target_os = "linux"))]
+impl SdNotify {
+    pub fn from_env() -> Option<Self> {
+        None
+    }
+
+    fn send(&self, _state: &str) {}
+}
+
+impl SdNotify {
+    // Прогрев закончился, первая проверка впереди
+    pub fn ready(&self) {
+        self.send("READY=1");
+    }
+
+    // Проверка прошла целиком; без этого systemd перезапустит сервис через WatchdogSec
+    pub fn watchdog(&self) {
+        self.send("WATCHDOG=1");
+    }
+
+    pub fn stopping(&self) {
+        self.send("STOPPING=1");
+    }
+}
+
+// Интервал сторожевого таймера, который systemd передаёт в WATCHDOG_USEC
+pub fn watchdog_interval() -> Option<Duration> {
+    let usec: u64 = std::env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+    (usec > 0).then(|| Duration::from_micros(usec))
+}
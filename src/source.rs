@@ -0,0 +1,496 @@
+use std::collections::HashMap;
+use std::io;
+use std::time::Duration;
+use sysinfo::{DiskUsage, Pid, Process, ProcessStatus, System, Uid};
+
+/// Процесс, каким его видит проверка: имя, командная строка, CPU, память и то, что нужно
+/// фильтрам и дереву процессов. В программе это [`sysinfo::Process`], в тестах —
+/// [`CannedProcess`].
+pub trait ProcessView {
+    fn name(&self) -> &str;
+    /// Командная строка через пробел; `Ok(None)` — пустая (поток ядра, зомби). Ошибка, кроме
+    /// `NotFound` у завершившегося процесса, засчитывается в сбои чтения /proc.
+    fn cmdline(&self) -> io::Result<Option<String>>;
+    /// CPU в процентах от одного ядра с прошлого обновления, как у sysinfo.
+    fn cpu_usage(&self) -> f32;
+    /// Resident set size, байт.
+    fn memory(&self) -> u64;
+    /// Время запуска в секундах Unix; 0 — неизвестно.
+    fn start_time(&self) -> u64;
+    fn parent(&self) -> Option<Pid>;
+    fn status(&self) -> ProcessStatus;
+    fn user_id(&self) -> Option<&Uid>;
+    /// Накопительные счётчики чтения и записи диска.
+    fn disk_usage(&self) -> DiskUsage;
+    /// Процесс уже завершился (каталога в /proc нет), хотя ещё есть в списке.
+    fn exited(&self) -> bool;
+    /// Полный путь к исполняемому файлу.
+    fn exe(&self) -> Option<String>;
+    /// Переменные окружения на момент запуска; None — не читаются.
+    fn environ(&self) -> Option<Vec<(String, String)>>;
+    fn open_files(&self) -> Option<usize>;
+    fn threads(&self) -> Option<usize>;
+    /// Объём в свопе, байт.
+    fn swap(&self) -> Option<u64>;
+    /// Функция ядра, в которой процесс ждёт.
+    fn wchan(&self) -> Option<String>;
+    /// Время CPU с запуска (user + system).
+    fn cpu_time(&self) -> Option<Duration>;
+    /// Полный ID контейнера Docker/containerd.
+    fn container_id(&self) -> Option<String>;
+    /// Сетевое пространство имён, например `net:[4026531840]`.
+    fn net_namespace(&self) -> Option<String>;
+    /// Принятые и отправленные байты всего сетевого пространства, без loopback.
+    fn net_traffic(&self) -> Option<(u64, u64)>;
+}
+
+/// Список процессов и объёмы системы, с которыми работает [`crate::Watcher`]. В программе —
+/// обновлённый [`sysinfo::System`]; в тестах — [`CannedProcesses`], где процессы и их CPU
+/// задаются вручную.
+pub trait ProcessSource {
+    fn processes(&self) -> Box<dyn Iterator<Item = (&Pid, &dyn ProcessView)> + '_>;
+    fn process(&self, pid: Pid) -> Option<&dyn ProcessView>;
+    /// Объём RAM, байт: для порогов памяти в процентах.
+    fn total_memory(&self) -> u64;
+    /// Объём свопа, байт; 0 — свопа нет.
+    fn total_swap(&self) -> u64;
+    /// Число логических ядер для режима `normalized`.
+    fn cpu_count(&self) -> usize;
+}
+
+impl ProcessView for Process {
+    fn name(&self) -> &str {
+        Process::name(self)
+    }
+
+    // На Linux — напрямую из /proc/PID/cmdline: sysinfo обновляет его не всегда
+    #[cfg(target_os = "linux")]
+    fn cmdline(&self) -> io::Result<Option<String>> {
+        let content = std::fs::read(crate::procfs::process_file(self.pid(), "cmdline"))?;
+        // В /proc/PID/cmdline аргументы разделены нулевыми байтами
+        let args: Vec<&str> = content
+            .split(|&b| b == 0)
+            .filter(|s| !s.is_empty())
+            .map(|s| std::str::from_utf8(s).unwrap_or_default())
+            .collect();
+        Ok((!args.is_empty()).then(|| args.join(" ")))
+    }
+
+    // Вне Linux /proc нет — берём аргументы, которые собирает sysinfo (иногда они обрезаны)
+    #[cfg(not(target_os = "linux"))]
+    fn cmdline(&self) -> io::Result<Option<String>> {
+        let args = self.cmd();
+        Ok((!args.is_empty()).then(|| args.join(" ")))
+    }
+
+    fn cpu_usage(&self) -> f32 {
+        Process::cpu_usage(self)
+    }
+
+    fn memory(&self) -> u64 {
+        Process::memory(self)
+    }
+
+    fn start_time(&self) -> u64 {
+        Process::start_time(self)
+    }
+
+    fn parent(&self) -> Option<Pid> {
+        Process::parent(self)
+    }
+
+    fn status(&self) -> ProcessStatus {
+        Process::status(self)
+    }
+
+    fn user_id(&self) -> Option<&Uid> {
+        Process::user_id(self)
+    }
+
+    fn disk_usage(&self) -> DiskUsage {
+        Process::disk_usage(self)
+    }
+
+    fn exited(&self) -> bool {
+        process_exited(self.pid())
+    }
+
+    // Путь к исполняемому файлу из ссылки /proc/PID/exe; у чужих процессов без root она не читается.
+    // Удалённый после запуска файл показывается с пометкой " (deleted)"
+    #[cfg(target_os = "linux")]
+    fn exe(&self) -> Option<String> {
+        std::fs::read_link(crate::procfs::process_file(self.pid(), "exe"))
+            .ok()
+            .map(|path| path.to_string_lossy().into_owned())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn exe(&self) -> Option<String> {
+        Process::exe(self).map(|path| path.to_string_lossy().into_owned())
+    }
+
+    fn environ(&self) -> Option<Vec<(String, String)>> {
+        read_environ(self.pid())
+    }
+
+    fn open_files(&self) -> Option<usize> {
+        count_open_files(self.pid())
+    }
+
+    fn threads(&self) -> Option<usize> {
+        count_threads(self.pid())
+    }
+
+    fn swap(&self) -> Option<u64> {
+        read_swap(self.pid())
+    }
+
+    fn wchan(&self) -> Option<String> {
+        read_wchan(self.pid())
+    }
+
+    fn cpu_time(&self) -> Option<Duration> {
+        read_cpu_time(self.pid())
+    }
+
+    fn container_id(&self) -> Option<String> {
+        crate::container::container_id(self.pid())
+    }
+
+    fn net_namespace(&self) -> Option<String> {
+        crate::network::namespace_of(&self.pid().to_string())
+    }
+
+    fn net_traffic(&self) -> Option<(u64, u64)> {
+        crate::network::read_net_dev(self.pid())
+    }
+}
+
+// Открытые дескрипторы — записи в /proc/PID/fd. Чужие процессы без прав root дают
+// PermissionDenied: такой процесс просто не проверяется
+#[cfg(target_os = "linux")]
+fn count_open_files(pid: Pid) -> Option<usize> {
+    std::fs::read_dir(crate::procfs::process_file(pid, "fd")).ok().map(|entries| entries.count())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn count_open_files(_pid: Pid) -> Option<usize> {
+    None
+}
+
+// Каталога /proc/PID уже нет — процесс завершился. Вне Linux проверить нечем
+#[cfg(target_os = "linux")]
+fn process_exited(pid: Pid) -> bool {
+    !crate::procfs::process_dir(pid).exists()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn process_exited(_pid: Pid) -> bool {
+    false
+}
+
+// Потоки — записи в /proc/PID/task; если каталог не читается, процесс не проверяется
+#[cfg(target_os = "linux")]
+fn count_threads(pid: Pid) -> Option<usize> {
+    std::fs::read_dir(crate::procfs::process_file(pid, "task")).ok().map(|entries| entries.count())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn count_threads(_pid: Pid) -> Option<usize> {
+    None
+}
+
+// Своп процесса — строка "VmSwap: N kB" в /proc/PID/status. У потоков ядра её нет: своп 0
+#[cfg(target_os = "linux")]
+fn read_swap(pid: Pid) -> Option<u64> {
+    let status = std::fs::read_to_string(crate::procfs::process_file(pid, "status")).ok()?;
+    let Some(line) = status.lines().find_map(|line| line.strip_prefix("VmSwap:")) else {
+        return Some(0);
+    };
+    let kib: u64 = line.trim().strip_suffix("kB")?.trim().parse().ok()?;
+    Some(kib * 1024)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_swap(_pid: Pid) -> Option<u64> {
+    None
+}
+
+// Где ядро держит процесс — /proc/PID/wchan. "0" — процесс не спит или ядро адрес скрывает
+#[cfg(target_os = "linux")]
+fn read_wchan(pid: Pid) -> Option<String> {
+    let wchan = std::fs::read_to_string(crate::procfs::process_file(pid, "wchan")).ok()?;
+    let wchan = wchan.trim();
+    (!wchan.is_empty() && wchan != "0").then(|| wchan.to_string())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_wchan(_pid: Pid) -> Option<String> {
+    None
+}
+
+// Время CPU с запуска — utime + stime (поля 14 и 15) из /proc/PID/stat, в тиках ядра. Имя
+// процесса в скобках может содержать пробелы и скобки, поэтому поля считаются после последней ')'
+#[cfg(target_os = "linux")]
+fn read_cpu_time(pid: Pid) -> Option<Duration> {
+    let stat = std::fs::read_to_string(crate::procfs::process_file(pid, "stat")).ok()?;
+    let mut fields = stat.get(stat.rfind(')')? + 1..)?.split_whitespace();
+    let utime: u64 = fields.nth(11)?.parse().ok()?;
+    let stime: u64 = fields.next()?.parse().ok()?;
+    let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    (ticks > 0).then(|| Duration::from_secs_f64((utime + stime) as f64 / ticks as f64))
+}
+
+// sysinfo 0.30 накопленного времени CPU не отдаёт
+#[cfg(not(target_os = "linux"))]
+fn read_cpu_time(_pid: Pid) -> Option<Duration> {
+    None
+}
+
+// /proc/PID/environ — переменные KEY=VALUE через нулевой байт. Окружение чужих процессов
+// читается только от root; окружение на момент запуска, setenv внутри процесса не видно
+#[cfg(target_os = "linux")]
+fn read_environ(pid: Pid) -> Option<Vec<(String, String)>> {
+    let content = std::fs::read(crate::procfs::process_file(pid, "environ")).ok()?;
+    Some(
+        content
+            .split(|&b| b == 0)
+            .filter_map(|entry| {
+                let entry = String::from_utf8_lossy(entry);
+                let (key, value) = entry.split_once('=')?;
+                Some((key.to_string(), value.to_string()))
+            })
+            .collect(),
+    )
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_environ(_pid: Pid) -> Option<Vec<(String, String)>> {
+    None
+}
+
+impl ProcessSource for System {
+    fn processes(&self) -> Box<dyn Iterator<Item = (&Pid, &dyn ProcessView)> + '_> {
+        Box::new(System::processes(self).iter().map(|(pid, process)| (pid, process as &dyn ProcessView)))
+    }
+
+    fn process(&self, pid: Pid) -> Option<&dyn ProcessView> {
+        System::process(self, pid).map(|process| process as &dyn ProcessView)
+    }
+
+    fn total_memory(&self) -> u64 {
+        System::total_memory(self)
+    }
+
+    fn total_swap(&self) -> u64 {
+        System::total_swap(self)
+    }
+
+    fn cpu_count(&self) -> usize {
+        self.cpus().len()
+    }
+}
+
+/// Процесс с заданными вручную значениями, для [`CannedProcesses`]. Всё, что у настоящего
+/// процесса читается из /proc (окружение, дескрипторы, своп, время CPU, контейнер, сеть),
+/// берётся из полей, так что проверка с ним не зависит от процессов на машине. Действия
+/// правил (`enable_actions`) по-прежнему шлют сигналы настоящему PID — в тестах их не включают.
+#[derive(Clone, Debug)]
+pub struct CannedProcess {
+    pub pid: Pid,
+    pub name: String,
+    pub cmdline: Option<String>,
+    pub cpu_usage: f32,
+    pub memory: u64,
+    pub start_time: u64,
+    pub parent: Option<Pid>,
+    pub status: ProcessStatus,
+    pub user_id: Option<Uid>,
+    pub disk_usage: DiskUsage,
+    /// Процесс завершился между обновлением списка и чтением его файлов.
+    pub exited: bool,
+    pub exe: Option<String>,
+    pub environ: Option<Vec<(String, String)>>,
+    pub open_files: Option<usize>,
+    pub threads: Option<usize>,
+    pub swap: Option<u64>,
+    pub wchan: Option<String>,
+    pub cpu_time: Option<Duration>,
+    pub container_id: Option<String>,
+    pub net_namespace: Option<String>,
+    pub net_traffic: Option<(u64, u64)>,
+}
+
+impl CannedProcess {
+    /// Работающий процесс без родителя и владельца; командная строка — имя, память 0,
+    /// время запуска неизвестно (`MIN_PROCESS_AGE_SECONDS` его не отсеивает), окружение
+    /// пустое, остального (дескрипторы, своп, время CPU, контейнер, сеть) нет.
+    pub fn new(pid: u32, name: &str, cpu_usage: f32) -> Self {
+        CannedProcess {
+            pid: Pid::from_u32(pid),
+            name: name.to_string(),
+            cmdline: Some(name.to_string()),
+            cpu_usage,
+            memory: 0,
+            start_time: 0,
+            parent: None,
+            status: ProcessStatus::Run,
+            user_id: None,
+            disk_usage: DiskUsage::default(),
+            exited: false,
+            exe: None,
+            environ: Some(Vec::new()),
+            open_files: None,
+            threads: None,
+            swap: None,
+            wchan: None,
+            cpu_time: None,
+            container_id: None,
+            net_namespace: None,
+            net_traffic: None,
+        }
+    }
+}
+
+impl ProcessView for CannedProcess {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn cmdline(&self) -> io::Result<Option<String>> {
+        Ok(self.cmdline.clone())
+    }
+
+    fn cpu_usage(&self) -> f32 {
+        self.cpu_usage
+    }
+
+    fn memory(&self) -> u64 {
+        self.memory
+    }
+
+    fn start_time(&self) -> u64 {
+        self.start_time
+    }
+
+    fn parent(&self) -> Option<Pid> {
+        self.parent
+    }
+
+    fn status(&self) -> ProcessStatus {
+        self.status
+    }
+
+    fn user_id(&self) -> Option<&Uid> {
+        self.user_id.as_ref()
+    }
+
+    fn disk_usage(&self) -> DiskUsage {
+        self.disk_usage
+    }
+
+    fn exited(&self) -> bool {
+        self.exited
+    }
+
+    fn exe(&self) -> Option<String> {
+        self.exe.clone()
+    }
+
+    fn environ(&self) -> Option<Vec<(String, String)>> {
+        self.environ.clone()
+    }
+
+    fn open_files(&self) -> Option<usize> {
+        self.open_files
+    }
+
+    fn threads(&self) -> Option<usize> {
+        self.threads
+    }
+
+    fn swap(&self) -> Option<u64> {
+        self.swap
+    }
+
+    fn wchan(&self) -> Option<String> {
+        self.wchan.clone()
+    }
+
+    fn cpu_time(&self) -> Option<Duration> {
+        self.cpu_time
+    }
+
+    fn container_id(&self) -> Option<String> {
+        self.container_id.clone()
+    }
+
+    fn net_namespace(&self) -> Option<String> {
+        self.net_namespace.clone()
+    }
+
+    fn net_traffic(&self) -> Option<(u64, u64)> {
+        self.net_traffic
+    }
+}
+
+/// Список процессов в памяти вместо [`sysinfo::System`]: тест собирает процессы, меняет их
+/// CPU и память между вызовами [`crate::Watcher::check`] и смотрит, когда сработает порог.
+/// Вместе с подменёнными часами ([`crate::Watcher::set_clock`]) так проверяются и
+/// `SUSTAINED_SECONDS`, и кулдауны.
+#[derive(Clone, Debug)]
+pub struct CannedProcesses {
+    pub processes: HashMap<Pid, CannedProcess>,
+    pub total_memory: u64,
+    pub total_swap: u64,
+    pub cpu_count: usize,
+}
+
+impl CannedProcesses {
+    /// Пустой список на машине с `cpu_count` ядрами и `total_memory` байт RAM, без свопа.
+    pub fn new(cpu_count: usize, total_memory: u64) -> Self {
+        CannedProcesses {
+            processes: HashMap::new(),
+            total_memory,
+            total_swap: 0,
+            cpu_count,
+        }
+    }
+
+    /// Добавляет процесс или заменяет процесс с тем же PID.
+    pub fn insert(&mut self, process: CannedProcess) {
+        self.processes.insert(process.pid, process);
+    }
+
+    /// Процесс для правки между проверками, например `get_mut(pid).unwrap().cpu_usage = 95.0`.
+    pub fn get_mut(&mut self, pid: u32) -> Option<&mut CannedProcess> {
+        self.processes.get_mut(&Pid::from_u32(pid))
+    }
+
+    /// Убирает процесс, как будто он завершился.
+    pub fn remove(&mut self, pid: u32) -> Option<CannedProcess> {
+        self.processes.remove(&Pid::from_u32(pid))
+    }
+}
+
+impl ProcessSource for CannedProcesses {
+    fn processes(&self) -> Box<dyn Iterator<Item = (&Pid, &dyn ProcessView)> + '_> {
+        Box::new(self.processes.iter().map(|(pid, process)| (pid, process as &dyn ProcessView)))
+    }
+
+    fn process(&self, pid: Pid) -> Option<&dyn ProcessView> {
+        self.processes.get(&pid).map(|process| process as &dyn ProcessView)
+    }
+
+    fn total_memory(&self) -> u64 {
+        self.total_memory
+    }
+
+    fn total_swap(&self) -> u64 {
+        self.total_swap
+    }
+
+    fn cpu_count(&self) -> usize {
+        self.cpu_count
+    }
+}
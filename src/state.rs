@@ -0,0 +1,135 @@
+use cpu_watcher::{AlertKind, Cooldown, DedupKey};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime};
+use sysinfo::{Pid, System};
+
+// Запись о недавнем оповещении: по PID (start_time нужен, чтобы переиспользованный PID
+// не унаследовал кулдаун чужого процесса), по командной строке или по имени
+#[derive(Serialize, Deserialize)]
+struct StateEntry {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pid: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    start_time: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cmdline: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    kind: AlertKind,
+    alerted_at: u64,
+    // В старых файлах серии нет: такой кулдаун начинается с обычной длительности
+    #[serde(default)]
+    streak: u32,
+}
+
+impl StateEntry {
+    fn key(self, sys: &System) -> Option<DedupKey> {
+        match (self.pid, self.cmdline, self.name) {
+            (Some(pid), _, _) => {
+                let pid = Pid::from_u32(pid);
+                let alive = sys
+                    .process(pid)
+                    .is_some_and(|process| Some(process.start_time()) == self.start_time);
+                alive.then_some(DedupKey::Pid(pid))
+            }
+            (None, Some(cmdline), _) => Some(DedupKey::Cmdline(cmdline)),
+            (None, None, Some(name)) => Some(DedupKey::Name(name)),
+            (None, None, None) => None,
+        }
+    }
+}
+
+// Кулдауны идут по монотонным часам, а Instant не переживает перезапуск, поэтому в файл
+// пишется настенное время, пересчитанное через текущий момент
+fn to_unix_seconds(at: Instant) -> Option<u64> {
+    let wall = SystemTime::now().checked_sub(Instant::now().saturating_duration_since(at))?;
+    Some(wall.duration_since(SystemTime::UNIX_EPOCH).ok()?.as_secs())
+}
+
+// Запись из будущего (часы перевели назад) считается сделанной сейчас. Запись старше, чем
+// позволяют монотонные часы (на Linux — старше загрузки системы), выбрасывается
+fn from_unix_seconds(seconds: u64) -> Option<Instant> {
+    let wall = SystemTime::UNIX_EPOCH + Duration::from_secs(seconds);
+    let ago = SystemTime::now().duration_since(wall).unwrap_or_default();
+    Instant::now().checked_sub(ago)
+}
+
+pub fn load(path: &Path, sys: &System) -> HashMap<(DedupKey, AlertKind), Cooldown> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return HashMap::new(),
+        Err(e) => {
+            warn!("Cannot read state file {}: {}", path.display(), e);
+            return HashMap::new();
+        }
+    };
+    let entries: Vec<StateEntry> = match serde_json::from_str(&content) {
+        Ok(entries) => entries,
+        Err(e) => {
+            warn!("Ignoring malformed state file {}: {}", path.display(), e);
+            return HashMap::new();
+        }
+    };
+
+    let total = entries.len();
+    let alerted: HashMap<_, _> = entries
+        .into_iter()
+        .filter_map(|entry| {
+            let kind = entry.kind;
+            let cooldown = Cooldown {
+                alerted_at: from_unix_seconds(entry.alerted_at)?,
+                streak: entry.streak,
+            };
+            Some(((entry.key(sys)?, kind), cooldown))
+        })
+        .collect();
+
+    info!(
+        "Restored {} of {} cooldown entries from {}",
+        alerted.len(),
+        total,
+        path.display()
+    );
+    alerted
+}
+
+// Пишем во временный файл и переименовываем, чтобы падение посреди записи не испортило состояние
+pub fn save(path: &Path, alerted: &HashMap<(DedupKey, AlertKind), Cooldown>, sys: &System) -> io::Result<()> {
+    let entries: Vec<StateEntry> = alerted
+        .iter()
+        .filter_map(|((key, kind), cooldown)| {
+            let mut entry = StateEntry {
+                pid: None,
+                start_time: None,
+                cmdline: None,
+                name: None,
+                kind: *kind,
+                alerted_at: to_unix_seconds(cooldown.alerted_at)?,
+                streak: cooldown.streak,
+            };
+            match key {
+                DedupKey::Pid(pid) => {
+                    entry.pid = Some(pid.as_u32());
+                    entry.start_time = Some(sys.process(*pid)?.start_time());
+                }
+                DedupKey::Cmdline(cmdline) => entry.cmdline = Some(cmdline.clone()),
+                DedupKey::Name(name) => entry.name = Some(name.clone()),
+            }
+            Some(entry)
+        })
+        .collect();
+
+    let tmp_path = path.with_extension("tmp");
+    let mut file = fs::File::create(&tmp_path)?;
+    file.write_all(serde_json::to_string(&entries)?.as_bytes())?;
+    file.sync_all()?;
+    fs::rename(&tmp_path, path)?;
+
+    info!("Saved {} cooldown entries to {}", entries.len(), path.display());
+    Ok(())
+}
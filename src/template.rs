@@ -0,0 +1,179 @@
+use crate::markup::Markup;
+
+// Значения, которые можно подставить в шаблон сообщения
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Field {
+    Headline,
+    Severity,
+    Name,
+    Exe,
+    Env,
+    Pid,
+    Rule,
+    Cpu,
+    Aggregate,
+    Threshold,
+    Mode,
+    Trend,
+    CpuTime,
+    Mem,
+    Disk,
+    Net,
+    OpenFiles,
+    Threads,
+    Swap,
+    DState,
+    Score,
+    OverLimit,
+    Age,
+    Action,
+    Started,
+    Container,
+    Cmd,
+    System,
+}
+
+const FIELDS: [(&str, Field); 28] = [
+    ("headline", Field::Headline),
+    ("severity", Field::Severity),
+    ("name", Field::Name),
+    ("exe", Field::Exe),
+    ("env", Field::Env),
+    ("pid", Field::Pid),
+    ("rule", Field::Rule),
+    ("cpu", Field::Cpu),
+    ("aggregate", Field::Aggregate),
+    ("threshold", Field::Threshold),
+    ("mode", Field::Mode),
+    ("trend", Field::Trend),
+    ("cpu_time", Field::CpuTime),
+    ("mem", Field::Mem),
+    ("disk", Field::Disk),
+    ("net", Field::Net),
+    ("open_files", Field::OpenFiles),
+    ("threads", Field::Threads),
+    ("swap", Field::Swap),
+    ("d_state", Field::DState),
+    ("score", Field::Score),
+    ("over_limit", Field::OverLimit),
+    ("age", Field::Age),
+    ("action", Field::Action),
+    ("started", Field::Started),
+    ("container", Field::Container),
+    ("cmd", Field::Cmd),
+    ("system", Field::System),
+];
+
+// Раскладка сообщения по умолчанию
+pub const DEFAULT_TEMPLATE: &str = "{headline}
+Name: {name}
+Exe: {exe}
+Env: {env}
+PID: {pid}
+Rule: {rule}
+CPU: {cpu}% ({mode})
+{aggregate}
+Trend: {trend}
+CPU time: {cpu_time}
+Mem: {mem}
+Disk: {disk}
+Net: {net}
+Open files: {open_files}
+Threads: {threads}
+Swap: {swap}
+D state: {d_state}
+Score: {score}
+Over limit: {over_limit}
+Age: {age}
+Action: {action}
+Started: {started}
+Container: {container}
+Cmd: {cmd}
+{system}";
+
+enum Segment {
+    Text(String),
+    Field(Field),
+}
+
+// Разобранный MESSAGE_TEMPLATE. Строка, в которой все подстановки пустые (нет правила,
+// действия и т.п.), выбрасывается целиком
+pub struct Template {
+    lines: Vec<Vec<Segment>>,
+}
+
+impl Template {
+    // "{{" и "}}" — литеральные скобки, "\n" в значении из окружения — перевод строки
+    pub fn parse(template: &str) -> Result<Self, String> {
+        let template = template.replace("\\n", "\n");
+        let mut lines = Vec::new();
+        for line in template.split('\n') {
+            let mut segments = Vec::new();
+            let mut text = String::new();
+            let mut chars = line.chars().peekable();
+            while let Some(c) = chars.next() {
+                match c {
+                    '{' if chars.peek() == Some(&'{') => {
+                        chars.next();
+                        text.push('{');
+                    }
+                    '}' if chars.peek() == Some(&'}') => {
+                        chars.next();
+                        text.push('}');
+                    }
+                    '{' => {
+                        let mut name = String::new();
+                        loop {
+                            match chars.next() {
+                                Some('}') => break,
+                                Some(c) => name.push(c),
+                                None => return Err(format!("unclosed placeholder {{{} in message template", name)),
+                            }
+                        }
+                        let field = FIELDS
+                            .iter()
+                            .find(|(key, _)| *key == name)
+                            .map(|(_, field)| *field)
+                            .ok_or_else(|| format!("unknown placeholder {{{}}} in message template", name))?;
+                        if !text.is_empty() {
+                            segments.push(Segment::Text(std::mem::take(&mut text)));
+                        }
+                        segments.push(Segment::Field(field));
+                    }
+                    '}' => return Err("unmatched '}' in message template (use '}}')".to_string()),
+                    c => text.push(c),
+                }
+            }
+            if !text.is_empty() {
+                segments.push(Segment::Text(text));
+            }
+            lines.push(segments);
+        }
+        Ok(Template { lines })
+    }
+
+    // value возвращает уже оформленное значение поля; литеральный текст экранируется здесь
+    pub fn render(&self, markup: Markup, value: impl Fn(Field) -> String) -> String {
+        let mut out = Vec::new();
+        for segments in &self.lines {
+            let mut line = String::new();
+            let mut has_fields = false;
+            let mut has_values = false;
+            for segment in segments {
+                match segment {
+                    Segment::Text(text) => line.push_str(&markup.escape(text)),
+                    Segment::Field(field) => {
+                        has_fields = true;
+                        let value = value(*field);
+                        has_values |= !value.is_empty();
+                        line.push_str(&value);
+                    }
+                }
+            }
+            if !has_fields || has_values {
+                out.push(line);
+            }
+        }
+        out.join("\n")
+    }
+}
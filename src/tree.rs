@@ -0,0 +1,92 @@
+use std::collections::{HashMap, HashSet};
+use crate::source::ProcessSource;
+use sysinfo::Pid;
+
+// Глубже этого дерево не обходим: защищает от патологических цепочек parent()
+const MAX_DEPTH: usize = 32;
+
+// Дети каждого процесса по ссылкам parent() — для суммарного CPU процесса и его потомков
+pub struct ProcessTree {
+    children: HashMap<Pid, Vec<Pid>>,
+}
+
+impl ProcessTree {
+    pub fn new(sys: &dyn ProcessSource) -> Self {
+        ProcessTree::from_parents(
+            sys.processes()
+                .filter_map(|(pid, process)| Some((*pid, process.parent()?))),
+        )
+    }
+
+    // Пары (PID, PID родителя)
+    pub fn from_parents(parents: impl IntoIterator<Item = (Pid, Pid)>) -> Self {
+        let mut children: HashMap<Pid, Vec<Pid>> = HashMap::new();
+        for (pid, parent) in parents {
+            if pid != parent {
+                children.entry(parent).or_default().push(pid);
+            }
+        }
+        ProcessTree { children }
+    }
+
+    // Все потомки root не глубже MAX_DEPTH; visited не даёт зациклиться
+    pub fn descendants(&self, root: Pid) -> Vec<Pid> {
+        let mut found = Vec::new();
+        let mut visited = HashSet::from([root]);
+        let mut level = vec![root];
+        for _ in 0..MAX_DEPTH {
+            let next: Vec<Pid> = level
+                .iter()
+                .filter_map(|pid| self.children.get(pid))
+                .flatten()
+                .copied()
+                .filter(|child| visited.insert(*child))
+                .collect();
+            if next.is_empty() {
+                break;
+            }
+            found.extend_from_slice(&next);
+            level = next;
+        }
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pid(value: u32) -> Pid {
+        Pid::from_u32(value)
+    }
+
+    fn sorted(mut pids: Vec<Pid>) -> Vec<u32> {
+        pids.sort();
+        pids.into_iter().map(|pid| pid.as_u32()).collect()
+    }
+
+    #[test]
+    fn collects_all_levels() {
+        let tree = ProcessTree::from_parents([(pid(2), pid(1)), (pid(3), pid(1)), (pid(4), pid(2)), (pid(5), pid(9))]);
+        assert_eq!(sorted(tree.descendants(pid(1))), [2, 3, 4]);
+        assert!(tree.descendants(pid(4)).is_empty());
+    }
+
+    #[test]
+    fn survives_a_cycle() {
+        // 1 → 2 → 3 → 1 и процесс, записанный своим же родителем
+        let tree = ProcessTree::from_parents([(pid(2), pid(1)), (pid(3), pid(2)), (pid(1), pid(3)), (pid(7), pid(7))]);
+        assert_eq!(sorted(tree.descendants(pid(1))), [2, 3]);
+        assert_eq!(sorted(tree.descendants(pid(2))), [1, 3]);
+        assert!(tree.descendants(pid(7)).is_empty());
+    }
+
+    #[test]
+    fn stops_at_max_depth() {
+        // Цепочка 1 → 2 → … → 100: глубже MAX_DEPTH уровней потомки не ищутся
+        let tree = ProcessTree::from_parents((2..=100).map(|child| (pid(child), pid(child - 1))));
+        let found = sorted(tree.descendants(pid(1)));
+        assert_eq!(found.len(), MAX_DEPTH);
+        assert_eq!(found.last(), Some(&(MAX_DEPTH as u32 + 1)));
+    }
+}
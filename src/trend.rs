@@ -0,0 +1,72 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use sysinfo::Pid;
+
+// Разница средних по половинам окна (в процентных пунктах), ниже которой CPU считается ровным
+const FLAT_DELTA: f32 = 2.0;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Trend {
+    pub delta: f32,
+    pub peak: f32,
+}
+
+impl Trend {
+    pub fn arrow(&self) -> &'static str {
+        if self.delta > FLAT_DELTA {
+            "↑"
+        } else if self.delta < -FLAT_DELTA {
+            "↓"
+        } else {
+            "→"
+        }
+    }
+}
+
+impl fmt::Display for Trend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (peak {:.1}%)", self.arrow(), self.peak)
+    }
+}
+
+// Последние TREND_WINDOW замеров CPU по каждому PID
+pub struct CpuHistory {
+    window: usize,
+    samples: HashMap<Pid, VecDeque<f32>>,
+}
+
+impl CpuHistory {
+    pub fn new(window: usize) -> Self {
+        CpuHistory {
+            window,
+            samples: HashMap::new(),
+        }
+    }
+
+    pub fn record(&mut self, pid: Pid, cpu: f32) {
+        if self.window == 0 {
+            return;
+        }
+        let samples = self.samples.entry(pid).or_default();
+        if samples.len() == self.window {
+            samples.pop_front();
+        }
+        samples.push_back(cpu);
+    }
+
+    // Сравнивает среднее старой и новой половины окна; нужно хотя бы два замера
+    pub fn trend(&self, pid: Pid) -> Option<Trend> {
+        let samples = self.samples.get(&pid).filter(|samples| samples.len() >= 2)?;
+        let half = samples.len() / 2;
+        let older = samples.iter().take(half).sum::<f32>() / half as f32;
+        let newer = samples.iter().rev().take(half).sum::<f32>() / half as f32;
+        Some(Trend {
+            delta: newer - older,
+            peak: samples.iter().copied().fold(0.0, f32::max),
+        })
+    }
+
+    pub fn retain(&mut self, mut alive: impl FnMut(&Pid) -> bool) {
+        self.samples.retain(|pid, _| alive(pid));
+    }
+}
@@ -0,0 +1,142 @@
+use cpu_watcher::config::Config;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use sysinfo::{Pid, ProcessRefreshKind, System};
+
+// Что наблюдаем: конкретный PID или PID из файла (файл перечитывается каждую проверку,
+// чтобы подхватить перезапуск сервиса)
+pub enum WatchTarget {
+    Pid(Pid),
+    PidFile(PathBuf),
+}
+
+impl WatchTarget {
+    pub fn from_config(config: &Config) -> Option<WatchTarget> {
+        match (config.watch_pid, &config.watch_pidfile) {
+            (Some(pid), _) => Some(WatchTarget::Pid(Pid::from_u32(pid))),
+            (None, Some(path)) => Some(WatchTarget::PidFile(path.clone())),
+            (None, None) => None,
+        }
+    }
+
+    fn resolve(&self) -> Option<Pid> {
+        match self {
+            WatchTarget::Pid(pid) => Some(*pid),
+            WatchTarget::PidFile(path) => fs::read_to_string(path)
+                .ok()?
+                .trim()
+                .parse::<u32>()
+                .ok()
+                .map(Pid::from_u32),
+        }
+    }
+}
+
+impl fmt::Display for WatchTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WatchTarget::Pid(pid) => write!(f, "PID {}", pid),
+            WatchTarget::PidFile(path) => write!(f, "PID file {}", path.display()),
+        }
+    }
+}
+
+pub enum WatchEvent {
+    Started(Pid, String),
+    Exited(Pid, String),
+}
+
+pub struct PidWatch {
+    target: WatchTarget,
+    current: Option<(Pid, String)>,
+}
+
+impl PidWatch {
+    pub fn new(target: WatchTarget) -> Self {
+        PidWatch { target, current: None }
+    }
+
+    pub fn target(&self) -> &WatchTarget {
+        &self.target
+    }
+
+    // Обновляет наблюдаемый процесс с потомками и возвращает их PID вместе с событиями
+    // появления/завершения корневого процесса
+    pub fn refresh(&mut self, sys: &mut System, kind: ProcessRefreshKind) -> (HashSet<Pid>, Vec<WatchEvent>) {
+        let root = self.target.resolve();
+        let tree = match root {
+            Some(root) => refresh_tree(sys, root, kind),
+            None => HashSet::new(),
+        };
+        let alive = root
+            .filter(|root| tree.contains(root))
+            .and_then(|root| Some((root, sys.process(root)?.name().to_string())));
+
+        let mut events = Vec::new();
+        if self.current.as_ref().map(|(pid, _)| *pid) != alive.as_ref().map(|(pid, _)| *pid) {
+            if let Some((pid, name)) = self.current.take() {
+                events.push(WatchEvent::Exited(pid, name));
+            }
+            if let Some((pid, name)) = &alive {
+                events.push(WatchEvent::Started(*pid, name.clone()));
+            }
+            self.current = alive;
+        }
+        (tree, events)
+    }
+}
+
+fn collect_tree(root: Pid, children: impl Fn(Pid) -> Vec<Pid>) -> HashSet<Pid> {
+    let mut tree = HashSet::new();
+    let mut stack = vec![root];
+    while let Some(pid) = stack.pop() {
+        // Повторно встреченный PID не обходим — защита от циклов
+        if tree.insert(pid) {
+            stack.extend(children(pid));
+        }
+    }
+    tree
+}
+
+// Потомки из /proc/PID/task/*/children: обновляем в sysinfo только их, без полного обхода /proc
+#[cfg(target_os = "linux")]
+fn refresh_tree(sys: &mut System, root: Pid, kind: ProcessRefreshKind) -> HashSet<Pid> {
+    let mut tree = collect_tree(root, children_from_proc);
+    sys.refresh_pids_specifics(&tree.iter().copied().collect::<Vec<_>>(), kind);
+    tree.retain(|pid| sys.process(*pid).is_some());
+    tree
+}
+
+#[cfg(target_os = "linux")]
+fn children_from_proc(pid: Pid) -> Vec<Pid> {
+    let Ok(tasks) = fs::read_dir(cpu_watcher::procfs::process_file(pid, "task")) else {
+        return Vec::new();
+    };
+    tasks
+        .flatten()
+        .filter_map(|task| fs::read_to_string(task.path().join("children")).ok())
+        .flat_map(|children| {
+            children
+                .split_whitespace()
+                .filter_map(|child| child.parse::<u32>().ok())
+                .map(Pid::from_u32)
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn refresh_tree(sys: &mut System, root: Pid, kind: ProcessRefreshKind) -> HashSet<Pid> {
+    sys.refresh_processes_specifics(kind);
+    let mut children: std::collections::HashMap<Pid, Vec<Pid>> = std::collections::HashMap::new();
+    for (pid, process) in sys.processes() {
+        if let Some(parent) = process.parent() {
+            children.entry(parent).or_default().push(*pid);
+        }
+    }
+    let mut tree = collect_tree(root, |pid| children.get(&pid).cloned().unwrap_or_default());
+    tree.retain(|pid| sys.process(*pid).is_some());
+    tree
+}
@@ -0,0 +1,77 @@
+use crate::config::Config;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Instant;
+use sysinfo::{Pid, ProcessStatus, System};
+
+/// Родитель, который не забирает завершившихся потомков, и его зомби.
+pub struct ZombieParent {
+    /// None — родитель неизвестен (зомби без PPID).
+    pub pid: Option<Pid>,
+    pub name: String,
+    pub zombies: Vec<Pid>,
+}
+
+/// Зомби в системе не меньше `zombie_threshold`; в `parents` — только родители, о которых
+/// ещё не оповещали в пределах кулдауна.
+pub struct ZombieAlert {
+    pub total: usize,
+    pub parents: Vec<ZombieParent>,
+}
+
+/// Подсчёт зомби по уже обновлённому списку процессов. Кулдаун ведётся по PID родителя:
+/// один и тот же незабирающий родитель не шлёт оповещение на каждой проверке.
+pub struct ZombieMonitor {
+    threshold: usize,
+    cooldown_seconds: u64,
+    alerted: HashMap<Option<Pid>, Instant>,
+}
+
+impl ZombieMonitor {
+    /// None, если `zombie_threshold` не задан.
+    pub fn new(config: &Config) -> Option<ZombieMonitor> {
+        Some(ZombieMonitor {
+            threshold: config.zombie_threshold?,
+            cooldown_seconds: config.cooldown_seconds,
+            alerted: HashMap::new(),
+        })
+    }
+
+    /// Оповещение, если зомби не меньше порога и среди их родителей есть новые.
+    pub fn check(&mut self, sys: &System, now: Instant) -> Option<ZombieAlert> {
+        // BTreeMap — родители в сообщении всегда в одном порядке
+        let mut by_parent: BTreeMap<Option<Pid>, Vec<Pid>> = BTreeMap::new();
+        for (pid, process) in sys.processes() {
+            if process.status() == ProcessStatus::Zombie {
+                by_parent.entry(process.parent()).or_default().push(*pid);
+            }
+        }
+        // Родитель, у которого зомби больше нет, при следующем появлении оповестит сразу
+        self.alerted.retain(|parent, _| by_parent.contains_key(parent));
+        let total = by_parent.values().map(Vec::len).sum();
+        if total == 0 || total < self.threshold {
+            return None;
+        }
+        let parents: Vec<ZombieParent> = by_parent
+            .into_iter()
+            .filter(|(parent, _)| match self.alerted.get(parent) {
+                Some(last) => now.saturating_duration_since(*last).as_secs() >= self.cooldown_seconds,
+                None => true,
+            })
+            .map(|(parent, mut zombies)| {
+                zombies.sort();
+                let name = parent
+                    .and_then(|pid| sys.process(pid))
+                    .map_or_else(|| "?".to_string(), |process| process.name().to_string());
+                ZombieParent { pid: parent, name, zombies }
+            })
+            .collect();
+        (!parents.is_empty()).then_some(ZombieAlert { total, parents })
+    }
+
+    /// Запоминает отправленное оповещение: до конца кулдауна эти родители не повторятся.
+    pub fn mark_alerted(&mut self, alert: &ZombieAlert, now: Instant) {
+        for parent in &alert.parents {
+            self.alerted.insert(parent.pid, now);
+        }
+    }
+}